@@ -0,0 +1,93 @@
+use crate::board::Board;
+use crate::search::Searcher;
+use std::time::{Duration, Instant};
+
+/// Depth each position is searched to when no depth is given
+pub const DEFAULT_BENCH_DEPTH: u8 = 5;
+
+/// Positions searched by the benchmark, covering openings, middlegames and endgames
+const BENCH_POSITIONS: [&str; 20] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+    "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP3PPP/R2QKB1R w KQ - 0 8",
+    "rnbqkb1r/pp3ppp/4pn2/2pp4/3P4/2PBPN2/PP3PPP/RNBQK2R b KQkq - 1 5",
+    "r2q1rk1/pp2ppbp/2p2np1/6B1/3PP1b1/Q1P2N2/P4PPP/3RKB1R b K - 0 13",
+    "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1",
+    "r1bbk1nr/pp3p1p/2n5/1N4p1/2Np1B2/8/PPP2PPP/2KR1B1R w kq - 0 13",
+    "3r1rk1/p5pp/bpp1pp2/8/q1PP1P2/b3P3/P2NQRPP/1R2B1K1 b - - 6 22",
+    "r1q2rk1/2p1bppp/2Pp4/p6b/Q1PNp3/4B3/PP1R1PPP/2K4R w - - 2 18",
+    "4k2r/1pb2ppp/1p2p3/1R1p4/3P4/2r1PN2/P4PPP/1R4K1 b - - 3 22",
+    "3q2k1/pb3p1p/4pbp1/2r5/PpN2N2/1P2P2P/5PP1/Q2R2K1 b - - 4 26",
+    "6k1/6p1/6Pp/ppp5/3pn2P/1P3K2/1PP2P2/3N4 b - - 0 1",
+    "3b4/5kp1/1p1p1p1p/pP1PpP1P/P1P1P3/3KN3/8/8 w - - 0 1",
+    "8/6pk/1p6/8/PP3p1p/5P2/4KP1q/3Q4 w - - 0 1",
+    "8/8/8/8/5kp1/P7/8/1K1N4 w - - 0 1",
+];
+
+/// Totals from a benchmark run
+pub struct BenchResult {
+    pub nodes: u64,
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    /// Nodes searched per second
+    pub fn nps(&self) -> u64 {
+        let ms = self.elapsed.as_millis().max(1) as u64;
+        self.nodes * 1000 / ms
+    }
+}
+
+/// Searches every benchmark position to a fixed depth.
+///
+/// The searcher is reset before each position, so the node count only changes
+/// when the search itself does. That makes it a quick check that a change
+/// meant to be a speedup doesn't alter the search.
+///
+/// # Arguments
+/// * `searcher` - Searcher to run
+/// * `depth` - Depth to search each position to
+pub fn run_bench(searcher: &mut Searcher, depth: u8) -> BenchResult {
+    let start = Instant::now();
+    let mut nodes = 0;
+
+    for fen in BENCH_POSITIONS {
+        searcher.reset();
+        searcher.find_best_move(&Board::new(fen), depth, None);
+        nodes += searcher.nodes();
+    }
+
+    searcher.reset();
+    BenchResult {
+        nodes,
+        elapsed: start.elapsed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_positions_are_valid() {
+        for fen in BENCH_POSITIONS {
+            assert!(Board::from_fen(fen).is_ok(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_bench_node_count_is_stable() {
+        let mut searcher = Searcher::new();
+
+        let first = run_bench(&mut searcher, 2);
+        let second = run_bench(&mut searcher, 2);
+
+        assert!(first.nodes > 0);
+        assert_eq!(first.nodes, second.nodes);
+    }
+}
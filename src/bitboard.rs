@@ -27,10 +27,8 @@ pub const FILE_F: Bitboard = FILE_E << 1;
 pub const FILE_G: Bitboard = FILE_F << 1;
 pub const FILE_H: Bitboard = FILE_G << 1;
 
-pub const WHITE_KING_SIDE: Bitboard = 0x0000000000000060;
-pub const WHITE_QUEEN_SIDE: Bitboard = 0x000000000000000E;
-pub const BLACK_KING_SIDE: Bitboard = 0x6000000000000000;
-pub const BLACK_QUEEN_SIDE: Bitboard = 0x0E00000000000000;
+pub const LIGHT_SQUARES: Bitboard = 0x55AA55AA55AA55AA;
+pub const DARK_SQUARES: Bitboard = !LIGHT_SQUARES;
 
 pub trait BitboardOperations {
     fn shift(&self, n: i8) -> Bitboard;
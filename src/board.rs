@@ -1,8 +1,9 @@
-use crate::bitboard::{Bitboard, BitboardOperations};
-use crate::fen::fen_to_board;
-use crate::moves::{Move, MoveType};
+use crate::bitboard::{Bitboard, BitboardOperations, DARK_SQUARES, LIGHT_SQUARES};
+use crate::fen::{board_to_fen, fen_to_board, FenError};
+use crate::move_gen::{GameStatus, MoveGenerator};
+use crate::moves::{Move, MoveError, MoveType};
 use crate::pieces::{Color, ColorIterator, Piece, PieceIterator, COLOR_COUNT, PIECE_COUNT};
-use crate::square::{Square, A1, A8, D1, D8, F1, F8, G1, G8, H1, H8};
+use crate::square::{rank_file_to_square, Square, C1, C8, D1, D8, F1, F8, G1, G8};
 
 // Represents the chess board using bitboards
 #[derive(Copy, Clone)]
@@ -11,37 +12,128 @@ pub struct Board {
     pub active_color: Color,
     pub castling_ability: Castle,
     pub en_passant_target: Option<Square>,
-
-    #[allow(dead_code)]
     pub halfmove_clock: u8,
-    #[allow(dead_code)]
-    pub fullmove_counter: u8,
+    pub fullmove_counter: u16,
 }
 
 impl Board {
+    // Creates a board from a FEN, panicking if it is malformed. Prefer from_fen for untrusted input
+    #[allow(dead_code)]
     pub fn new(fen: &str) -> Self {
-        match fen_to_board(fen) {
+        match Self::from_fen(fen) {
             Ok(board) => board,
-            Err(err) => {
-                println!("Error constructing FEN: {}", err);
-                println!("Setting board to default values");
-                Self::default()
-            }
+            Err(err) => panic!("Invalid FEN '{}': {}", fen, err),
         }
     }
 
-    // Creates the default board state
-    pub fn default() -> Self {
+    // Creates a board from a FEN, returning an error describing why it is malformed
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        fen_to_board(fen)
+    }
+
+    /// Creates a board with no pieces, White to move and no castling rights.
+    ///
+    /// Pieces and the rest of the state can then be set up with `set_piece` and
+    /// the other setters, as an alternative to writing a FEN.
+    pub fn empty() -> Self {
         Self {
-            position: Position::default(),
+            position: Position::new(),
             active_color: Color::White,
-            castling_ability: Castle::new(true, true, true, true),
+            castling_ability: Castle::new(false, false, false, false),
             en_passant_target: None,
             halfmove_clock: 0,
             fullmove_counter: 1,
         }
     }
 
+    /// Puts a piece on a square, replacing whatever was there
+    pub fn set_piece(&mut self, color: Color, piece: Piece, square: Square) {
+        if let (Some(old_color), Some(old_piece)) =
+            (self.get_color_at(square), self.get_piece_at(square))
+        {
+            self.remove_piece(old_color, old_piece, square);
+        }
+
+        self.add_piece(color, piece, square);
+    }
+
+    pub fn set_active_color(&mut self, color: Color) {
+        self.active_color = color;
+    }
+
+    /// Sets whether a side can still castle king side and queen side
+    pub fn set_castling_rights(&mut self, color: Color, king_side: bool, queen_side: bool) {
+        let (king_castle, queen_castle) = match color {
+            Color::White => ('K', 'Q'),
+            Color::Black => ('k', 'q'),
+        };
+
+        self.castling_ability.set(king_castle, king_side);
+        self.castling_ability.set(queen_castle, queen_side);
+    }
+
+    /// Sets the square a pawn skipped over with a double push, if any
+    pub fn set_en_passant(&mut self, square: Option<Square>) {
+        self.en_passant_target = square;
+    }
+
+    // Writes the board as a FEN
+    pub fn to_fen(&self) -> String {
+        board_to_fen(self)
+    }
+
+    /// Flips the board vertically and swaps the colors, along with the side to
+    /// move, castling rights and en passant square.
+    ///
+    /// The result is the same position seen from the other side, so anything
+    /// that should treat both colors alike, like the evaluation, can be checked
+    /// against it.
+    pub fn mirror(&self) -> Board {
+        let mut mirrored = *self;
+
+        // Reversing the bytes of a bitboard reverses the order of its ranks
+        let pieces = &self.position.pieces;
+        let colors = &self.position.colors;
+        mirrored.position = Position {
+            pieces: pieces.map(Bitboard::swap_bytes),
+            colors: [
+                colors[Color::Black].swap_bytes(),
+                colors[Color::White].swap_bytes(),
+            ],
+        };
+
+        for color in ColorIterator::new() {
+            let (king_side, queen_side) = self.castling_ability(color);
+            mirrored.set_castling_rights(!color, king_side, queen_side);
+
+            for king_side in [true, false] {
+                let file = self.castling_ability.rook_file(color, king_side);
+                mirrored
+                    .castling_ability
+                    .set_rook_file(!color, king_side, file);
+            }
+        }
+
+        mirrored.active_color = !self.active_color;
+        mirrored.en_passant_target = self.en_passant_target.map(|square| square ^ 56);
+        mirrored
+    }
+
+    /// Lists the legal moves in this position.
+    ///
+    /// This is the supported way to get moves when using the engine as a library.
+    /// It uses a shared move generator, so the lookup tables are only built by
+    /// the first call.
+    #[allow(dead_code)]
+    pub fn legal_moves(&self) -> Vec<Move> {
+        MoveGenerator::shared().generate_moves(self)
+    }
+
+    /// Adjudicates this position, see `MoveGenerator::game_status`
+    pub fn game_status(&self, position_history: &[u64]) -> GameStatus {
+        MoveGenerator::shared().game_status(self, position_history)
+    }
+
     // Returns select pieces of a certain color e.g. white pawns
     pub fn bb(&self, color: Color, piece: Piece) -> Bitboard {
         self.position.bb(color, piece)
@@ -62,6 +154,16 @@ impl Board {
         self.active_color
     }
 
+    /// The square of the rook a side castles with on the king side or queen side
+    pub fn castling_rook(&self, color: Color, king_side: bool) -> Square {
+        let rank = match color {
+            Color::White => 0,
+            Color::Black => 7,
+        };
+
+        rank_file_to_square(rank, self.castling_ability.rook_file(color, king_side))
+    }
+
     pub fn castling_ability(&self, color: Color) -> (bool, bool) {
         match color {
             Color::White => (
@@ -85,6 +187,29 @@ impl Board {
         self.bb_color(Color::White) | self.bb_color(Color::Black)
     }
 
+    /// Checks if neither side has enough material to ever checkmate: bare kings,
+    /// a single minor piece, or only bishops that all stand on one square color
+    pub fn is_insufficient_material(&self) -> bool {
+        let heavy_or_pawns =
+            self.bb_piece(Piece::Pawn) | self.bb_piece(Piece::Rook) | self.bb_piece(Piece::Queen);
+        if heavy_or_pawns != 0 {
+            return false;
+        }
+
+        let knights = self.bb_piece(Piece::Knight);
+        let bishops = self.bb_piece(Piece::Bishop);
+        if (knights | bishops).count_ones() <= 1 {
+            return true;
+        }
+
+        knights == 0 && (bishops & LIGHT_SQUARES == 0 || bishops & DARK_SQUARES == 0)
+    }
+
+    /// Checks if fifty moves by each side have passed without a capture or pawn move
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.halfmove_clock >= 100
+    }
+
     pub fn add_piece(&mut self, color: Color, piece: Piece, square: Square) {
         self.position.add_piece(color, piece, square);
     }
@@ -103,7 +228,26 @@ impl Board {
         self.active_color = !self.active_color;
     }
 
+    /// Plays a move given in coordinate notation (e.g. `e2e4`) after checking it's legal.
+    ///
+    /// The board is left unchanged if the move can't be played.
+    ///
+    /// # Returns
+    /// The move played
+    pub fn try_make_uci(&mut self, s: &str, move_gen: &MoveGenerator) -> Result<Move, MoveError> {
+        let mv =
+            Move::from_algebraic(s, self).ok_or_else(|| MoveError::Malformed(s.to_string()))?;
+
+        if !move_gen.is_legal_move(self, &mv) {
+            return Err(MoveError::Illegal(s.to_string()));
+        }
+
+        self.make_move(&mv);
+        Ok(mv)
+    }
+
     pub fn make_move(&mut self, mv: &Move) {
+        self.update_move_counters(mv);
         self.reset_en_passant_target();
         self.change_castling_rights(mv);
 
@@ -118,6 +262,26 @@ impl Board {
         self.change_color();
     }
 
+    // Must be called before the move is made and the active color changes
+    fn update_move_counters(&mut self, mv: &Move) {
+        // Promotions are pawn moves, but their piece type is the promoted piece
+        let is_irreversible = mv.piece_type == Piece::Pawn
+            || matches!(
+                mv.move_type,
+                MoveType::Capture | MoveType::EnPassant | MoveType::Promotion
+            );
+
+        if is_irreversible {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+        }
+
+        if self.active_color == Color::Black {
+            self.fullmove_counter = self.fullmove_counter.saturating_add(1);
+        }
+    }
+
     fn reset_en_passant_target(&mut self) {
         self.en_passant_target = None;
     }
@@ -132,20 +296,7 @@ impl Board {
 
         // Moving the rook when the rights for its side are set removes them
         if mv.piece_type == Piece::Rook {
-            let (king_side_rights, queen_side_rights) = self.castling_ability(color);
-            let (rook_on_king_side, rook_on_queen_side) = match color {
-                Color::White => (mv.from == H1, mv.from == A1),
-                Color::Black => (mv.from == H8, mv.from == A8),
-            };
-
-            if rook_on_king_side && king_side_rights {
-                self.castling_ability.remove_side_rights(color, Piece::King);
-            }
-
-            if rook_on_queen_side && queen_side_rights {
-                self.castling_ability
-                    .remove_side_rights(color, Piece::Queen);
-            }
+            self.remove_rook_rights(color, mv.from);
         }
 
         // Capturing a rook when its rights are set removes them
@@ -154,21 +305,7 @@ impl Board {
             let captured_piece = self.get_piece_at(mv.to).unwrap();
 
             if captured_piece == Piece::Rook {
-                let (king_side_rights, queen_side_rights) = self.castling_ability(!color);
-                let (rook_on_king_side, rook_on_queen_side) = match color {
-                    Color::White => (mv.to == H8, mv.to == A8),
-                    Color::Black => (mv.to == H1, mv.to == A1),
-                };
-
-                if rook_on_king_side && king_side_rights {
-                    self.castling_ability
-                        .remove_side_rights(!color, Piece::King);
-                }
-
-                if rook_on_queen_side && queen_side_rights {
-                    self.castling_ability
-                        .remove_side_rights(!color, Piece::Queen);
-                }
+                self.remove_rook_rights(!color, mv.to);
             }
         }
 
@@ -176,25 +313,25 @@ impl Board {
         if mv.move_type == MoveType::Promotion {
             // A promotion could be a push of a pawn and not a capture, so this can be None
             if let Some(Piece::Rook) = self.get_piece_at(mv.to) {
-                let (king_side_rights, queen_side_rights) = self.castling_ability(!color);
-                let (rook_on_king_side, rook_on_queen_side) = match color {
-                    Color::White => (mv.to == H8, mv.to == A8),
-                    Color::Black => (mv.to == H1, mv.to == A1),
-                };
-
-                if rook_on_king_side && king_side_rights {
-                    self.castling_ability
-                        .remove_side_rights(!color, Piece::King);
-                }
-
-                if rook_on_queen_side && queen_side_rights {
-                    self.castling_ability
-                        .remove_side_rights(!color, Piece::Queen);
-                }
+                self.remove_rook_rights(!color, mv.to);
             }
         }
     }
 
+    // Removes a side's rights to castle with the rook on a square, if it's one of its castling rooks
+    fn remove_rook_rights(&mut self, color: Color, square: Square) {
+        let (king_side_rights, queen_side_rights) = self.castling_ability(color);
+
+        if king_side_rights && square == self.castling_rook(color, true) {
+            self.castling_ability.remove_side_rights(color, Piece::King);
+        }
+
+        if queen_side_rights && square == self.castling_rook(color, false) {
+            self.castling_ability
+                .remove_side_rights(color, Piece::Queen);
+        }
+    }
+
     fn make_quiet(&mut self, mv: &Move) {
         let color = self.active_color;
 
@@ -223,41 +360,23 @@ impl Board {
 
     fn make_en_passant(&mut self, mv: &Move) {
         let color = self.active_color;
-        let offset = match color {
-            Color::White => 8,
-            Color::Black => -8,
-        };
-
-        let captured_square = (mv.to as i8 - offset) as u8;
+        let captured_square = en_passant_capture_square(color, mv.to);
 
         self.remove_piece(!color, Piece::Pawn, captured_square);
         self.remove_piece(color, Piece::Pawn, mv.from);
         self.add_piece(color, Piece::Pawn, mv.to)
     }
 
+    // Castles are encoded as the king capturing its own rook, so the king and rook
+    // destinations only depend on the side castled to
     fn make_castle(&mut self, mv: &Move) {
         let color = self.active_color;
-
-        let is_king_side = match color {
-            Color::White => G1 == mv.to,
-            Color::Black => G8 == mv.to,
-        };
-
-        let (rook_from, rook_to) = match is_king_side {
-            true => match color {
-                Color::White => (H1, F1),
-                Color::Black => (H8, F8),
-            },
-            false => match color {
-                Color::White => (A1, D1),
-                Color::Black => (A8, D8),
-            },
-        };
+        let (king_to, rook_to) = castling_destinations(color, mv.to > mv.from);
 
         self.remove_piece(color, Piece::King, mv.from);
-        self.add_piece(color, Piece::King, mv.to);
+        self.remove_piece(color, Piece::Rook, mv.to);
 
-        self.remove_piece(color, Piece::Rook, rook_from);
+        self.add_piece(color, Piece::King, king_to);
         self.add_piece(color, Piece::Rook, rook_to);
     }
 
@@ -278,7 +397,6 @@ impl Board {
         PieceIterator::new().find(|&piece| self.bb_piece(piece) & square_bb != 0)
     }
 
-    #[allow(dead_code)]
     pub fn get_color_at(&self, square: Square) -> Option<Color> {
         let square_bb = Bitboard::square_to_bitboard(square);
 
@@ -300,6 +418,20 @@ impl Board {
     }
 }
 
+impl Default for Board {
+    // Creates the default board state
+    fn default() -> Self {
+        Self {
+            position: Position::default(),
+            active_color: Color::White,
+            castling_ability: Castle::new(true, true, true, true),
+            en_passant_target: None,
+            halfmove_clock: 0,
+            fullmove_counter: 1,
+        }
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct Position {
     pieces: [Bitboard; PIECE_COUNT], // Six bitboards for the pieces
@@ -358,12 +490,45 @@ impl Position {
     }
 }
 
+/// Where the king and rook end up after castling, which is the same in standard
+/// chess and Chess960
+pub fn castling_destinations(color: Color, king_side: bool) -> (Square, Square) {
+    match (color, king_side) {
+        (Color::White, true) => (G1, F1),
+        (Color::White, false) => (C1, D1),
+        (Color::Black, true) => (G8, F8),
+        (Color::Black, false) => (C8, D8),
+    }
+}
+
+/// The square of the pawn a pawn of `color` takes en passant by moving to `target`,
+/// which is the square just behind the target
+pub fn en_passant_capture_square(color: Color, target: Square) -> Square {
+    // Targets are on the sixth rank for White and the third for Black, so the
+    // square behind them is always on the board
+    let rank = target / 8;
+    debug_assert!(
+        (color == Color::White && rank == 5) || (color == Color::Black && rank == 2),
+        "{} is not an en passant target for {:?}",
+        target,
+        color
+    );
+
+    match color {
+        Color::White => target - 8,
+        Color::Black => target + 8,
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct Castle {
     white_king: bool,
     white_queen: bool,
     black_king: bool,
     black_queen: bool,
+    // Files the castling rooks start on for each color, king side first.
+    // Only Chess960 positions start them anywhere but the h and a files
+    rook_files: [[u8; 2]; COLOR_COUNT],
 }
 
 impl Castle {
@@ -373,9 +538,19 @@ impl Castle {
             white_queen,
             black_king,
             black_queen,
+            rook_files: [[7, 0]; COLOR_COUNT],
         }
     }
 
+    /// The file the rook castled with on one side starts on
+    pub fn rook_file(&self, color: Color, king_side: bool) -> u8 {
+        self.rook_files[color.index()][!king_side as usize]
+    }
+
+    pub fn set_rook_file(&mut self, color: Color, king_side: bool, file: u8) {
+        self.rook_files[color.index()][!king_side as usize] = file;
+    }
+
     pub fn set(&mut self, castle: char, ability: bool) {
         match castle {
             'K' => self.white_king = ability,
@@ -414,3 +589,175 @@ impl Castle {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::square::algebraic_to_square;
+
+    #[test]
+    fn test_legal_moves() {
+        assert_eq!(Board::default().legal_moves().len(), 20);
+
+        // Only the king can move out of check
+        let board = Board::new("7k/8/8/8/8/8/7P/r6K w - - 0 1");
+        let moves: Vec<String> = board
+            .legal_moves()
+            .into_iter()
+            .map(Move::to_algebraic)
+            .collect();
+        assert_eq!(moves, vec!["h1g2"]);
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        let insufficient = [
+            "8/8/4k3/8/8/3K4/8/8 w - - 0 1",
+            "8/8/4k3/8/8/3KN3/8/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/8/5b2 w - - 0 1",
+            // Bishops on the same square color
+            "8/2b5/4k3/8/8/3K4/8/2B5 w - - 0 1",
+        ];
+        let sufficient = [
+            "8/8/4k3/8/8/3K4/4P3/8 w - - 0 1",
+            "8/8/4k3/8/8/3KR3/8/8 w - - 0 1",
+            "8/8/4k3/8/8/3KNN2/8/8 w - - 0 1",
+            "8/8/4k3/8/8/3KBn2/8/8 w - - 0 1",
+            // Bishops on opposite square colors
+            "8/8/2b1k3/8/8/3K4/8/2B5 w - - 0 1",
+        ];
+
+        for fen in insufficient {
+            assert!(Board::new(fen).is_insufficient_material(), "{}", fen);
+        }
+        for fen in sufficient {
+            assert!(!Board::new(fen).is_insufficient_material(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_build_position_piece_by_piece() {
+        let mut board = Board::empty();
+        board.set_piece(Color::White, Piece::King, algebraic_to_square("e1"));
+        board.set_piece(Color::White, Piece::Rook, algebraic_to_square("h1"));
+        board.set_piece(Color::White, Piece::Pawn, algebraic_to_square("e5"));
+        board.set_piece(Color::Black, Piece::King, algebraic_to_square("e8"));
+        board.set_piece(Color::Black, Piece::Rook, algebraic_to_square("a8"));
+        board.set_piece(Color::Black, Piece::Pawn, algebraic_to_square("d5"));
+        board.set_castling_rights(Color::White, true, false);
+        board.set_castling_rights(Color::Black, false, true);
+        board.set_en_passant(Some(algebraic_to_square("d6")));
+
+        assert_eq!(board.to_fen(), "r3k3/8/8/3pP3/8/8/8/4K2R w Kq d6 0 1");
+        assert_eq!(
+            board.legal_moves(),
+            Board::new(&board.to_fen()).legal_moves()
+        );
+
+        // Setting a piece replaces the one already there
+        board.set_piece(Color::Black, Piece::Queen, algebraic_to_square("h1"));
+        board.set_active_color(Color::Black);
+        assert_eq!(board.to_fen(), "r3k3/8/8/3pP3/8/8/8/4K2q b Kq d6 0 1");
+        assert_eq!(board.bb_all().count_ones(), 6);
+    }
+
+    #[test]
+    fn test_mirror() {
+        let pairs = [
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w Kq - 3 12",
+                "r3k2r/pppbbppp/2n2q1P/1P2p3/3pn3/BN2PNP1/P1PPQPB1/R3K2R b Qk - 3 12",
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3",
+                "rnbqkbnr/pppp1ppp/8/3Pp3/8/8/PPP1PPPP/RNBQKBNR w KQkq e6 0 3",
+            ),
+        ];
+
+        for (fen, mirrored_fen) in pairs {
+            let board = Board::new(fen);
+            let mirrored = board.mirror();
+
+            assert_eq!(mirrored.to_fen(), mirrored_fen);
+            assert_eq!(mirrored.mirror().to_fen(), fen);
+            assert_eq!(Board::new(&mirrored.to_fen()).to_fen(), mirrored_fen);
+
+            // Both sides have the same moves, just mirrored
+            let moves = MoveGenerator::shared();
+            assert_eq!(moves.run_perft(&board, 2), moves.run_perft(&mirrored, 2));
+        }
+    }
+
+    #[test]
+    fn test_fifty_move_draw() {
+        assert!(!Board::new("8/8/4k3/8/8/3KR3/8/8 w - - 99 80").is_fifty_move_draw());
+        assert!(Board::new("8/8/4k3/8/8/3KR3/8/8 w - - 100 80").is_fifty_move_draw());
+    }
+
+    #[test]
+    fn test_try_make_uci() {
+        let move_gen = MoveGenerator::shared();
+        let mut board = Board::new("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1");
+
+        assert_eq!(
+            board.try_make_uci("e1e9", move_gen),
+            Err(MoveError::Malformed("e1e9".to_string()))
+        );
+        // Taking the undefended checking rook is legal
+        assert_eq!(
+            board.try_make_uci("e1e2", move_gen).map(Move::to_algebraic),
+            Ok("e1e2".to_string())
+        );
+
+        let mut board = Board::new("4k3/8/8/8/8/8/3r4/4K3 w - - 0 1");
+        assert_eq!(
+            board.try_make_uci("e1d1", move_gen),
+            Err(MoveError::Illegal("e1d1".to_string()))
+        );
+        assert_eq!(board.active_color(), Color::White);
+        assert!(board.try_make_uci("e1d2", move_gen).is_ok());
+        assert_eq!(board.active_color(), Color::Black);
+    }
+
+    #[test]
+    fn test_move_counters() {
+        let mut board = Board::default();
+
+        // Knight moves count towards the fifty-move rule, pawn moves and captures reset it
+        for (mv, counters) in [
+            ("g1f3", "1 1"),
+            ("g8f6", "2 2"),
+            ("f3g1", "3 2"),
+            ("e7e5", "0 3"),
+            ("b1c3", "1 3"),
+            ("f8b4", "2 4"),
+            ("c3d5", "3 4"),
+            ("f6d5", "0 5"),
+        ] {
+            board.make_move(&Move::from_algebraic(mv, &board).unwrap());
+            assert!(
+                board.to_fen().ends_with(&format!(" {}", counters)),
+                "{}",
+                mv
+            );
+        }
+    }
+
+    #[test]
+    fn test_rook_moves_and_captures_remove_castling_rights() {
+        // Capturing the rook on h8 takes away Black's king side castle
+        let board = Board::new("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        let after = board.clone_with_move(&Move::from_algebraic("h1h8", &board).unwrap());
+        assert_eq!(after.to_fen(), "r3k2R/8/8/8/8/8/8/R3K3 b Qq - 0 1");
+
+        // A promotion capturing the rook on a8 takes away Black's queen side castle
+        let board = Board::new("r3k2r/1P6/8/8/8/8/8/4K3 w kq - 0 1");
+        let after = board.clone_with_move(&Move::from_algebraic("b7a8q", &board).unwrap());
+        assert_eq!(after.to_fen(), "Q3k2r/8/8/8/8/8/8/4K3 b k - 0 1");
+
+        // Moving a rook off its square only takes away its own side's castle
+        let board = Board::new("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        let after = board.clone_with_move(&Move::from_algebraic("a1a2", &board).unwrap());
+        assert_eq!(after.to_fen(), "r3k2r/8/8/8/8/8/R7/4K2R b Kkq - 1 1");
+    }
+}
@@ -0,0 +1,191 @@
+use crate::board::Board;
+use crate::fen::FenError;
+use crate::moves::Move;
+use crate::search::Searcher;
+use crate::timer::TimeLimits;
+use std::fmt;
+
+/// Number of fields at the start of an EPD line that describe the position
+const POSITION_FIELDS: usize = 4;
+
+/// Reasons an EPD line can fail to parse
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EpdError {
+    InvalidPosition(FenError),
+    MissingBestMove,
+    InvalidBestMove(String),
+}
+
+impl fmt::Display for EpdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EpdError::InvalidPosition(err) => write!(f, "invalid position: {}", err),
+            EpdError::MissingBestMove => write!(f, "missing bm operation"),
+            EpdError::InvalidBestMove(san) => write!(f, "best move '{}' is not legal", san),
+        }
+    }
+}
+
+/// A test position from an EPD suite
+#[derive(Clone)]
+pub struct EpdEntry {
+    pub board: Board,
+    /// Playing any of these moves solves the position
+    pub best_moves: Vec<Move>,
+    pub id: Option<String>,
+}
+
+/// Parses an EPD line like `<position> bm Qxf7+; id "name";`
+///
+/// The position is the first four fields of a FEN. Of the operations only `bm`,
+/// which is required, and `id` are used, others are ignored.
+///
+/// # Arguments
+/// * `line` - The EPD line
+pub fn parse_epd(line: &str) -> Result<EpdEntry, EpdError> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let position_end = fields.len().min(POSITION_FIELDS);
+
+    let board =
+        Board::from_fen(&fields[..position_end].join(" ")).map_err(EpdError::InvalidPosition)?;
+
+    let operations = fields[position_end..].join(" ");
+    let mut best_moves = None;
+    let mut id = None;
+
+    for operation in operations.split(';').map(str::trim) {
+        let (opcode, operands) = operation.split_once(' ').unwrap_or((operation, ""));
+
+        match opcode {
+            "bm" => {
+                let moves = operands
+                    .split_whitespace()
+                    .map(|san| {
+                        Move::from_san(san, &board)
+                            .ok_or_else(|| EpdError::InvalidBestMove(san.to_string()))
+                    })
+                    .collect::<Result<Vec<Move>, EpdError>>()?;
+                best_moves = Some(moves);
+            }
+            "id" => id = Some(operands.trim().trim_matches('"').to_string()),
+            _ => {
+                // Ignore operations that aren't needed to run a suite
+            }
+        }
+    }
+
+    match best_moves {
+        Some(best_moves) if !best_moves.is_empty() => Ok(EpdEntry {
+            board,
+            best_moves,
+            id,
+        }),
+        _ => Err(EpdError::MissingBestMove),
+    }
+}
+
+/// Searches a position and checks whether the engine plays one of its best moves.
+///
+/// The searcher is reset first so earlier positions don't affect the result.
+///
+/// # Arguments
+/// * `searcher` - Searcher to run
+/// * `entry` - The position to solve
+/// * `depth` - Maximum search depth
+/// * `time_limits` - Optional time limits for the search
+pub fn solves(
+    searcher: &mut Searcher,
+    entry: &EpdEntry,
+    depth: u8,
+    time_limits: Option<TimeLimits>,
+) -> bool {
+    searcher.reset();
+    let (_, best_move) = searcher.find_best_move(&entry.board, depth, time_limits);
+
+    best_move.is_some_and(|mv| entry.best_moves.contains(&mv))
+}
+
+/// Runs every position of a suite, printing whether each was solved.
+///
+/// # Arguments
+/// * `searcher` - Searcher to run
+/// * `entries` - The suite's positions
+/// * `depth` - Maximum search depth for each position
+/// * `time_limits` - Optional time limits for each position
+///
+/// # Returns
+/// The number of positions solved
+pub fn run_suite(
+    searcher: &mut Searcher,
+    entries: &[EpdEntry],
+    depth: u8,
+    time_limits: Option<TimeLimits>,
+) -> usize {
+    let mut solved = 0;
+
+    for (idx, entry) in entries.iter().enumerate() {
+        let result = if solves(searcher, entry, depth, time_limits) {
+            solved += 1;
+            "solved"
+        } else {
+            "failed"
+        };
+
+        let id = entry.id.clone().unwrap_or_else(|| (idx + 1).to_string());
+        println!("info string epd {} {}", id, result);
+    }
+
+    println!("info string epd solved {} of {}", solved, entries.len());
+    solved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_best_moves_and_id() {
+        let entry =
+            parse_epd("4k3/5p2/8/6B1/8/8/8/3R2K1 w - - bm Rd8+ Bd8; id \"back rank\";").unwrap();
+
+        let best_moves: Vec<String> = entry
+            .best_moves
+            .into_iter()
+            .map(Move::to_algebraic)
+            .collect();
+        assert_eq!(best_moves, vec!["d1d8", "g5d8"]);
+        assert_eq!(entry.id.as_deref(), Some("back rank"));
+    }
+
+    #[test]
+    fn test_rejects_invalid_lines() {
+        assert!(matches!(
+            parse_epd("4k3/5p2/8/6B1/8/8/8/3R2K1 x - - bm Rd8+;"),
+            Err(EpdError::InvalidPosition(_))
+        ));
+        assert_eq!(
+            parse_epd("4k3/5p2/8/6B1/8/8/8/3R2K1 w - - id \"no moves\";").err(),
+            Some(EpdError::MissingBestMove)
+        );
+        assert_eq!(
+            parse_epd("4k3/5p2/8/6B1/8/8/8/3R2K1 w - - bm Rd9;").err(),
+            Some(EpdError::InvalidBestMove("Rd9".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_runs_suite() {
+        let entries: Vec<EpdEntry> = [
+            "4k3/5p2/8/6B1/8/8/8/3R2K1 w - - bm Rd8#; id \"back rank\";",
+            "7k/6pp/8/8/8/8/6PP/R5K1 w - - bm Ra8#; id \"corner\";",
+            // Not the best move, so this one fails
+            "7k/6pp/8/8/8/8/6PP/R5K1 w - - bm Kf1; id \"wrong\";",
+        ]
+        .iter()
+        .map(|line| parse_epd(line).unwrap())
+        .collect();
+
+        let mut searcher = Searcher::new();
+        assert_eq!(run_suite(&mut searcher, &entries, 3, None), 2);
+    }
+}
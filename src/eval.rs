@@ -1,10 +1,18 @@
-use crate::bitboard::{BitboardIterator, SQUARES};
+use crate::bitboard::{
+    Bitboard, BitboardIterator, BitboardOperations, DARK_SQUARES, FILES, FILE_A, LIGHT_SQUARES,
+    RANKS, RANK_1, RANK_2, RANK_3, RANK_4, RANK_5, RANK_6, RANK_7, RANK_8, SQUARES,
+};
 use crate::board::Board;
-use crate::pieces::{Color, Piece, PIECE_COUNT};
+use crate::eval_cache::EvalCache;
+use crate::lookup::generate_king_lookup_table;
+use crate::move_gen::MoveGenerator;
+use crate::moves::{EAST, NORTH, SOUTH, WEST};
+use crate::pieces::{Color, ColorIterator, Piece, PieceIterator, COLOR_COUNT, PIECE_COUNT};
+use crate::square::{square_to_file, square_to_rank, Square};
 
-type PST = [i32; SQUARES as usize];
+type Pst = [i32; SQUARES as usize];
 
-const OPENING_TABLES: [PST; PIECE_COUNT] = [
+const OPENING_TABLES: [Pst; PIECE_COUNT] = [
     // Pawn (82 + positional)
     [
         82, 82, 82, 82, 82, 82, 82, 82, 180, 216, 143, 177, 150, 208, 116, 71, 76, 89, 108, 113,
@@ -50,7 +58,7 @@ const OPENING_TABLES: [PST; PIECE_COUNT] = [
     ],
 ];
 
-const ENDGAME_TABLES: [PST; PIECE_COUNT] = [
+const ENDGAME_TABLES: [Pst; PIECE_COUNT] = [
     // Pawn (94 + positional)
     [
         94, 94, 94, 94, 94, 94, 94, 94, 272, 267, 252, 228, 241, 226, 259, 281, 188, 194, 179, 161,
@@ -94,83 +102,708 @@ const ENDGAME_TABLES: [PST; PIECE_COUNT] = [
     ],
 ];
 
+/// Default opening piece values, already included in `OPENING_TABLES`
+pub const DEFAULT_OPENING_VALUES: [i32; PIECE_COUNT] = [82, 337, 365, 477, 1025, 0];
+
+/// Default endgame piece values, already included in `ENDGAME_TABLES`
+pub const DEFAULT_ENDGAME_VALUES: [i32; PIECE_COUNT] = [94, 281, 297, 512, 936, 0];
+
+/// Manhattan distance from each square to the nearest of the four center squares
+#[rustfmt::skip]
+const CENTER_DISTANCE: Pst = [
+    6, 5, 4, 3, 3, 4, 5, 6,
+    5, 4, 3, 2, 2, 3, 4, 5,
+    4, 3, 2, 1, 1, 2, 3, 4,
+    3, 2, 1, 0, 0, 1, 2, 3,
+    3, 2, 1, 0, 0, 1, 2, 3,
+    4, 3, 2, 1, 1, 2, 3, 4,
+    5, 4, 3, 2, 2, 3, 4, 5,
+    6, 5, 4, 3, 3, 4, 5, 6,
+];
+
+/// Mop-up bonus per step the lone king is pushed away from the center
+const MOP_UP_CENTER_WEIGHT: i32 = 10;
+
+/// Mop-up bonus per step the attacking king is closer to the lone king
+const MOP_UP_PROXIMITY_WEIGHT: i32 = 4;
+
+/// Largest Manhattan distance between two squares
+const MAX_MANHATTAN_DISTANCE: i32 = 14;
+
+/// Opening and endgame bonus for a knight on an outpost
+const KNIGHT_OUTPOST_BONUS: (i32, i32) = (25, 15);
+
+/// Opening and endgame bonus for a bishop on an outpost
+const BISHOP_OUTPOST_BONUS: (i32, i32) = (12, 6);
+
+/// Opening and endgame bonus for each pair of friendly pawns side by side
+const PHALANX_BONUS: (i32, i32) = (8, 6);
+
+/// Bonus for a passed pawn connected to another passed pawn, by rank counted
+/// from the pawn's own side. Scaled by `CONNECTED_PASSER_SCALE` for each phase
+const CONNECTED_PASSER_BONUS: [i32; RANKS as usize] = [0, 5, 10, 15, 30, 55, 90, 0];
+
+/// Opening and endgame penalty for a pawn that's fallen behind its neighbours
+/// and can't advance without being taken by an enemy pawn
+const BACKWARD_PAWN_PENALTY: (i32, i32) = (8, 12);
+
+/// Opening and endgame penalty for each group of pawns on adjacent files beyond the first
+const PAWN_ISLAND_PENALTY: (i32, i32) = (5, 10);
+
+/// Opening and endgame bonus for a rook on the enemy's second rank
+const ROOK_ON_SEVENTH_BONUS: (i32, i32) = (10, 20);
+
+/// Opening and endgame bonus for a rook on the enemy's second rank when it
+/// attacks pawns there or cuts the enemy king off on its back rank
+const ROOK_ON_SEVENTH_ACTIVE_BONUS: (i32, i32) = (20, 40);
+
+/// The enemy's second and back ranks, indexed by color
+const SEVENTH_RANKS: [Bitboard; COLOR_COUNT] = [RANK_7, RANK_2];
+const EIGHTH_RANKS: [Bitboard; COLOR_COUNT] = [RANK_8, RANK_1];
+
+/// Ranks in the enemy half where a piece can sit on an outpost, indexed by color
+const OUTPOST_RANKS: [Bitboard; COLOR_COUNT] = [RANK_4 | RANK_5 | RANK_6, RANK_5 | RANK_4 | RANK_3];
+
+/// Attack units each piece adds per square of the enemy king zone it attacks
+/// (Pawn, Knight, Bishop, Rook, Queen, King)
+const KING_ATTACK_WEIGHTS: [usize; PIECE_COUNT] = [0, 2, 2, 3, 5, 0];
+
+/// Fewest pieces that have to attack the king zone before it counts as an attack
+const MIN_KING_ATTACKERS: u32 = 2;
+
+/// Opening penalty by attack units. It grows slowly for a few attackers and
+/// much faster once enough pieces join in, as one attacker is rarely dangerous
+#[rustfmt::skip]
+const KING_SAFETY_TABLE: [i32; 100] = [
+      0,   0,   1,   2,   3,   5,   7,   9,  12,  15,
+     18,  22,  26,  30,  35,  39,  44,  50,  56,  62,
+     68,  75,  82,  85,  89,  97, 105, 113, 122, 131,
+    140, 150, 169, 180, 191, 202, 213, 225, 237, 248,
+    260, 272, 283, 295, 307, 319, 330, 342, 354, 366,
+    377, 389, 401, 412, 424, 436, 448, 459, 471, 483,
+    494, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+];
+
+/// Default opening and endgame percentages of `CONNECTED_PASSER_BONUS` applied
+const CONNECTED_PASSER_SCALE: (i32, i32) = (50, 100);
+
+/// Default opening and endgame percentages of `KING_SAFETY_TABLE` applied
+const KING_SAFETY_SCALE: (i32, i32) = (100, 0);
+
+/// Evaluation terms whose weights can be tuned without recompiling
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EvalWeight {
+    KnightOutpost,
+    BishopOutpost,
+    Phalanx,
+    /// Percentage of the per-rank connected passed pawn bonus
+    ConnectedPasser,
+    BackwardPawn,
+    PawnIsland,
+    RookOnSeventh,
+    ActiveRookOnSeventh,
+    /// Percentage of the king safety table's penalty
+    KingSafety,
+}
+
+/// Every tunable evaluation weight, in the order they are stored
+pub const EVAL_WEIGHTS: [EvalWeight; 9] = [
+    EvalWeight::KnightOutpost,
+    EvalWeight::BishopOutpost,
+    EvalWeight::Phalanx,
+    EvalWeight::ConnectedPasser,
+    EvalWeight::BackwardPawn,
+    EvalWeight::PawnIsland,
+    EvalWeight::RookOnSeventh,
+    EvalWeight::ActiveRookOnSeventh,
+    EvalWeight::KingSafety,
+];
+
+impl EvalWeight {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Whether the weight is a percentage of a bonus rather than a bonus of its own
+    fn is_percentage(self) -> bool {
+        matches!(self, EvalWeight::ConnectedPasser | EvalWeight::KingSafety)
+    }
+
+    /// The weight's (opening, endgame) value before any tuning
+    pub fn default_value(self) -> (i32, i32) {
+        match self {
+            EvalWeight::KnightOutpost => KNIGHT_OUTPOST_BONUS,
+            EvalWeight::BishopOutpost => BISHOP_OUTPOST_BONUS,
+            EvalWeight::Phalanx => PHALANX_BONUS,
+            EvalWeight::ConnectedPasser => CONNECTED_PASSER_SCALE,
+            EvalWeight::BackwardPawn => BACKWARD_PAWN_PENALTY,
+            EvalWeight::PawnIsland => PAWN_ISLAND_PENALTY,
+            EvalWeight::RookOnSeventh => ROOK_ON_SEVENTH_BONUS,
+            EvalWeight::ActiveRookOnSeventh => ROOK_ON_SEVENTH_ACTIVE_BONUS,
+            EvalWeight::KingSafety => KING_SAFETY_SCALE,
+        }
+    }
+}
+
+impl std::fmt::Display for EvalWeight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
 const PHASE_INCREMENTS: [i32; PIECE_COUNT] = [0, 1, 1, 2, 4, 0];
 
+/// Scale factor that leaves the evaluation unchanged
+const SCALE_NORMAL: i32 = 64;
+
+/// Scale factor for opposite-colored bishop endings with no other pieces
+const SCALE_PURE_OPPOSITE_BISHOPS: i32 = 32;
+
+/// Scale factor for opposite-colored bishop endings that still have rooks or queens
+const SCALE_OPPOSITE_BISHOPS: i32 = 48;
+
+/// Most pawns a side can have for opposite-colored bishops to be considered drawish
+const OPPOSITE_BISHOPS_MAX_PAWNS: u32 = 5;
+
+/// Game phase of the starting position, where the position is fully an opening
+pub const MAX_PHASE: i32 = 24;
+
+/// Calculates the game phase from the remaining non-pawn material.
+///
+/// Ranges from `MAX_PHASE` with all pieces on the board down to 0 with only kings and pawns.
+/// Pawns don't count, as trading them doesn't make a position more of an endgame
+/// the way trading pieces does. Promotions can add more material than the starting
+/// position has, so the phase is capped at `MAX_PHASE`; removing material never
+/// raises it.
+pub fn game_phase(board: &Board) -> i32 {
+    let phase: i32 = PieceIterator::new()
+        .map(|piece| board.bb_piece(piece).count_ones() as i32 * PHASE_INCREMENTS[piece.index()])
+        .sum();
+
+    phase.min(MAX_PHASE)
+}
+
 pub struct Evaluator {
     gamephase: i32,
     opening_score: i32,
     endgame_score: i32,
+    opening_values: [i32; PIECE_COUNT],
+    endgame_values: [i32; PIECE_COUNT],
+    opening_tables: [Pst; PIECE_COUNT],
+    endgame_tables: [Pst; PIECE_COUNT],
+    /// Opening and endgame values of the tunable terms, indexed by `EvalWeight`
+    weights: [(i32, i32); EVAL_WEIGHTS.len()],
+    /// Squares enemy pawns could attack each square from, indexed by color then square
+    pawn_attack_spans: [[Bitboard; SQUARES as usize]; COLOR_COUNT],
+    /// Squares enemy pawns would have to be on to stop a pawn, indexed by color then square
+    passed_pawn_spans: [[Bitboard; SQUARES as usize]; COLOR_COUNT],
+    /// The king's square and the squares around it, indexed by the king's square
+    king_zones: [Bitboard; SQUARES as usize],
+    move_generator: &'static MoveGenerator,
+    cache: EvalCache,
 }
 
 impl Evaluator {
     pub fn new() -> Self {
-        Self {
+        let mut evaluator = Self {
             gamephase: 0,
             opening_score: 0,
             endgame_score: 0,
+            opening_values: DEFAULT_OPENING_VALUES,
+            endgame_values: DEFAULT_ENDGAME_VALUES,
+            opening_tables: OPENING_TABLES,
+            endgame_tables: ENDGAME_TABLES,
+            weights: EVAL_WEIGHTS.map(EvalWeight::default_value),
+            pawn_attack_spans: pawn_attack_spans(),
+            passed_pawn_spans: passed_pawn_spans(),
+            king_zones: king_zones(),
+            move_generator: MoveGenerator::shared(),
+            cache: EvalCache::new(),
+        };
+        evaluator.initialize_tables();
+        evaluator
+    }
+
+    /// Sets the opening and endgame value of a piece in centipawns
+    pub fn set_piece_value(&mut self, piece: Piece, opening: i32, endgame: i32) {
+        self.opening_values[piece.index()] = opening;
+        self.endgame_values[piece.index()] = endgame;
+        self.initialize_tables();
+
+        // Cached scores were computed with the old values
+        self.clear_cache();
+    }
+
+    /// Sets the opening and endgame value of a tunable evaluation term
+    pub fn set_weight(&mut self, weight: EvalWeight, opening: i32, endgame: i32) {
+        self.weights[weight.index()] = (opening, endgame);
+
+        // Cached scores were computed with the old weights
+        self.clear_cache();
+    }
+
+    /// Gets the opening and endgame value of a tunable evaluation term
+    pub fn weight(&self, weight: EvalWeight) -> (i32, i32) {
+        self.weights[weight.index()]
+    }
+
+    /// Removes all cached evaluations
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+
+    /// Gets the opening and endgame value of a piece in centipawns
+    pub fn piece_value(&self, piece: Piece) -> (i32, i32) {
+        (
+            self.opening_values[piece.index()],
+            self.endgame_values[piece.index()],
+        )
+    }
+
+    /// Rebuilds the piece-square tables so their base values match the current piece values
+    fn initialize_tables(&mut self) {
+        for piece in PieceIterator::new() {
+            let idx = piece.index();
+            let opening_delta = self.opening_values[idx] - DEFAULT_OPENING_VALUES[idx];
+            let endgame_delta = self.endgame_values[idx] - DEFAULT_ENDGAME_VALUES[idx];
+
+            for square in 0..SQUARES as usize {
+                self.opening_tables[idx][square] = OPENING_TABLES[idx][square] + opening_delta;
+                self.endgame_tables[idx][square] = ENDGAME_TABLES[idx][square] + endgame_delta;
+            }
         }
     }
 
+    /// Evaluates a position, reusing the cached score if it was evaluated before
+    ///
+    /// # Arguments
+    /// * `board` - Position to evaluate
+    /// * `hash` - Zobrist hash of the position
+    pub fn evaluate_cached(&mut self, board: &Board, hash: u64) -> i32 {
+        if let Some(score) = self.cache.probe(hash) {
+            return score;
+        }
+
+        let score = self.evaluate(board);
+        self.cache.store(hash, score);
+        score
+    }
+
+    /// Evaluates a position from the perspective of the side to move.
+    ///
+    /// This sums the terms directly instead of filling in an `EvalBreakdown`,
+    /// as it's called at every leaf of the search.
     pub fn evaluate(&mut self, board: &Board) -> i32 {
         self.reset();
 
         let active_color = board.active_color();
+        for color in ColorIterator::new() {
+            self.eval_color(color, board);
+        }
 
-        self.eval_piece_type(active_color, Piece::Pawn, board);
-        self.eval_piece_type(active_color, Piece::Knight, board);
-        self.eval_piece_type(active_color, Piece::Bishop, board);
-        self.eval_piece_type(active_color, Piece::Rook, board);
-        self.eval_piece_type(active_color, Piece::Queen, board);
-        self.eval_piece_type(active_color, Piece::King, board);
+        // The piece-square tables already include the material
+        let mut opening = self.opening_score;
+        let mut endgame = self.endgame_score;
 
-        let opening_phase = self.gamephase.min(24);
-        let endgame_phase = 24 - opening_phase;
+        let ours = self.term_counts(board, active_color);
+        let theirs = self.term_counts(board, !active_color);
+        for weight in EVAL_WEIGHTS {
+            let count = ours[weight.index()] - theirs[weight.index()];
+            let (term_opening, term_endgame) = self.weigh_term(weight, count);
+            opening += term_opening;
+            endgame += term_endgame;
+        }
+        endgame += self.mop_up(board, active_color) - self.mop_up(board, !active_color);
 
-        (self.opening_score * opening_phase + self.endgame_score * endgame_phase) / 24
+        tapered_score(opening, endgame, self.draw_scale(board), self.gamephase)
     }
 
-    fn eval_piece_type(&mut self, color: Color, piece: Piece, board: &Board) {
-        let piece_idx = piece.index();
-        let phase_inc = PHASE_INCREMENTS[piece_idx];
+    /// Checks that a position scores the same as its mirror with the colors
+    /// swapped, as every term should treat both sides alike
+    pub fn is_symmetric(&mut self, board: &Board) -> bool {
+        self.evaluate(board) == self.evaluate(&board.mirror())
+    }
 
-        // Active player
-        let player_bb = board.bb(color, piece);
-        let mut player_opening = 0;
-        let mut player_endgame = 0;
-        let mut player_count = 0;
+    /// Evaluates a position term by term, for debugging and tuning.
+    ///
+    /// Scores are from the perspective of the side to move, like `evaluate`.
+    pub fn evaluate_breakdown(&mut self, board: &Board) -> EvalBreakdown {
+        let features = self.extract_features(board);
+        self.weigh(&features)
+    }
 
-        let bitboard_iter = BitboardIterator::new(player_bb);
-        for bit in bitboard_iter {
-            let square = if color == Color::White { bit ^ 56 } else { bit };
+    /// Gets the raw terms of a position before any weights are applied, so a
+    /// tuner can fit the weights to game results.
+    ///
+    /// This also counts mobility, which the evaluation doesn't use yet, so it's
+    /// slower than `evaluate` and not meant for search.
+    pub fn features(&mut self, board: &Board) -> EvalFeatures {
+        let mut features = self.extract_features(board);
 
-            player_opening += OPENING_TABLES[piece_idx][square as usize];
-            player_endgame += ENDGAME_TABLES[piece_idx][square as usize];
-            player_count += phase_inc;
+        let active_color = board.active_color();
+        for piece in PieceIterator::new() {
+            features.mobility[piece.index()] = self.mobility(board, active_color, piece)
+                - self.mobility(board, !active_color, piece);
         }
+        features
+    }
 
-        // Opponent player
-        let opp_color = !color;
-        let opp_bb = board.bb(opp_color, piece);
-        let mut opp_opening = 0;
-        let mut opp_endgame = 0;
-        let mut opp_count = 0;
+    /// Applies the current piece values and weights to a position's features.
+    ///
+    /// `weigh(&features(board)).total()` is the same as `evaluate(board)`.
+    pub fn weigh(&self, features: &EvalFeatures) -> EvalBreakdown {
+        let mut material = (0, 0);
+        for piece in PieceIterator::new() {
+            let count = features.material[piece.index()];
+            material.0 += count * self.opening_values[piece.index()];
+            material.1 += count * self.endgame_values[piece.index()];
+        }
 
-        let bitboard_iter = BitboardIterator::new(opp_bb);
-        for bit in bitboard_iter {
-            let square = if opp_color == Color::White {
-                bit ^ 56
+        let term = |weight: EvalWeight| self.weigh_term(weight, features.terms[weight.index()]);
+
+        EvalBreakdown {
+            phase: features.phase,
+            material,
+            piece_squares: features.piece_squares,
+            outposts: add(
+                term(EvalWeight::KnightOutpost),
+                term(EvalWeight::BishopOutpost),
+            ),
+            pawn_structure: add(
+                add(term(EvalWeight::Phalanx), term(EvalWeight::ConnectedPasser)),
+                add(term(EvalWeight::BackwardPawn), term(EvalWeight::PawnIsland)),
+            ),
+            rooks_on_seventh: add(
+                term(EvalWeight::RookOnSeventh),
+                term(EvalWeight::ActiveRookOnSeventh),
+            ),
+            king_safety: term(EvalWeight::KingSafety),
+            mop_up: features.mop_up,
+            draw_scale: features.draw_scale,
+        }
+    }
+
+    /// Counts every term of a position except mobility, for tuning and breakdowns
+    fn extract_features(&mut self, board: &Board) -> EvalFeatures {
+        self.reset();
+
+        let active_color = board.active_color();
+        for color in ColorIterator::new() {
+            self.eval_color(color, board);
+        }
+
+        let mut material = [0; PIECE_COUNT];
+        let mut piece_squares = (self.opening_score, self.endgame_score);
+        for piece in PieceIterator::new() {
+            let count = board.bb(active_color, piece).count_ones() as i32
+                - board.bb(!active_color, piece).count_ones() as i32;
+            material[piece.index()] = count;
+
+            // The tables include the piece values, which are counted as material
+            piece_squares.0 -= count * self.opening_values[piece.index()];
+            piece_squares.1 -= count * self.endgame_values[piece.index()];
+        }
+
+        let ours = self.term_counts(board, active_color);
+        let theirs = self.term_counts(board, !active_color);
+
+        EvalFeatures {
+            phase: self.gamephase.min(MAX_PHASE),
+            material,
+            piece_squares,
+            terms: std::array::from_fn(|idx| ours[idx] - theirs[idx]),
+            mobility: [0; PIECE_COUNT],
+            mop_up: self.mop_up(board, active_color) - self.mop_up(board, !active_color),
+            draw_scale: self.draw_scale(board),
+        }
+    }
+
+    /// How often each tunable term applies to `color`, indexed by `EvalWeight`.
+    ///
+    /// Penalties count negatively. Percentage terms hold the bonus they scale
+    /// instead, which for king safety is also negative.
+    fn term_counts(&self, board: &Board, color: Color) -> [i32; EVAL_WEIGHTS.len()] {
+        let mut counts = [0; EVAL_WEIGHTS.len()];
+
+        let (knights, bishops) = self.outposts(board, color);
+        let (phalanxes, passer_bonus) = self.pawn_structure(board, color);
+        let (rooks, active_rooks) = self.rooks_on_seventh(board, color);
+
+        counts[EvalWeight::KnightOutpost.index()] = knights;
+        counts[EvalWeight::BishopOutpost.index()] = bishops;
+        counts[EvalWeight::Phalanx.index()] = phalanxes;
+        counts[EvalWeight::ConnectedPasser.index()] = passer_bonus;
+        counts[EvalWeight::BackwardPawn.index()] = -self.backward_pawns(board, color);
+        counts[EvalWeight::PawnIsland.index()] = -extra_pawn_islands(board.bb(color, Piece::Pawn));
+        counts[EvalWeight::RookOnSeventh.index()] = rooks;
+        counts[EvalWeight::ActiveRookOnSeventh.index()] = active_rooks;
+        counts[EvalWeight::KingSafety.index()] = -self.king_danger(board, color);
+        counts
+    }
+
+    /// Opening and endgame value of a term counted by `term_counts`
+    fn weigh_term(&self, weight: EvalWeight, count: i32) -> (i32, i32) {
+        let (opening, endgame) = self.weight(weight);
+
+        if weight.is_percentage() {
+            (count * opening / 100, count * endgame / 100)
+        } else {
+            (count * opening, count * endgame)
+        }
+    }
+
+    /// Pawns that support each other: the number of phalanxes of pawns side by
+    /// side, and the bonus for passed pawns with another passed pawn next to or
+    /// defending them
+    fn pawn_structure(&self, board: &Board, color: Color) -> (i32, i32) {
+        let pawns = board.bb(color, Piece::Pawn);
+        let enemy_pawns = board.bb(!color, Piece::Pawn);
+
+        let phalanxes = (pawns & pawns.shift(EAST)).count_ones() as i32;
+
+        let mut passers = Bitboard::empty();
+        for square in BitboardIterator::new(pawns) {
+            if self.passed_pawn_spans[color.index()][square as usize] & enemy_pawns == 0 {
+                passers |= Bitboard::square_to_bitboard(square);
+            }
+        }
+
+        // Neighbours on adjacent files one rank behind, level with or ahead of each passer
+        let beside = passers.shift(EAST) | passers.shift(WEST);
+        let connected = passers & (beside | beside.shift(NORTH) | beside.shift(SOUTH));
+
+        let mut passer_bonus = 0;
+        for square in BitboardIterator::new(connected) {
+            let rank = square_to_rank(square);
+            let relative_rank = if color == Color::White {
+                rank
             } else {
-                bit
+                RANKS - 1 - rank
             };
+            passer_bonus += CONNECTED_PASSER_BONUS[relative_rank as usize];
+        }
+        (phalanxes, passer_bonus)
+    }
+
+    /// Number of `color`'s backward pawns: pawns whose stop square is attacked by
+    /// an enemy pawn with no pawn on an adjacent file level with or behind them
+    /// that could come up to defend it
+    fn backward_pawns(&self, board: &Board, color: Color) -> i32 {
+        // Boards set up by hand can have a pawn on the last rank, which has no stop square
+        let pawns = board.bb(color, Piece::Pawn) & !EIGHTH_RANKS[color.index()];
+        let enemy_pawns = board.bb(!color, Piece::Pawn);
+        let forward = if color == Color::White { NORTH } else { SOUTH };
+        let enemy_attacks = enemy_pawns.shift(WEST - forward) | enemy_pawns.shift(EAST - forward);
 
-            opp_opening += OPENING_TABLES[piece_idx][square as usize];
-            opp_endgame += ENDGAME_TABLES[piece_idx][square as usize];
-            opp_count += phase_inc;
+        let backward = BitboardIterator::new(pawns).filter(|&square| {
+            let stop = (square as i8 + forward) as Square;
+
+            // Squares our pawns could attack the stop square from, at or behind this pawn
+            let supporters = self.pawn_attack_spans[(!color).index()][stop as usize];
+
+            pawns & supporters == 0 && enemy_attacks & Bitboard::square_to_bitboard(stop) != 0
+        });
+
+        backward.count() as i32
+    }
+
+    /// Number of `color`'s rooks on the enemy's second rank, split into those
+    /// without and with enemy pawns to attack there or the enemy king stuck on
+    /// its back rank
+    fn rooks_on_seventh(&self, board: &Board, color: Color) -> (i32, i32) {
+        let seventh = SEVENTH_RANKS[color.index()];
+        let rooks = (board.bb(color, Piece::Rook) & seventh).count_ones() as i32;
+
+        let pawns_on_seventh = board.bb(!color, Piece::Pawn) & seventh != 0;
+        let king_on_eighth = board.bb(!color, Piece::King) & EIGHTH_RANKS[color.index()] != 0;
+
+        if pawns_on_seventh || king_on_eighth {
+            (0, rooks)
+        } else {
+            (rooks, 0)
         }
+    }
+
+    /// Danger from enemy pieces attacking the squares around `color`'s king.
+    ///
+    /// Each attacker adds its weight in attack units for every zone square it
+    /// attacks, and the total is looked up in `KING_SAFETY_TABLE`. King attacks
+    /// need pieces to back them up, so by default this only applies in the opening.
+    fn king_danger(&self, board: &Board, color: Color) -> i32 {
+        let king = board.bb(color, Piece::King);
+        if king == 0 {
+            return 0;
+        }
+
+        let zone = self.king_zones[king.trailing_zeros() as usize];
+        let enemy = !color;
+        let lookup = &self.move_generator.lookup;
 
-        self.opening_score += player_opening - opp_opening;
-        self.endgame_score += player_endgame - opp_endgame;
-        self.gamephase += player_count + opp_count;
+        // Pawns add no units, but still count toward the attackers needed
+        let backward = if enemy == Color::White { SOUTH } else { NORTH };
+        let pawn_targets = zone.shift(backward + WEST) | zone.shift(backward + EAST);
+        let mut attackers = (board.bb(enemy, Piece::Pawn) & pawn_targets).count_ones();
+        let mut units = 0;
+
+        // Each piece's attacks are looked up once and intersected with the zone
+        for piece in [
+            Piece::Knight,
+            Piece::Bishop,
+            Piece::Rook,
+            Piece::Queen,
+            Piece::King,
+        ] {
+            for square in BitboardIterator::new(board.bb(enemy, piece)) {
+                let attacks = match piece {
+                    Piece::Knight | Piece::King => lookup.non_sliding_moves(square, piece),
+                    _ => lookup.sliding_moves(square, board.bb_all(), piece),
+                };
+
+                let zone_attacks = attacks & zone;
+                if zone_attacks != 0 {
+                    attackers += 1;
+                    units +=
+                        zone_attacks.count_ones() as usize * KING_ATTACK_WEIGHTS[piece.index()];
+                }
+            }
+        }
+
+        if attackers < MIN_KING_ATTACKERS {
+            return 0;
+        }
+
+        KING_SAFETY_TABLE[units.min(KING_SAFETY_TABLE.len() - 1)]
+    }
+
+    /// Number of knights and bishops on outposts: squares in the enemy half that
+    /// are defended by one of our pawns and can never be attacked by an enemy pawn.
+    fn outposts(&self, board: &Board, color: Color) -> (i32, i32) {
+        let pawns = board.bb(color, Piece::Pawn);
+        let enemy_pawns = board.bb(!color, Piece::Pawn);
+        let forward = if color == Color::White { NORTH } else { SOUTH };
+        let defended = pawns.shift(forward + WEST) | pawns.shift(forward + EAST);
+
+        let count = |piece: Piece| {
+            let candidates = board.bb(color, piece) & defended & OUTPOST_RANKS[color.index()];
+
+            BitboardIterator::new(candidates)
+                .filter(|&square| {
+                    self.pawn_attack_spans[color.index()][square as usize] & enemy_pawns == 0
+                })
+                .count() as i32
+        };
+
+        (count(Piece::Knight), count(Piece::Bishop))
+    }
+
+    /// Squares a piece type of `color` attacks that aren't taken by its own pieces.
+    ///
+    /// Pawns and kings don't count, as their moves say little about how active they are.
+    fn mobility(&self, board: &Board, color: Color, piece: Piece) -> i32 {
+        let lookup = &self.move_generator.lookup;
+        let own_pieces = board.bb_color(color);
+
+        BitboardIterator::new(board.bb(color, piece))
+            .map(|square| {
+                let attacks = match piece {
+                    Piece::Knight => lookup.non_sliding_moves(square, piece),
+                    Piece::Bishop | Piece::Rook | Piece::Queen => {
+                        lookup.sliding_moves(square, board.bb_all(), piece)
+                    }
+                    Piece::Pawn | Piece::King => 0,
+                };
+                (attacks & !own_pieces).count_ones() as i32
+            })
+            .sum()
+    }
+
+    /// Scale factor (out of `SCALE_NORMAL`) pulling the endgame score of drawish endings toward 0.
+    ///
+    /// Opposite-colored bishop endings are notoriously hard to win even with
+    /// extra pawns, so a material edge there is worth less.
+    fn draw_scale(&self, board: &Board) -> i32 {
+        let white_bishops = board.bb(Color::White, Piece::Bishop);
+        let black_bishops = board.bb(Color::Black, Piece::Bishop);
+
+        let one_bishop_each = white_bishops.count_ones() == 1 && black_bishops.count_ones() == 1;
+        let no_knights = board.bb_piece(Piece::Knight) == 0;
+        let few_pawns = board.bb(Color::White, Piece::Pawn).count_ones()
+            <= OPPOSITE_BISHOPS_MAX_PAWNS
+            && board.bb(Color::Black, Piece::Pawn).count_ones() <= OPPOSITE_BISHOPS_MAX_PAWNS;
+
+        if !one_bishop_each || !no_knights || !few_pawns {
+            return SCALE_NORMAL;
+        }
+
+        if !is_opposite_colored(white_bishops, black_bishops) {
+            return SCALE_NORMAL;
+        }
+
+        let heavy_pieces = board.bb_piece(Piece::Rook) | board.bb_piece(Piece::Queen);
+        if heavy_pieces == 0 {
+            SCALE_PURE_OPPOSITE_BISHOPS
+        } else {
+            SCALE_OPPOSITE_BISHOPS
+        }
+    }
+
+    /// Bonus for `strong` when the opponent is down to a lone king and `strong`
+    /// has the pieces to force mate.
+    ///
+    /// Piece-square tables alone give no sense of direction when mating, so this
+    /// rewards driving the lone king to the edge and bringing our own king closer.
+    fn mop_up(&self, board: &Board, strong: Color) -> i32 {
+        let weak = !strong;
+        let weak_king = board.bb(weak, Piece::King);
+        let strong_king = board.bb(strong, Piece::King);
+
+        let weak_is_lone_king = board.bb_color(weak) == weak_king;
+
+        let can_mate = has_mating_material(board, strong);
+
+        if !weak_is_lone_king || !can_mate || weak_king == 0 || strong_king == 0 {
+            return 0;
+        }
+
+        let weak_square = weak_king.trailing_zeros() as Square;
+        let strong_square = strong_king.trailing_zeros() as Square;
+
+        let center_distance = CENTER_DISTANCE[weak_square as usize];
+        let king_distance = manhattan_distance(weak_square, strong_square);
+
+        MOP_UP_CENTER_WEIGHT * center_distance
+            + MOP_UP_PROXIMITY_WEIGHT * (MAX_MANHATTAN_DISTANCE - king_distance)
+    }
+
+    /// Accumulates the piece-square scores and game phase of one side's pieces.
+    ///
+    /// Scores are added for the side to move and subtracted for its opponent.
+    fn eval_color(&mut self, color: Color, board: &Board) {
+        let sign = if color == board.active_color() { 1 } else { -1 };
+
+        for piece in PieceIterator::new() {
+            let piece_idx = piece.index();
+            let opening_table = &self.opening_tables[piece_idx];
+            let endgame_table = &self.endgame_tables[piece_idx];
+
+            let mut opening = 0;
+            let mut endgame = 0;
+
+            for bit in BitboardIterator::new(board.bb(color, piece)) {
+                // Tables are laid out from Black's point of view
+                let square = if color == Color::White { bit ^ 56 } else { bit };
+
+                opening += opening_table[square as usize];
+                endgame += endgame_table[square as usize];
+                self.gamephase += PHASE_INCREMENTS[piece_idx];
+            }
+
+            self.opening_score += sign * opening;
+            self.endgame_score += sign * endgame;
+        }
     }
 
     fn reset(&mut self) {
@@ -180,9 +813,243 @@ impl Evaluator {
     }
 }
 
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The unweighted terms of an evaluation, for tuning.
+///
+/// Counts are from the perspective of the side to move, ours minus theirs.
+pub struct EvalFeatures {
+    /// Game phase from the remaining material, see `game_phase`
+    pub phase: i32,
+    /// Difference in the number of each piece, indexed by piece
+    pub material: [i32; PIECE_COUNT],
+    /// Sums of the piece-square tables without the piece values
+    pub piece_squares: (i32, i32),
+    /// How often each tunable term applies, indexed by `EvalWeight`.
+    /// Percentage terms hold the bonus they scale
+    pub terms: [i32; EVAL_WEIGHTS.len()],
+    /// Difference in the number of squares each piece type attacks, indexed by
+    /// piece. Only filled in by `Evaluator::features` and not weighted yet
+    pub mobility: [i32; PIECE_COUNT],
+    /// Endgame bonus for driving a lone king to the edge
+    pub mop_up: i32,
+    /// Scale factor (out of `SCALE_NORMAL`) applied to the endgame score
+    pub draw_scale: i32,
+}
+
+/// The terms of an evaluation, each as (opening, endgame) where they're tapered
+pub struct EvalBreakdown {
+    /// Game phase from the remaining material, see `game_phase`
+    pub phase: i32,
+    pub material: (i32, i32),
+    /// Piece-square bonuses on top of the material
+    pub piece_squares: (i32, i32),
+    pub outposts: (i32, i32),
+    /// Phalanxes, connected passed pawns, backward pawns and pawn islands
+    pub pawn_structure: (i32, i32),
+    pub rooks_on_seventh: (i32, i32),
+    /// Penalty for enemy pieces attacking the squares around the king
+    pub king_safety: (i32, i32),
+    /// Endgame bonus for driving a lone king to the edge
+    pub mop_up: i32,
+    /// Scale factor (out of `SCALE_NORMAL`) applied to the endgame score
+    pub draw_scale: i32,
+}
+
+impl EvalBreakdown {
+    /// Sums the terms into the untapered (opening, endgame) scores
+    pub fn scores(&self) -> (i32, i32) {
+        let opening = self.material.0
+            + self.piece_squares.0
+            + self.outposts.0
+            + self.pawn_structure.0
+            + self.rooks_on_seventh.0
+            + self.king_safety.0;
+        let endgame = self.material.1
+            + self.piece_squares.1
+            + self.outposts.1
+            + self.pawn_structure.1
+            + self.rooks_on_seventh.1
+            + self.king_safety.1
+            + self.mop_up;
+
+        (opening, endgame)
+    }
+
+    /// Combines the terms into the final evaluation
+    pub fn total(&self) -> i32 {
+        let (opening, endgame) = self.scores();
+        tapered_score(opening, endgame, self.draw_scale, self.phase)
+    }
+}
+
+impl std::fmt::Display for EvalBreakdown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Term           Opening  Endgame")?;
+        writeln!(
+            f,
+            "Material       {:>7}  {:>7}",
+            self.material.0, self.material.1
+        )?;
+        writeln!(
+            f,
+            "Piece squares  {:>7}  {:>7}",
+            self.piece_squares.0, self.piece_squares.1
+        )?;
+        writeln!(
+            f,
+            "Outposts       {:>7}  {:>7}",
+            self.outposts.0, self.outposts.1
+        )?;
+        writeln!(
+            f,
+            "Pawn structure {:>7}  {:>7}",
+            self.pawn_structure.0, self.pawn_structure.1
+        )?;
+        writeln!(
+            f,
+            "Rook on 7th    {:>7}  {:>7}",
+            self.rooks_on_seventh.0, self.rooks_on_seventh.1
+        )?;
+        writeln!(
+            f,
+            "King safety    {:>7}  {:>7}",
+            self.king_safety.0, self.king_safety.1
+        )?;
+        writeln!(f, "Mop up         {:>7}  {:>7}", "", self.mop_up)?;
+        writeln!(f, "Phase          {}/{}", self.phase, MAX_PHASE)?;
+        writeln!(f, "Draw scale     {}/{}", self.draw_scale, SCALE_NORMAL)?;
+        write!(f, "Total          {}", self.total())
+    }
+}
+
+/// Blends opening and endgame scores by game phase.
+///
+/// Drawish endings only weaken the endgame component so the scaling fades
+/// in as material comes off.
+fn tapered_score(opening: i32, endgame: i32, draw_scale: i32, phase: i32) -> i32 {
+    let opening_phase = phase.min(MAX_PHASE);
+    let endgame_phase = MAX_PHASE - opening_phase;
+    let endgame = endgame * draw_scale / SCALE_NORMAL;
+
+    (opening * opening_phase + endgame * endgame_phase) / MAX_PHASE
+}
+
+/// Sum of two (opening, endgame) score pairs
+fn add(first: (i32, i32), second: (i32, i32)) -> (i32, i32) {
+    (first.0 + second.0, first.1 + second.1)
+}
+
+/// Groups of pawns on adjacent files beyond the first, as every split leaves more pawns
+/// without neighbours to defend them
+fn extra_pawn_islands(pawns: Bitboard) -> i32 {
+    let mut files: u8 = 0;
+    for file in 0..FILES {
+        if pawns & (FILE_A << file) != 0 {
+            files |= 1 << file;
+        }
+    }
+
+    // Each island starts at an occupied file with no occupied file to its left
+    let islands = (files & !(files << 1)).count_ones() as i32;
+    (islands - 1).max(0)
+}
+
+/// For each color and square, the squares on adjacent files further up the board
+/// (from that color's side) where an enemy pawn could still attack the square from
+fn pawn_attack_spans() -> [[Bitboard; SQUARES as usize]; COLOR_COUNT] {
+    let mut spans = [[Bitboard::empty(); SQUARES as usize]; COLOR_COUNT];
+
+    for color in ColorIterator::new() {
+        for square in 0..SQUARES {
+            let file = square_to_file(square) as i32;
+            let rank = square_to_rank(square);
+            let ranks_ahead = match color {
+                Color::White => rank + 1..RANKS,
+                Color::Black => 0..rank,
+            };
+
+            for span_rank in ranks_ahead {
+                for span_file in [file - 1, file + 1] {
+                    if (0..FILES as i32).contains(&span_file) {
+                        spans[color.index()][square as usize] |=
+                            Bitboard::rank_file_to_bitboard(span_rank, span_file as u8);
+                    }
+                }
+            }
+        }
+    }
+    spans
+}
+
+/// For each color and square, the squares on the same and adjacent files further
+/// up the board (from that color's side) where an enemy pawn could stop a pawn there
+fn passed_pawn_spans() -> [[Bitboard; SQUARES as usize]; COLOR_COUNT] {
+    let mut spans = pawn_attack_spans();
+
+    for color in ColorIterator::new() {
+        let forward = if color == Color::White { NORTH } else { SOUTH };
+
+        for square in 0..SQUARES {
+            let mut ahead = Bitboard::square_to_bitboard(square).shift(forward);
+            while ahead != 0 {
+                spans[color.index()][square as usize] |= ahead;
+                ahead = ahead.shift(forward);
+            }
+        }
+    }
+    spans
+}
+
+/// For each square, the square itself and the squares a king there could move to
+fn king_zones() -> [Bitboard; SQUARES as usize] {
+    let mut zones = generate_king_lookup_table();
+
+    for square in 0..SQUARES {
+        zones[square as usize] |= Bitboard::square_to_bitboard(square);
+    }
+    zones
+}
+
+/// Returns true if the two single-bishop bitboards are on different colored squares
+fn is_opposite_colored(bishop: Bitboard, other_bishop: Bitboard) -> bool {
+    let is_light = |bb: Bitboard| bb & LIGHT_SQUARES != 0;
+    let is_dark = |bb: Bitboard| bb & DARK_SQUARES != 0;
+
+    (is_light(bishop) && is_dark(other_bishop)) || (is_dark(bishop) && is_light(other_bishop))
+}
+
+/// Returns true if `color`'s pieces can force mate against a lone king without
+/// promoting: a queen, a rook, bishops on both colors, or a bishop and a knight
+fn has_mating_material(board: &Board, color: Color) -> bool {
+    let heavy_pieces = board.bb(color, Piece::Queen) | board.bb(color, Piece::Rook);
+    let bishops = board.bb(color, Piece::Bishop);
+    let knights = board.bb(color, Piece::Knight);
+
+    let bishop_pair = bishops & LIGHT_SQUARES != 0 && bishops & DARK_SQUARES != 0;
+    let bishop_and_knight = bishops != 0 && knights != 0;
+
+    heavy_pieces != 0 || bishop_pair || bishop_and_knight
+}
+
+/// Number of king steps along files and ranks between two squares
+fn manhattan_distance(a: Square, b: Square) -> i32 {
+    let file_distance = (square_to_file(a) as i32 - square_to_file(b) as i32).abs();
+    let rank_distance = (square_to_rank(a) as i32 - square_to_rank(b) as i32).abs();
+
+    file_distance + rank_distance
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::bitboard::{FILE_A, FILE_B, FILE_C, FILE_D, FILE_E};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
     use std::time::Instant;
 
     #[test]
@@ -197,4 +1064,633 @@ mod tests {
         let duration = start.elapsed();
         println!("Test took: {:?}", duration);
     }
+
+    /// Opening score, endgame score, and game phase computed one piece type at a
+    /// time for both sides, the way the evaluation was originally written
+    fn per_piece_type_components(board: &Board) -> (i32, i32, i32) {
+        let active_color = board.active_color();
+        let relative_square = |color: Color, bit: u8| {
+            if color == Color::White {
+                bit ^ 56
+            } else {
+                bit
+            }
+        };
+
+        let (mut opening, mut endgame, mut phase) = (0, 0, 0);
+        for piece in PieceIterator::new() {
+            let idx = piece.index();
+
+            for bit in BitboardIterator::new(board.bb(active_color, piece)) {
+                let square = relative_square(active_color, bit) as usize;
+                opening += OPENING_TABLES[idx][square];
+                endgame += ENDGAME_TABLES[idx][square];
+                phase += PHASE_INCREMENTS[idx];
+            }
+
+            for bit in BitboardIterator::new(board.bb(!active_color, piece)) {
+                let square = relative_square(!active_color, bit) as usize;
+                opening -= OPENING_TABLES[idx][square];
+                endgame -= ENDGAME_TABLES[idx][square];
+                phase += PHASE_INCREMENTS[idx];
+            }
+        }
+
+        (opening, endgame, phase)
+    }
+
+    #[test]
+    fn test_mirrored_positions_evaluate_the_same() {
+        // Each position next to its mirror: ranks flipped, colors and the side to move swapped
+        let pairs = [
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                "r3k2r/pppbbppp/2n2q1P/1P2p3/3pn3/BN2PNP1/P1PPQPB1/R3K2R b KQkq - 0 1",
+            ),
+            (
+                "r4rk1/pp3p1p/3p2pQ/3Np1N1/4P3/8/PPP2PPP/R4RK1 w - - 0 1",
+                "r4rk1/ppp2ppp/8/4p3/3nP1n1/3P2Pq/PP3P1P/R4RK1 b - - 0 1",
+            ),
+            (
+                "4k3/6p1/5p2/4p3/2P1P3/3P4/8/4K3 w - - 0 1",
+                "4k3/8/3p4/2p1p3/4P3/5P2/6P1/4K3 b - - 0 1",
+            ),
+        ];
+        let mut evaluator = Evaluator::new();
+
+        // Scores are from the side to move, so a position and its mirror score the same
+        for (fen, mirrored_fen) in pairs {
+            let breakdown = evaluator.evaluate_breakdown(&Board::new(fen));
+            let mirrored = evaluator.evaluate_breakdown(&Board::new(mirrored_fen));
+
+            assert_eq!(breakdown.piece_squares, mirrored.piece_squares, "{}", fen);
+            assert_eq!(breakdown.king_safety, mirrored.king_safety, "{}", fen);
+            assert_eq!(breakdown.total(), mirrored.total(), "{}", fen);
+        }
+
+        // With only the kings left, White's castled king is worth what Black's is
+        let kings = Board::new("6k1/8/8/8/8/8/8/6K1 w - - 0 1");
+        assert_eq!(evaluator.evaluate_breakdown(&kings).piece_squares, (0, 0));
+    }
+
+    #[test]
+    fn test_evaluation_is_symmetric() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1",
+            "6k1/6p1/6Pp/ppp5/3pn2P/1P3K2/1PP2P2/3N4 b - - 0 1",
+            "8/5k2/3b4/2p5/2P5/4B3/5K2/8 b - - 0 1",
+            "8/8/8/3k4/8/8/8/Q6K w - - 0 1",
+        ];
+        let mut evaluator = Evaluator::new();
+
+        for fen in fens {
+            let board = Board::new(fen);
+            let breakdown = evaluator.evaluate_breakdown(&board);
+            let mirrored = evaluator.evaluate_breakdown(&board.mirror());
+
+            assert!(evaluator.is_symmetric(&board), "{}", fen);
+            assert_eq!(breakdown.material, mirrored.material, "{}", fen);
+            assert_eq!(breakdown.piece_squares, mirrored.piece_squares, "{}", fen);
+            assert_eq!(breakdown.outposts, mirrored.outposts, "{}", fen);
+            assert_eq!(breakdown.pawn_structure, mirrored.pawn_structure, "{}", fen);
+            assert_eq!(
+                breakdown.rooks_on_seventh, mirrored.rooks_on_seventh,
+                "{}",
+                fen
+            );
+            assert_eq!(breakdown.king_safety, mirrored.king_safety, "{}", fen);
+            assert_eq!(breakdown.mop_up, mirrored.mop_up, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_pawn_attack_spans() {
+        let spans = pawn_attack_spans();
+
+        // d5 for White can be hit from c6, c7, e6 and e7 (c8/e8 pawns can't exist but are harmless)
+        let d5 = 35;
+        assert_eq!(
+            spans[Color::White.index()][d5],
+            (FILE_C | FILE_E) & (RANK_6 | RANK_7 | RANK_8)
+        );
+        assert_eq!(
+            spans[Color::Black.index()][d5],
+            (FILE_C | FILE_E) & (RANK_1 | RANK_2 | RANK_3 | RANK_4)
+        );
+        assert_eq!(spans[Color::White.index()][0], FILE_B & !RANK_1);
+    }
+
+    #[test]
+    fn test_knight_outpost() {
+        let evaluator = Evaluator::new();
+
+        // The d5 knight is defended by the e4 pawn and no black pawn can challenge it
+        let board = Board::new("4k3/1p3p2/8/3N4/4P3/8/8/4K3 w - - 0 1");
+        assert_eq!(evaluator.outposts(&board, Color::White), (1, 0));
+
+        // A c-pawn could still drive it away
+        let board = Board::new("4k3/2p2p2/8/3N4/4P3/8/8/4K3 w - - 0 1");
+        assert_eq!(evaluator.outposts(&board, Color::White), (0, 0));
+
+        // Undefended squares and squares in our own half aren't outposts
+        let board = Board::new("4k3/1p3p2/8/3N4/8/8/8/4K3 w - - 0 1");
+        assert_eq!(evaluator.outposts(&board, Color::White), (0, 0));
+
+        // Mirrored for Black, with a bishop on e4 defended by d5
+        let board = Board::new("4k3/8/8/3p4/4b3/8/P7/4K3 b - - 0 1");
+        assert_eq!(evaluator.outposts(&board, Color::Black), (0, 1));
+    }
+
+    #[test]
+    fn test_passed_pawn_spans() {
+        let spans = passed_pawn_spans();
+
+        let d5 = 35;
+        assert_eq!(
+            spans[Color::White.index()][d5],
+            (FILE_C | FILE_D | FILE_E) & (RANK_6 | RANK_7 | RANK_8)
+        );
+        assert_eq!(spans[Color::Black.index()][8], (FILE_A | FILE_B) & RANK_1);
+    }
+
+    #[test]
+    fn test_pawn_structure() {
+        let evaluator = Evaluator::new();
+
+        // d4-e4 is a phalanx but the pawns aren't passed
+        let board = Board::new("4k3/3p4/8/8/3PP3/8/8/4K3 w - - 0 1");
+        assert_eq!(evaluator.pawn_structure(&board, Color::White), (1, 0));
+
+        // Connected passers on d6 and e5 defend each other
+        let board = Board::new("4k3/8/3P4/4P3/8/8/8/4K3 w - - 0 1");
+        let (d6, e5) = (CONNECTED_PASSER_BONUS[5], CONNECTED_PASSER_BONUS[4]);
+        assert_eq!(evaluator.pawn_structure(&board, Color::White), (0, d6 + e5));
+
+        // Split passers get nothing
+        let board = Board::new("4k3/8/8/1P4P1/8/8/8/4K3 w - - 0 1");
+        assert_eq!(evaluator.pawn_structure(&board, Color::White), (0, 0));
+
+        // Black's b3-c3 passers are both a phalanx and connected
+        let board = Board::new("4k3/8/8/8/8/1pp5/8/4K3 b - - 0 1");
+        let passer = CONNECTED_PASSER_BONUS[5];
+        assert_eq!(
+            evaluator.pawn_structure(&board, Color::Black),
+            (1, 2 * passer)
+        );
+    }
+
+    #[test]
+    fn test_backward_pawns() {
+        let mut evaluator = Evaluator::new();
+
+        // d3 is behind c4 and e4 and can't go to d4 without being taken by e5,
+        // while Black's f6 is backed up by g7
+        let board = Board::new("4k3/6p1/5p2/4p3/2P1P3/3P4/8/4K3 w - - 0 1");
+        assert_eq!(evaluator.backward_pawns(&board, Color::White), 1);
+        assert_eq!(evaluator.backward_pawns(&board, Color::Black), 0);
+
+        // Without the g7 pawn Black's f6 can't be defended and e4 controls f5
+        let board = Board::new("4k3/8/5p2/4p3/2P1P3/3P4/8/4K3 w - - 0 1");
+        assert_eq!(evaluator.backward_pawns(&board, Color::Black), 1);
+
+        // Nothing controls d4, so d3 can catch up
+        let board = Board::new("4k3/6p1/5p2/8/2P1P3/3P4/8/4K3 w - - 0 1");
+        assert_eq!(evaluator.backward_pawns(&board, Color::White), 0);
+
+        // FENs can't hold a pawn on the last rank, but a hand built board can
+        let mut board = Board::new("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        board.set_piece(Color::White, Piece::Pawn, 56);
+        board.set_piece(Color::Black, Piece::Pawn, 7);
+        assert_eq!(evaluator.backward_pawns(&board, Color::White), 0);
+        assert_eq!(evaluator.backward_pawns(&board, Color::Black), 0);
+        evaluator.evaluate(&board);
+    }
+
+    #[test]
+    fn test_pawn_islands() {
+        let board = Board::new("4k3/pp3pp1/8/8/8/8/P1P1P1P1/4K3 w - - 0 1");
+
+        assert_eq!(extra_pawn_islands(board.bb(Color::White, Piece::Pawn)), 3);
+        assert_eq!(extra_pawn_islands(board.bb(Color::Black, Piece::Pawn)), 1);
+        assert_eq!(extra_pawn_islands(Bitboard::empty()), 0);
+
+        // Both sides have the same material, but White's scattered pawns are worse
+        let mut evaluator = Evaluator::new();
+        let breakdown = evaluator.evaluate_breakdown(&board);
+        assert!(breakdown.pawn_structure.0 < 0 && breakdown.pawn_structure.1 < 0);
+    }
+
+    #[test]
+    fn test_rooks_on_seventh() {
+        let evaluator = Evaluator::new();
+
+        // Black's king is on its back rank, so the rook is active
+        let board = Board::new("4k3/R7/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(evaluator.rooks_on_seventh(&board, Color::White), (0, 1));
+
+        // With the king off the back rank and no pawns to attack it's worth less
+        let board = Board::new("8/R7/4k3/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(evaluator.rooks_on_seventh(&board, Color::White), (1, 0));
+
+        // Black rooks on the second rank with White pawns there
+        let board = Board::new("8/8/4k3/8/8/8/rr3P2/6K1 w - - 0 1");
+        assert_eq!(evaluator.rooks_on_seventh(&board, Color::Black), (0, 2));
+        assert_eq!(evaluator.rooks_on_seventh(&board, Color::White), (0, 0));
+    }
+
+    #[test]
+    fn test_breakdown_adds_up_to_evaluation() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+            "8/8/8/3k4/8/8/8/Q6K w - - 0 1",
+            "8/5k2/3b4/2p5/2P5/4B3/5K2/8 b - - 0 1",
+        ];
+        let mut evaluator = Evaluator::new();
+
+        for fen in fens {
+            let board = Board::new(fen);
+            let breakdown = evaluator.evaluate_breakdown(&board);
+
+            assert_eq!(breakdown.total(), evaluator.evaluate(&board), "{}", fen);
+        }
+
+        // A queen up against a lone king is mostly material with a mop-up bonus
+        let breakdown = evaluator.evaluate_breakdown(&Board::new(fens[2]));
+        let queen = Piece::Queen.index();
+        assert_eq!(
+            breakdown.material,
+            (DEFAULT_OPENING_VALUES[queen], DEFAULT_ENDGAME_VALUES[queen])
+        );
+        assert!(breakdown.mop_up > 0);
+    }
+
+    #[test]
+    fn test_phase_never_rises_as_material_is_removed() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut evaluator = Evaluator::new();
+
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            // Extra queens from promotion push the raw phase past the maximum
+            "rnbqkbnr/1Qpppppp/8/8/8/8/1qPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "QQQQkQQQ/8/8/8/8/8/8/qqqqKqqq w - - 0 1",
+        ] {
+            let mut board = Board::new(fen);
+            let mut phase = game_phase(&board);
+            assert!((0..=MAX_PHASE).contains(&phase));
+
+            loop {
+                let pieces: Vec<(Color, Piece, Square)> = ColorIterator::new()
+                    .flat_map(|color| {
+                        [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]
+                            .into_iter()
+                            .flat_map(move |piece| {
+                                BitboardIterator::new(board.bb(color, piece))
+                                    .map(move |square| (color, piece, square))
+                            })
+                    })
+                    .collect();
+                if pieces.is_empty() {
+                    break;
+                }
+
+                let (color, piece, square) = pieces[rng.gen_range(0..pieces.len())];
+                board.remove_piece(color, piece, square);
+
+                let next_phase = game_phase(&board);
+                assert!(next_phase <= phase, "{} removing {:?}", fen, piece);
+                assert_eq!(evaluator.evaluate_breakdown(&board).phase, next_phase);
+                phase = next_phase;
+            }
+
+            assert_eq!(phase, 0);
+        }
+    }
+
+    #[test]
+    fn test_queen_trade_phase() {
+        let board = Board::new("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1");
+        assert_eq!(game_phase(&board), MAX_PHASE - 8);
+
+        // Each minor piece is worth one step
+        let board = Board::new("r1b1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KB1R w KQkq - 0 1");
+        assert_eq!(game_phase(&board), MAX_PHASE - 10);
+    }
+
+    #[test]
+    fn test_single_pass_matches_per_piece_type() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkb1r/p1pp1ppp/1p3n2/4N3/4P3/8/PPPP1PPP/RNBQKB1R w KQkq - 0 4",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r1b3nr/ppp3qp/1bnpk3/4p1BQ/3PP3/2P5/PP3PPP/RN3RK1 b - - 0 11",
+        ];
+
+        let mut evaluator = Evaluator::new();
+        for fen in fens {
+            let board = Board::new(fen);
+            evaluator.evaluate(&board);
+
+            let (opening, endgame, phase) = per_piece_type_components(&board);
+            assert_eq!(evaluator.opening_score, opening, "{}", fen);
+            assert_eq!(evaluator.endgame_score, endgame, "{}", fen);
+            assert_eq!(evaluator.gamephase, phase, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_queen_value_changes_evaluation() {
+        let mut evaluator = Evaluator::new();
+        let board = Board::new("3qk3/8/8/8/8/8/8/4K3 b - - 0 1");
+
+        let default_score = evaluator.evaluate(&board);
+
+        let (opening, endgame) = evaluator.piece_value(Piece::Queen);
+        evaluator.set_piece_value(Piece::Queen, opening + 100, endgame + 100);
+
+        assert_eq!(evaluator.evaluate(&board), default_score + 100);
+    }
+
+    #[test]
+    fn test_default_values_match_tables() {
+        let evaluator = Evaluator::new();
+
+        assert_eq!(evaluator.opening_tables, OPENING_TABLES);
+        assert_eq!(evaluator.endgame_tables, ENDGAME_TABLES);
+    }
+
+    #[test]
+    fn test_cached_evaluation_matches() {
+        let mut evaluator = Evaluator::new();
+        let board = Board::new("rnbqkb1r/p1pp1ppp/1p3n2/4N3/4P3/8/PPPP1PPP/RNBQKB1R w KQkq - 0 4");
+        let hash = 12345;
+
+        let score = evaluator.evaluate(&board);
+
+        assert_eq!(evaluator.evaluate_cached(&board, hash), score);
+        assert_eq!(evaluator.cache.probe(hash), Some(score));
+        assert_eq!(evaluator.evaluate_cached(&board, hash), score);
+    }
+
+    #[test]
+    fn test_piece_value_change_clears_cache() {
+        let mut evaluator = Evaluator::new();
+        let board = Board::new("3qk3/8/8/8/8/8/8/4K3 b - - 0 1");
+        let hash = 12345;
+
+        let score = evaluator.evaluate_cached(&board, hash);
+        evaluator.set_piece_value(Piece::Queen, 1125, 1036);
+
+        assert_eq!(evaluator.evaluate_cached(&board, hash), score + 100);
+    }
+
+    #[test]
+    fn test_mop_up_rewards_cornered_king() {
+        let mut evaluator = Evaluator::new();
+
+        let centered = Board::new("8/8/8/3k4/8/8/8/4K2Q w - - 0 1");
+        let cornered = Board::new("k7/8/8/8/8/8/8/4K2Q w - - 0 1");
+
+        assert!(evaluator.evaluate(&cornered) > evaluator.evaluate(&centered));
+    }
+
+    #[test]
+    fn test_mop_up_rewards_close_kings() {
+        let mut evaluator = Evaluator::new();
+
+        let far = Board::new("k7/8/8/8/8/8/8/6KQ w - - 0 1");
+        let close = Board::new("k7/8/2K5/8/8/8/8/7Q w - - 0 1");
+
+        assert!(evaluator.evaluate(&close) > evaluator.evaluate(&far));
+
+        // The lone king's side sees the same bonus against it
+        let far = Board::new("k7/8/8/8/8/8/8/6KQ b - - 0 1");
+        let close = Board::new("k7/8/2K5/8/8/8/8/7Q b - - 0 1");
+
+        assert!(evaluator.evaluate(&close) < evaluator.evaluate(&far));
+    }
+
+    #[test]
+    fn test_no_mop_up_with_material_on_both_sides() {
+        let evaluator = Evaluator::new();
+        let board = Board::new("k7/7p/8/8/8/8/8/4K2Q w - - 0 1");
+
+        assert_eq!(evaluator.mop_up(&board, Color::White), 0);
+        assert_eq!(evaluator.mop_up(&board, Color::Black), 0);
+    }
+
+    #[test]
+    fn test_no_mop_up_without_mating_material() {
+        let evaluator = Evaluator::new();
+
+        for fen in [
+            "k7/8/8/8/8/8/8/4KN2 w - - 0 1",
+            "k7/8/8/8/8/8/8/3NKN2 w - - 0 1",
+            // Bishops on the same color can't mate either
+            "k7/8/8/8/8/8/8/2B1K1B1 w - - 0 1",
+        ] {
+            let board = Board::new(fen);
+            assert_eq!(evaluator.mop_up(&board, Color::White), 0, "{}", fen);
+        }
+
+        for fen in [
+            "k7/8/8/8/8/8/8/4K2R w - - 0 1",
+            "k7/8/8/8/8/8/8/2B1KB2 w - - 0 1",
+            "k7/8/8/8/8/8/8/2B1K1N1 w - - 0 1",
+        ] {
+            let board = Board::new(fen);
+            assert!(evaluator.mop_up(&board, Color::White) > 0, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_opposite_colored_bishops_scaled() {
+        let evaluator = Evaluator::new();
+
+        // White's bishop is on c1 (dark) and Black's on c8 (light)
+        let pure = Board::new("2b3k1/5ppp/8/8/8/8/4PPPP/2B3K1 w - - 0 1");
+        assert_eq!(evaluator.draw_scale(&pure), SCALE_PURE_OPPOSITE_BISHOPS);
+
+        let with_rooks = Board::new("2b2rk1/5ppp/8/8/8/8/4PPPP/2B2RK1 w - - 0 1");
+        assert_eq!(evaluator.draw_scale(&with_rooks), SCALE_OPPOSITE_BISHOPS);
+    }
+
+    #[test]
+    fn test_same_colored_bishops_not_scaled() {
+        let evaluator = Evaluator::new();
+
+        let same_color = Board::new("5bk1/5ppp/8/8/8/8/4PPPP/2B3K1 w - - 0 1");
+        assert_eq!(evaluator.draw_scale(&same_color), SCALE_NORMAL);
+
+        let with_knight = Board::new("2b3k1/5ppp/8/8/8/8/4PPPP/2B1N1K1 w - - 0 1");
+        assert_eq!(evaluator.draw_scale(&with_knight), SCALE_NORMAL);
+
+        let many_pawns = Board::new("2b3k1/pp3ppp/8/8/8/8/PPPPPPPP/2B3K1 w - - 0 1");
+        assert_eq!(evaluator.draw_scale(&many_pawns), SCALE_NORMAL);
+    }
+
+    #[test]
+    fn test_opposite_colored_bishops_halve_endgame_score() {
+        let mut evaluator = Evaluator::new();
+
+        // Pure opposite-colored bishop ending with White a pawn up
+        let board = Board::new("2b3k1/6pp/8/8/8/8/5PPP/2B3K1 w - - 0 1");
+        let scaled = evaluator.evaluate(&board);
+        let breakdown = evaluator.evaluate_breakdown(&board);
+        let (opening_score, endgame_score) = breakdown.scores();
+
+        let opening_phase = breakdown.phase.min(MAX_PHASE);
+        let endgame_phase = MAX_PHASE - opening_phase;
+        let blend = |endgame_score: i32| {
+            (opening_score * opening_phase + endgame_score * endgame_phase) / MAX_PHASE
+        };
+
+        let unscaled = blend(endgame_score);
+        assert_eq!(scaled, blend(endgame_score / 2));
+        assert!(unscaled > scaled && scaled > 0);
+    }
+
+    #[test]
+    fn test_opposite_colored_bishop_edge_is_reduced() {
+        let mut evaluator = Evaluator::new();
+
+        // White is a pawn up in both, but only the first has opposite-colored bishops
+        let opposite = Board::new("2b3k1/6pp/8/8/8/8/5PPP/2B3K1 w - - 0 1");
+        let same = Board::new("5bk1/6pp/8/8/8/8/5PPP/2B3K1 w - - 0 1");
+
+        let opposite_score = evaluator.evaluate(&opposite);
+        let same_score = evaluator.evaluate(&same);
+
+        assert!(opposite_score > 0);
+        assert!(opposite_score < same_score / 2 + 20);
+    }
+
+    #[test]
+    fn test_king_safety_ignores_lone_attacker() {
+        let evaluator = Evaluator::new();
+
+        // Only the queen hits the squares around the king
+        let board = Board::new("6k1/8/8/8/7q/8/5PPP/6K1 w - - 0 1");
+
+        assert_eq!(evaluator.king_danger(&board, Color::White), 0);
+    }
+
+    #[test]
+    fn test_king_safety_grows_with_attackers() {
+        let evaluator = Evaluator::new();
+
+        // The knight joins the queen on f2 and h2, then the bishop on h2
+        let two = Board::new("6k1/8/8/8/6nq/8/5PPP/6K1 w - - 0 1");
+        let three = Board::new("6k1/8/3b4/8/6nq/8/5PPP/6K1 w - - 0 1");
+
+        let two_danger = evaluator.king_danger(&two, Color::White);
+        let three_danger = evaluator.king_danger(&three, Color::White);
+
+        assert_eq!(two_danger, KING_SAFETY_TABLE[14]);
+        assert!(three_danger > two_danger);
+        assert_eq!(evaluator.king_danger(&three, Color::Black), 0);
+
+        // By default the penalty only applies in the opening
+        let (opening, endgame) = evaluator.weigh_term(EvalWeight::KingSafety, -three_danger);
+        assert_eq!((opening, endgame), (-three_danger, 0));
+    }
+
+    #[test]
+    fn test_king_zone_includes_king_square() {
+        let zones = king_zones();
+
+        assert_eq!(zones[0].count_ones(), 4);
+        assert_eq!(zones[27].count_ones(), 9);
+    }
+
+    #[test]
+    fn test_weights_change_evaluation() {
+        let mut evaluator = Evaluator::new();
+
+        // White has a knight outpost on d5 and Black's king is under attack
+        let board = Board::new("r4rk1/pp3p1p/3p2pQ/3Np1N1/4P3/8/PPP2PPP/R4RK1 w - - 0 1");
+        let default_breakdown = evaluator.evaluate_breakdown(&board);
+        let default_score = evaluator.evaluate_cached(&board, 1);
+
+        for weight in EVAL_WEIGHTS {
+            assert_eq!(evaluator.weight(weight), weight.default_value());
+        }
+
+        evaluator.set_weight(EvalWeight::KnightOutpost, 100, 100);
+        evaluator.set_weight(EvalWeight::KingSafety, 0, 0);
+        let breakdown = evaluator.evaluate_breakdown(&board);
+
+        assert!(breakdown.outposts.0 > default_breakdown.outposts.0);
+        assert_eq!(breakdown.king_safety, (0, 0));
+        assert_ne!(default_breakdown.king_safety, (0, 0));
+        // The cached score from before the change isn't reused
+        assert_ne!(evaluator.evaluate_cached(&board, 1), default_score);
+    }
+
+    #[test]
+    fn test_weighted_features_match_evaluation() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+            "r4rk1/pp3p1p/3p2pQ/3Np1N1/4P3/8/PPP2PPP/R4RK1 w - - 0 1",
+            "4k3/8/8/8/8/1pp5/8/4K3 b - - 0 1",
+        ];
+        let mut evaluator = Evaluator::new();
+        evaluator.set_weight(EvalWeight::ConnectedPasser, 37, 80);
+
+        for fen in fens {
+            let board = Board::new(fen);
+            let features = evaluator.features(&board);
+
+            assert_eq!(
+                evaluator.weigh(&features).total(),
+                evaluator.evaluate(&board),
+                "{}",
+                fen
+            );
+        }
+
+        // White is a queen and two knights up, with one knight on an outpost
+        let features = evaluator.features(&Board::new(fens[2]));
+        assert_eq!(features.terms[EvalWeight::KnightOutpost.index()], 1);
+        assert_eq!(features.material, [0, 2, 0, 0, 1, 0]);
+        assert!(features.mobility[Piece::Knight.index()] > 0);
+    }
+
+    #[test]
+    fn test_evaluation_matches_weighted_features_in_random_games() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let move_generator = MoveGenerator::shared();
+        let mut evaluator = Evaluator::new();
+        evaluator.set_piece_value(Piece::Knight, 310, 290);
+        evaluator.set_weight(EvalWeight::KingSafety, 130, 20);
+        evaluator.set_weight(EvalWeight::ConnectedPasser, 37, 80);
+        evaluator.set_weight(EvalWeight::BackwardPawn, 9, 14);
+
+        for _ in 0..20 {
+            let mut board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+
+            for _ in 0..120 {
+                let features = evaluator.features(&board);
+                assert_eq!(
+                    evaluator.weigh(&features).total(),
+                    evaluator.evaluate(&board),
+                    "{}",
+                    board.to_fen()
+                );
+
+                let moves = move_generator.generate_moves(&board);
+                if moves.is_empty() {
+                    break;
+                }
+                board.make_move(&moves[rng.gen_range(0..moves.len())]);
+            }
+        }
+    }
 }
@@ -0,0 +1,112 @@
+/// Number of entries in the cache (must be a power of two)
+const DEFAULT_SIZE: usize = 1 << 18;
+
+/// Caches static evaluations by zobrist hash
+///
+/// The same positions are reached many times through transpositions and
+/// across iterations, so remembering their evaluation avoids iterating
+/// every piece again. Each slot is always replaced on store.
+#[derive(Debug, Clone)]
+pub struct EvalCache {
+    entries: Vec<Option<Entry>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    hash: u64,
+    score: i32,
+}
+
+impl EvalCache {
+    /// Creates a new empty cache
+    pub fn new() -> Self {
+        Self {
+            entries: vec![None; DEFAULT_SIZE],
+        }
+    }
+
+    /// Gets the cached evaluation of a position
+    ///
+    /// # Arguments
+    /// * `hash` - Zobrist hash of the position
+    ///
+    /// # Returns
+    /// The cached score or `None` if the position isn't cached
+    pub fn probe(&self, hash: u64) -> Option<i32> {
+        match self.entries[self.index(hash)] {
+            Some(entry) if entry.hash == hash => Some(entry.score),
+            _ => None,
+        }
+    }
+
+    /// Stores the evaluation of a position
+    ///
+    /// # Arguments
+    /// * `hash` - Zobrist hash of the position
+    /// * `score` - Static evaluation of the position
+    pub fn store(&mut self, hash: u64, score: i32) {
+        let index = self.index(hash);
+        self.entries[index] = Some(Entry { hash, score });
+    }
+
+    /// Removes all cached evaluations
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+
+    fn index(&self, hash: u64) -> usize {
+        (hash as usize) & (self.entries.len() - 1)
+    }
+}
+
+impl Default for EvalCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_empty_cache() {
+        let cache = EvalCache::new();
+        assert_eq!(cache.probe(12345), None);
+    }
+
+    #[test]
+    fn test_store_and_probe() {
+        let mut cache = EvalCache::new();
+
+        cache.store(12345, 42);
+
+        assert_eq!(cache.probe(12345), Some(42));
+    }
+
+    #[test]
+    fn test_colliding_hash_is_not_returned() {
+        let mut cache = EvalCache::new();
+        let hash = 12345;
+        let colliding_hash = hash + DEFAULT_SIZE as u64;
+
+        cache.store(hash, 42);
+
+        assert_eq!(cache.probe(colliding_hash), None);
+
+        cache.store(colliding_hash, -7);
+
+        assert_eq!(cache.probe(colliding_hash), Some(-7));
+        assert_eq!(cache.probe(hash), None);
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut cache = EvalCache::new();
+
+        cache.store(12345, 42);
+        cache.clear();
+
+        assert_eq!(cache.probe(12345), None);
+    }
+}
@@ -1,17 +1,58 @@
+use crate::bitboard::{BitboardIterator, RANK_1, RANK_8};
 use crate::board::{Board, Position, Castle};
 use crate::pieces::{Piece, Color};
-use crate::square::{Square, algebraic_to_square, rank_file_to_square};
+use crate::square::{Square, algebraic_to_square, rank_file_to_square, square_to_algebraic};
 use core::result::Result;
+use std::fmt;
 
-pub fn fen_to_board(fen: &str) -> Result<Board, String> {
-    let fen_parts: Vec<&str> = fen.split(' ').collect();
+// Reasons a FEN string can fail to parse
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FenError {
+    MissingField(&'static str),
+    InvalidRankCount(usize),
+    InvalidRankLength(u8),
+    InvalidPieceCharacter(char),
+    InvalidActiveColor(String),
+    InvalidCastlingAbility(String),
+    InvalidEnPassantTarget(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveCounter(String),
+    MissingKing(Color),
+    TooManyKings(Color),
+    PawnOnBackRank(String),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::MissingField(field) => write!(f, "missing {} field", field),
+            FenError::InvalidRankCount(count) => write!(f, "expected 8 ranks in piece placement, found {}", count),
+            FenError::InvalidRankLength(rank) => write!(f, "rank {} does not contain exactly 8 squares", rank),
+            FenError::InvalidPieceCharacter(c) => write!(f, "invalid character '{}' in piece placement", c),
+            FenError::InvalidActiveColor(color) => write!(f, "invalid active color '{}'", color),
+            FenError::InvalidCastlingAbility(castling) => write!(f, "invalid castling ability '{}'", castling),
+            FenError::InvalidEnPassantTarget(target) => write!(f, "invalid en passant target '{}'", target),
+            FenError::InvalidHalfmoveClock(clock) => write!(f, "invalid halfmove clock '{}'", clock),
+            FenError::InvalidFullmoveCounter(counter) => write!(f, "invalid fullmove counter '{}'", counter),
+            FenError::MissingKing(color) => write!(f, "no {:?} king", color),
+            FenError::TooManyKings(color) => write!(f, "more than one {:?} king", color),
+            FenError::PawnOnBackRank(square) => write!(f, "pawn on back rank square {}", square),
+        }
+    }
+}
+
+pub fn fen_to_board(fen: &str) -> Result<Board, FenError> {
+    let mut fen_parts = fen.split_whitespace();
+    let mut next_field = |name| fen_parts.next().ok_or(FenError::MissingField(name));
 
-    let position = parse_piece_placement(fen_parts[0])?;
-    let active_color = parse_active_color(fen_parts[1])?;
-    let castling_ability = parse_castling_ability(fen_parts[2])?;
-    let en_passant_target = parse_en_passant_target(fen_parts[3])?;
-    let halfmove_clock = parse_halfmove_clock(fen_parts[4]);
-    let fullmove_counter = parse_fullmove_counter(fen_parts[5]);
+    let position = parse_piece_placement(next_field("piece placement")?)?;
+    let active_color = parse_active_color(next_field("active color")?)?;
+    let castling_ability = parse_castling_ability(next_field("castling ability")?, &position)?;
+    let en_passant_target = parse_en_passant_target(next_field("en passant target")?)?;
+
+    // Some GUIs leave out the move counters, so they default to the start of a game
+    let halfmove_clock = fen_parts.next().map_or(Ok(0), parse_halfmove_clock)?;
+    let fullmove_counter = fen_parts.next().map_or(Ok(1), parse_fullmove_counter)?;
 
     Ok(Board {
         position,
@@ -23,6 +64,66 @@ pub fn fen_to_board(fen: &str) -> Result<Board, String> {
     })
 }
 
+// Writes a board as a FEN, the inverse of fen_to_board
+pub fn board_to_fen(board: &Board) -> String {
+    let mut ranks = Vec::new();
+
+    for rank in (0..8).rev() {
+        let mut placement = String::new();
+        let mut empty = 0;
+
+        for file in 0..8 {
+            let square = rank_file_to_square(rank, file);
+            match (board.get_color_at(square), board.get_piece_at(square)) {
+                (Some(color), Some(piece)) => {
+                    if empty > 0 {
+                        placement.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    placement.push(piece_to_char(color, piece));
+                }
+                _ => empty += 1,
+            }
+        }
+
+        if empty > 0 {
+            placement.push_str(&empty.to_string());
+        }
+        ranks.push(placement);
+    }
+
+    let active_color = match board.active_color() {
+        Color::White => "w",
+        Color::Black => "b",
+    };
+
+    // Rooks that don't start in the corners are named by their file, as in Shredder-FEN
+    let mut castling_ability = String::new();
+    for color in [Color::White, Color::Black] {
+        let (king_side, queen_side) = board.castling_ability(color);
+
+        for (ability, is_king_side, corner, c) in [(king_side, true, 7, 'k'), (queen_side, false, 0, 'q')] {
+            if !ability {
+                continue;
+            }
+
+            let rook_file = board.castling_ability.rook_file(color, is_king_side);
+            let c = if rook_file == corner { c } else { (b'a' + rook_file) as char };
+            castling_ability.push(if color == Color::White { c.to_ascii_uppercase() } else { c });
+        }
+    }
+
+    format!(
+        "{} {} {} {} {} {}",
+        ranks.join("/"),
+        active_color,
+        if castling_ability.is_empty() { "-".to_string() } else { castling_ability },
+        board.en_passant_target.map_or("-".to_string(), square_to_algebraic),
+        board.halfmove_clock,
+        board.fullmove_counter
+    )
+}
+
 /*
 <Piece Placement> ::= <rank8>'/'<rank7>'/'<rank6>'/'<rank5>'/'<rank4>'/'<rank3>'/'<rank2>'/'<rank1>
 <ranki>       ::= [<digit17>]<piece> {[<digit17>]<piece>} [<digit17>] | '8'
@@ -31,18 +132,22 @@ pub fn fen_to_board(fen: &str) -> Result<Board, String> {
 <white Piece> ::= 'P' | 'N' | 'B' | 'R' | 'Q' | 'K'
 <black Piece> ::= 'p' | 'n' | 'b' | 'r' | 'q' | 'k'
  */
-fn parse_piece_placement(piece_placement: &str) -> Result<Position, String> {
+fn parse_piece_placement(piece_placement: &str) -> Result<Position, FenError> {
     let mut position = Position::new();
     let pieces_placement_rank: Vec<&str> = piece_placement.split('/').collect();
 
     if pieces_placement_rank.len() != 8 {
-        return Err("Invalid number of ranks in FEN piece placement".to_string())
+        return Err(FenError::InvalidRankCount(pieces_placement_rank.len()))
     }
 
     for (idx, values) in pieces_placement_rank.iter().enumerate() {
         let rank = 7 - idx as u8;
         let mut file = 0;
         for c in values.chars() {
+            if file >= 8 {
+                return Err(FenError::InvalidRankLength(rank + 1))
+            }
+
             let square = rank_file_to_square(rank, file);
             match c {
                 'p' | 'n' | 'b' | 'r' | 'q' | 'k' | 
@@ -51,37 +156,89 @@ fn parse_piece_placement(piece_placement: &str) -> Result<Position, String> {
                     file += 1;
                 } 
                 '1'..='8' => file += c.to_digit(10).unwrap() as u8,
-                _ => return Err("Invalid character in FEN piece placement".to_string())
+                _ => return Err(FenError::InvalidPieceCharacter(c))
             };
         }
+
+        if file != 8 {
+            return Err(FenError::InvalidRankLength(rank + 1))
+        }
+    }
+
+    // Move generation and evaluation need exactly one king per side to be able to run
+    for color in [Color::White, Color::Black] {
+        match position.bb(color, Piece::King).count_ones() {
+            0 => return Err(FenError::MissingKing(color)),
+            1 => {}
+            _ => return Err(FenError::TooManyKings(color)),
+        }
+    }
+
+    // Pawns promote on reaching the last rank and can't move back to the first
+    let pawns = position.bb(Color::White, Piece::Pawn) | position.bb(Color::Black, Piece::Pawn);
+    if let Some(square) = BitboardIterator::new(pawns & (RANK_1 | RANK_8)).next() {
+        return Err(FenError::PawnOnBackRank(square_to_algebraic(square)));
     }
+
     Ok(position)
 }
 
 /*
 <Side to move> ::= {'w' | 'b'}
  */
-fn parse_active_color(active_color: &str) -> Result<Color, String> {
-    let c = active_color.chars().next().unwrap();
-    match c {
-        'w' => Ok(Color::White),
-        'b' => Ok(Color::Black),
-        _ => Err("Invalid character in FEN active color".to_string())
+fn parse_active_color(active_color: &str) -> Result<Color, FenError> {
+    match active_color {
+        "w" => Ok(Color::White),
+        "b" => Ok(Color::Black),
+        _ => Err(FenError::InvalidActiveColor(active_color.to_string()))
     }
 }
 
 /*
 <Castling ability> ::= '-' | ['K'] ['Q'] ['k'] ['q'] (1..4)
+Chess960 positions can instead name the castling rook's file, Shredder-FEN style, e.g. 'HAha'
  */
-fn parse_castling_ability(castling_ability: &str) -> Result<Castle, String> {
+fn parse_castling_ability(castling_ability: &str, position: &Position) -> Result<Castle, FenError> {
+    let invalid = || FenError::InvalidCastlingAbility(castling_ability.to_string());
+
     if castling_ability.chars().count() > 4 {
-        return Err("Invalid number of castling characters in FEN".to_string())
+        return Err(invalid())
     }
 
     // Rights will be off in the event of '-' and set on accordingly
     let mut castle_rights = Castle::new(false, false, false, false);
+    if castling_ability == "-" {
+        return Ok(castle_rights)
+    }
+
     for c in castling_ability.chars() {
-        castle_rights.set(c, true);
+        let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+        let rank = match color {
+            Color::White => 0,
+            Color::Black => 7,
+        };
+        let is_rook = |file: &u8| position.bb(color, Piece::Rook) & (1 << rank_file_to_square(rank, *file)) != 0;
+        let king_file = (0..8).find(|file| position.bb(color, Piece::King) & (1 << rank_file_to_square(rank, *file)) != 0).unwrap_or(4);
+
+        // 'K' and 'Q' castle with the outermost rook on that side of the king
+        let (king_side, rook_file) = match c.to_ascii_lowercase() {
+            'k' => (true, (king_file + 1..8).rev().find(is_rook).unwrap_or(7)),
+            'q' => (false, (0..king_file).find(is_rook).unwrap_or(0)),
+            file @ 'a'..='h' if file as u8 - b'a' != king_file => {
+                let file = file as u8 - b'a';
+                (file > king_file, file)
+            }
+            _ => return Err(invalid())
+        };
+
+        let right = match (color, king_side) {
+            (Color::White, true) => 'K',
+            (Color::White, false) => 'Q',
+            (Color::Black, true) => 'k',
+            (Color::Black, false) => 'q',
+        };
+        castle_rights.set(right, true);
+        castle_rights.set_rook_file(color, king_side, rook_file);
     }
     Ok(castle_rights)
 }
@@ -92,14 +249,15 @@ fn parse_castling_ability(castling_ability: &str) -> Result<Castle, String> {
 <fileLetter> ::= 'a' | 'b' | 'c' | 'd' | 'e' | 'f' | 'g' | 'h'
 <eprank>     ::= '3' | '6'
  */
-fn parse_en_passant_target(en_passant_target: &str) -> Result<Option<Square>, String> {
-    if en_passant_target.chars().count() > 2 {
-        return Err("Invalid number of en passant target characters in FEN".to_string())
+fn parse_en_passant_target(en_passant_target: &str) -> Result<Option<Square>, FenError> {
+    if en_passant_target == "-" {
+        return Ok(None)
     }
 
-    match en_passant_target.chars().next().unwrap() {
-        '-' => Ok(None),
-        _ => Ok(Some(algebraic_to_square(&en_passant_target[0..2])))
+    let mut chars = en_passant_target.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('a'..='h'), Some('1'..='8'), None) => Ok(Some(algebraic_to_square(en_passant_target))),
+        _ => Err(FenError::InvalidEnPassantTarget(en_passant_target.to_string()))
     }
 }
 
@@ -107,8 +265,8 @@ fn parse_en_passant_target(en_passant_target: &str) -> Result<Option<Square>, St
 <Halfmove Clock> ::= <digit> {<digit>}
 <digit> ::= '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9'
  */
-fn parse_halfmove_clock(halfmove_clock: &str) -> u8 {
-    halfmove_clock.parse().expect("Failed to parse halfmove clock from FEN")
+fn parse_halfmove_clock(halfmove_clock: &str) -> Result<u8, FenError> {
+    halfmove_clock.parse().map_err(|_| FenError::InvalidHalfmoveClock(halfmove_clock.to_string()))
 }
 
 /*
@@ -116,8 +274,8 @@ fn parse_halfmove_clock(halfmove_clock: &str) -> u8 {
 <digit19> ::= '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9'
 <digit>   ::= '0' | <digit19>
  */
-fn parse_fullmove_counter(fullmove_counter: &str) -> u8 {
-    fullmove_counter.parse().expect("Failed to parse fullmove counter from FEN")
+fn parse_fullmove_counter(fullmove_counter: &str) -> Result<u16, FenError> {
+    fullmove_counter.parse().map_err(|_| FenError::InvalidFullmoveCounter(fullmove_counter.to_string()))
 }
 
 pub fn char_to_piece(c: char) -> Piece {
@@ -132,10 +290,154 @@ pub fn char_to_piece(c: char) -> Piece {
     }
 }
 
+// Inverse of char_to_piece and char_to_color, white pieces are uppercase
+pub fn piece_to_char(color: Color, piece: Piece) -> char {
+    let c = match piece {
+        Piece::Pawn => 'p',
+        Piece::Knight => 'n',
+        Piece::Bishop => 'b',
+        Piece::Rook => 'r',
+        Piece::Queen => 'q',
+        Piece::King => 'k',
+    };
+
+    match color {
+        Color::White => c.to_ascii_uppercase(),
+        Color::Black => c,
+    }
+}
+
 pub fn char_to_color(c: char) -> Color {
     if c.is_lowercase() {
         Color::Black
     } else {
         Color::White
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::fen::{fen_to_board, board_to_fen, FenError};
+    use crate::pieces::Color;
+    use crate::square::algebraic_to_square;
+
+    #[test]
+    fn parses_valid_fen() {
+        assert!(fen_to_board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").is_ok());
+    }
+
+    #[test]
+    fn rejects_wrong_rank_count() {
+        let result = fen_to_board("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(result.err(), Some(FenError::InvalidRankCount(7)));
+    }
+
+    #[test]
+    fn rejects_overfull_rank() {
+        let result = fen_to_board("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(result.err(), Some(FenError::InvalidPieceCharacter('9')));
+
+        let result = fen_to_board("rnbqkbnrr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(result.err(), Some(FenError::InvalidRankLength(8)));
+    }
+
+    #[test]
+    fn rejects_bad_piece_character() {
+        let result = fen_to_board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1");
+        assert_eq!(result.err(), Some(FenError::InvalidPieceCharacter('X')));
+    }
+
+    #[test]
+    fn rejects_bad_active_color() {
+        let result = fen_to_board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1");
+        assert_eq!(result.err(), Some(FenError::InvalidActiveColor("x".to_string())));
+    }
+
+    #[test]
+    fn rejects_bad_castling_and_en_passant() {
+        let result = fen_to_board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkx - 0 1");
+        assert_eq!(result.err(), Some(FenError::InvalidCastlingAbility("KQkx".to_string())));
+
+        let result = fen_to_board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq z9 0 1");
+        assert_eq!(result.err(), Some(FenError::InvalidEnPassantTarget("z9".to_string())));
+    }
+
+    #[test]
+    fn rejects_missing_or_extra_kings() {
+        let result = Board::from_fen("8/8/8/8/8/8/8/8 w - - 0 1");
+        assert_eq!(result.err(), Some(FenError::MissingKing(Color::White)));
+
+        let result = Board::from_fen("8/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(result.err(), Some(FenError::MissingKing(Color::Black)));
+
+        let result = Board::from_fen("4k3/8/8/8/8/8/8/3KK3 w - - 0 1");
+        assert_eq!(result.err(), Some(FenError::TooManyKings(Color::White)));
+
+        let result = Board::from_fen("3kk3/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(result.err(), Some(FenError::TooManyKings(Color::Black)));
+    }
+
+    #[test]
+    fn rejects_pawn_on_back_rank() {
+        let result = Board::from_fen("P3k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(result.err(), Some(FenError::PawnOnBackRank("a8".to_string())));
+
+        let result = Board::from_fen("4k3/8/8/8/8/8/8/4K2p b - - 0 1");
+        assert_eq!(result.err(), Some(FenError::PawnOnBackRank("h1".to_string())));
+    }
+
+    #[test]
+    fn rejects_missing_fields() {
+        let result = fen_to_board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w");
+        assert_eq!(result.err(), Some(FenError::MissingField("castling ability")));
+    }
+
+    #[test]
+    fn parses_move_counters() {
+        let board = fen_to_board("8/8/4k3/8/8/4K3/8/8 b - - 42 300").unwrap();
+        assert_eq!(board.halfmove_clock, 42);
+        assert_eq!(board.fullmove_counter, 300);
+    }
+
+    #[test]
+    fn defaults_missing_move_counters() {
+        let board = fen_to_board("8/8/4k3/8/8/4K3/8/8 b - -").unwrap();
+        assert_eq!(board.halfmove_clock, 0);
+        assert_eq!(board.fullmove_counter, 1);
+
+        let board = fen_to_board("8/8/4k3/8/8/4K3/8/8 b - - 5").unwrap();
+        assert_eq!(board.halfmove_clock, 5);
+        assert_eq!(board.fullmove_counter, 1);
+    }
+
+    #[test]
+    fn writes_fen_that_parses_back_the_same() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b Kq - 12 40",
+        ] {
+            assert_eq!(board_to_fen(&fen_to_board(fen).unwrap()), fen);
+        }
+    }
+
+    #[test]
+    fn parses_chess960_castling_rooks() {
+        // Shredder-FEN names the rook files, the queen side rook starts next to the king
+        let board = fen_to_board("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+        assert_eq!(board.castling_rook(Color::White, true), algebraic_to_square("h1"));
+        assert_eq!(board.castling_rook(Color::White, false), algebraic_to_square("f1"));
+        assert_eq!(board.castling_rook(Color::Black, false), algebraic_to_square("f8"));
+
+        // Rooks in the corners are still written as 'K' and 'Q'
+        assert!(board_to_fen(&board).contains(" w KFkf - "));
+
+        // 'K' and 'Q' mean the outermost rook on that side
+        let board = fen_to_board("1r2k1r1/8/8/8/8/8/8/1R2K1R1 w KQkq - 0 1").unwrap();
+        assert_eq!(board.castling_rook(Color::White, true), algebraic_to_square("g1"));
+        assert_eq!(board.castling_rook(Color::Black, false), algebraic_to_square("b8"));
+        assert!(board_to_fen(&board).contains(" w GBgb - "));
+    }
+}
@@ -1,22 +1,36 @@
-use crate::{bitboard::SQUARES, moves::Move};
+use crate::bitboard::SQUARES;
+use crate::moves::Move;
+use crate::pieces::{Color, COLOR_COUNT, PIECE_COUNT};
 
 const SQUARE_COUNT: usize = SQUARES as usize;
 
+/// Largest score an entry can reach before the whole table is halved
+const MAX_HISTORY_SCORE: i32 = 1 << 14;
+
+/// Ordering keys from `ordering_score` range from 0 up to this, keeping quiet
+/// moves below captures, killers and promotions however many cutoffs they cause
+const HISTORY_ORDERING_RANGE: i32 = 399;
+
+type Scores = [[[i32; SQUARE_COUNT]; PIECE_COUNT]; COLOR_COUNT];
+
 /// History heuristic table for move ordering
 ///
 /// The history heuristic tracks which quiet moves have historically
 /// caused beta cutoffs. Moves that frequently cause cutoffs are likely
 /// to be good in similar positions and should be searched earlier.
+///
+/// Scores are indexed by the moving side, piece and destination square, so a
+/// knight and a bishop landing on the same square don't share a score.
 #[derive(Debug, Clone)]
 pub struct HistoryTable {
-    scores: [[i32; SQUARE_COUNT]; SQUARE_COUNT],
+    scores: Scores,
 }
 
 impl HistoryTable {
     /// Creates a new history table
     pub fn new() -> Self {
         Self {
-            scores: [[0; SQUARE_COUNT]; SQUARE_COUNT],
+            scores: [[[0; SQUARE_COUNT]; PIECE_COUNT]; COLOR_COUNT],
         }
     }
 
@@ -26,40 +40,62 @@ impl HistoryTable {
     /// moves that cause cutoffs at deeper search depth as they are
     /// more significant.
     ///
+    /// Once a score passes `MAX_HISTORY_SCORE` the whole table is halved, so
+    /// scores stay bounded while keeping their relative order.
+    ///
     /// # Arguments
+    /// * `color` - The side that played the move
     /// * `mv` - The move that caused the cutoff
     /// * `depth` - The depth at which the cutoff occurred
-    pub fn record_cutoff(&mut self, mv: &Move, depth: u8) {
-        let from = mv.from as usize;
-        let to = mv.to as usize;
-
+    pub fn record_cutoff(&mut self, color: Color, mv: &Move, depth: u8) {
         let increment = (depth as i32) * (depth as i32);
 
-        self.scores[from][to] = self.scores[from][to].saturating_add(increment);
+        let score = self.entry(color, mv);
+        *score = score.saturating_add(increment);
+
+        if *score > MAX_HISTORY_SCORE {
+            self.age();
+        }
     }
 
     /// Gets the history score for a move
     ///
     /// # Arguments
+    /// * `color` - The side playing the move
     /// * `mv` - The move to get the score for
     ///
     /// # Returns
     /// The history score
-    pub fn get_score(&self, mv: &Move) -> i32 {
-        let from = mv.from as usize;
-        let to = mv.to as usize;
+    pub fn get_score(&self, color: Color, mv: &Move) -> i32 {
+        self.scores[color.index()][mv.piece_type.index()][mv.to as usize]
+    }
 
-        self.scores[from][to]
+    /// Gets the history score of a move scaled into `0..=HISTORY_ORDERING_RANGE`
+    /// for use as a move ordering key
+    ///
+    /// # Arguments
+    /// * `color` - The side playing the move
+    /// * `mv` - The move to get the score for
+    pub fn ordering_score(&self, color: Color, mv: &Move) -> i32 {
+        self.get_score(color, mv).min(MAX_HISTORY_SCORE) * HISTORY_ORDERING_RANGE
+            / MAX_HISTORY_SCORE
+    }
+
+    /// Resets all history scores to zero
+    pub fn clear(&mut self) {
+        self.scores = [[[0; SQUARE_COUNT]; PIECE_COUNT]; COLOR_COUNT];
     }
 
     /// Ages all history scores by dividing by 2
     pub fn age(&mut self) {
-        for from in 0..SQUARE_COUNT {
-            for to in 0..SQUARE_COUNT {
-                self.scores[from][to] /= 2;
-            }
+        for score in self.scores.iter_mut().flatten().flatten() {
+            *score /= 2;
         }
     }
+
+    fn entry(&mut self, color: Color, mv: &Move) -> &mut i32 {
+        &mut self.scores[color.index()][mv.piece_type.index()][mv.to as usize]
+    }
 }
 
 impl Default for HistoryTable {
@@ -88,7 +124,7 @@ mod tests {
     fn test_new_table_has_zero_scores() {
         let history = HistoryTable::new();
         let mv = create_test_move(12, 28);
-        assert_eq!(history.get_score(&mv), 0);
+        assert_eq!(history.get_score(Color::White, &mv), 0);
     }
 
     #[test]
@@ -96,11 +132,11 @@ mod tests {
         let mut history = HistoryTable::new();
         let mv = create_test_move(12, 28);
 
-        history.record_cutoff(&mv, 5);
-        assert_eq!(history.get_score(&mv), 25);
+        history.record_cutoff(Color::White, &mv, 5);
+        assert_eq!(history.get_score(Color::White, &mv), 25);
 
-        history.record_cutoff(&mv, 3);
-        assert_eq!(history.get_score(&mv), 34);
+        history.record_cutoff(Color::White, &mv, 3);
+        assert_eq!(history.get_score(Color::White, &mv), 34);
     }
 
     #[test]
@@ -109,10 +145,10 @@ mod tests {
         let mv1 = create_test_move(12, 28);
         let mv2 = create_test_move(6, 21);
 
-        history.record_cutoff(&mv1, 10);
-        history.record_cutoff(&mv2, 5);
+        history.record_cutoff(Color::White, &mv1, 10);
+        history.record_cutoff(Color::White, &mv2, 5);
 
-        assert!(history.get_score(&mv1) > history.get_score(&mv2));
+        assert!(history.get_score(Color::White, &mv1) > history.get_score(Color::White, &mv2));
     }
 
     #[test]
@@ -121,10 +157,68 @@ mod tests {
         let mv1 = create_test_move(12, 28);
         let mv2 = create_test_move(6, 21);
 
-        history.record_cutoff(&mv1, 5);
+        history.record_cutoff(Color::White, &mv1, 5);
+
+        assert_eq!(history.get_score(Color::White, &mv1), 25);
+        assert_eq!(history.get_score(Color::White, &mv2), 0);
+    }
+
+    #[test]
+    fn test_clear_resets_scores() {
+        let mut history = HistoryTable::new();
+        let mv = create_test_move(12, 28);
+
+        history.record_cutoff(Color::White, &mv, 10);
+        history.clear();
 
-        assert_eq!(history.get_score(&mv1), 25);
-        assert_eq!(history.get_score(&mv2), 0);
+        assert_eq!(history.get_score(Color::White, &mv), 0);
+    }
+
+    #[test]
+    fn test_scores_are_per_color_and_piece() {
+        let mut history = HistoryTable::new();
+        let knight_move = Move::new(6, 21, Piece::Knight, MoveType::Quiet);
+        let bishop_move = Move::new(12, 21, Piece::Bishop, MoveType::Quiet);
+        let other_knight_move = Move::new(11, 21, Piece::Knight, MoveType::Quiet);
+
+        history.record_cutoff(Color::White, &knight_move, 5);
+
+        // Any knight reaching the square shares the score, other pieces and Black don't
+        assert_eq!(history.get_score(Color::White, &other_knight_move), 25);
+        assert_eq!(history.get_score(Color::White, &bishop_move), 0);
+        assert_eq!(history.get_score(Color::Black, &knight_move), 0);
+    }
+
+    #[test]
+    fn test_deep_cutoffs_rescale_table() {
+        let mut history = HistoryTable::new();
+        let mv = create_test_move(12, 28);
+        let other = create_test_move(6, 21);
+
+        history.record_cutoff(Color::White, &other, 40);
+        for _ in 0..1000 {
+            history.record_cutoff(Color::White, &mv, 60);
+            assert!(history.get_score(Color::White, &mv) <= MAX_HISTORY_SCORE);
+        }
+
+        // Rescaling halved the other move's score too
+        assert!(history.get_score(Color::White, &other) < 1600);
+        assert!(history.get_score(Color::White, &mv) > history.get_score(Color::White, &other));
+    }
+
+    #[test]
+    fn test_ordering_score_is_bounded() {
+        let mut history = HistoryTable::new();
+        let mv = create_test_move(12, 28);
+
+        assert_eq!(history.ordering_score(Color::White, &mv), 0);
+
+        for _ in 0..1000 {
+            history.record_cutoff(Color::White, &mv, 60);
+            let score = history.ordering_score(Color::White, &mv);
+            assert!((0..=HISTORY_ORDERING_RANGE).contains(&score));
+        }
+        assert!(history.ordering_score(Color::White, &mv) >= HISTORY_ORDERING_RANGE / 2);
     }
 
     #[test]
@@ -132,13 +226,13 @@ mod tests {
         let mut history = HistoryTable::new();
         let mv = create_test_move(12, 28);
 
-        history.record_cutoff(&mv, 10);
-        assert_eq!(history.get_score(&mv), 100);
+        history.record_cutoff(Color::White, &mv, 10);
+        assert_eq!(history.get_score(Color::White, &mv), 100);
 
         history.age();
-        assert_eq!(history.get_score(&mv), 50);
+        assert_eq!(history.get_score(Color::White, &mv), 50);
 
         history.age();
-        assert_eq!(history.get_score(&mv), 25);
+        assert_eq!(history.get_score(Color::White, &mv), 25);
     }
 }
@@ -1,7 +1,5 @@
 use crate::moves::Move;
-
-/// Maximum search depth supported
-const MAX_DEPTH: usize = 64;
+use crate::search::MAX_PLY;
 
 /// Number of killer moves to track per ply
 const KILLERS_PER_PLY: usize = 2;
@@ -9,14 +7,14 @@ const KILLERS_PER_PLY: usize = 2;
 /// Manages killer moves for efficient move ordering
 #[derive(Debug, Clone)]
 pub struct KillerMoves {
-    moves: [[Option<Move>; KILLERS_PER_PLY]; MAX_DEPTH],
+    moves: [[Option<Move>; KILLERS_PER_PLY]; MAX_PLY],
 }
 
 impl KillerMoves {
     /// Creates a new empty killer move table
     pub fn new() -> Self {
         Self {
-            moves: [[None; KILLERS_PER_PLY]; MAX_DEPTH],
+            moves: [[None; KILLERS_PER_PLY]; MAX_PLY],
         }
     }
 
@@ -77,9 +75,8 @@ impl KillerMoves {
     }
 
     /// Clears all killer moves
-    #[allow(dead_code)]
     pub fn clear(&mut self) {
-        self.moves = [[None; KILLERS_PER_PLY]; MAX_DEPTH];
+        self.moves = [[None; KILLERS_PER_PLY]; MAX_PLY];
     }
 
     /// Clears killer moves at a specific ply
@@ -95,7 +92,7 @@ impl KillerMoves {
 
     /// Validates that a ply is within supported bounds
     fn is_valid_ply(&self, ply: u8) -> bool {
-        (ply as usize) < MAX_DEPTH
+        (ply as usize) < MAX_PLY
     }
 
     /// Shifts existing killers and inserts a new one
@@ -265,12 +262,12 @@ mod tests {
         let move1 = create_test_move(8, 16);
 
         // Test at maximum valid ply
-        let max_ply = (MAX_DEPTH - 1) as u8;
+        let max_ply = (MAX_PLY - 1) as u8;
         killers.store(move1, max_ply);
         assert!(killers.is_killer(&move1, max_ply));
 
         // Test beyond maximum
-        let invalid_ply = MAX_DEPTH as u8;
+        let invalid_ply = MAX_PLY as u8;
         killers.store(move1, invalid_ply);
         assert!(!killers.is_killer(&move1, invalid_ply));
     }
@@ -0,0 +1,43 @@
+//! Flounder is a UCI chess engine that can also be embedded as a library.
+//!
+//! [`Searcher::analyze`] searches a [`Board`] and returns the best move along
+//! with its score and principal variation. [`MoveGenerator`] generates legal
+//! moves and [`Evaluator`] gives static evaluations.
+
+mod bench;
+mod bitboard;
+mod board;
+mod epd;
+mod eval;
+mod eval_cache;
+mod fen;
+mod history;
+mod killer_moves;
+mod lookup;
+mod magic;
+mod move_gen;
+mod move_picker;
+mod moves;
+mod pgn;
+mod pieces;
+mod pv;
+mod repetition;
+mod search;
+mod square;
+#[cfg(feature = "syzygy")]
+mod tablebase;
+mod timer;
+mod transposition;
+mod uci;
+mod util;
+mod zobrist;
+
+pub use board::Board;
+pub use eval::{EvalBreakdown, EvalFeatures, EvalWeight, Evaluator};
+pub use move_gen::{GameStatus, MoveGenerator, PerftStats};
+pub use moves::Move;
+pub use search::{SearchLimits, SearchResult, Searcher};
+#[cfg(feature = "syzygy")]
+pub use tablebase::{Tablebase, Wdl};
+pub use timer::TimeLimits;
+pub use uci::Flounder;
@@ -158,17 +158,25 @@ pub struct Magic {
     pub bishop_attacks: Vec<Vec<Bitboard>>,
     pub rook_magics: [u64; 64],
     pub bishop_magics: [u64; 64],
+    /// Index the attack tables with PEXT instead of the magics
+    use_pext: bool,
 }
 
 impl Magic {
+    /// Builds the attack tables, indexed with PEXT if the CPU supports it
     pub fn new() -> Self {
-        // To initialize new magics to Self::init_magics(Piece::Type)
-        // Going to use precomputed magics in BISHOP_MAGICS and ROOK_MAGICS
+        Self::with_pext(pext_supported())
+    }
+
+    fn with_pext(use_pext: bool) -> Self {
+        // The magics were found once with Self::init_magics and embedded in BISHOP_MAGICS
+        // and ROOK_MAGICS, so startup only has to fill the attack tables
         let bishop_magics = BISHOP_MAGICS;
         let rook_magics = ROOK_MAGICS;
         let (bishop_attack_masks, bishop_attacks) =
-            Self::init_slider_attacks(Piece::Bishop, bishop_magics);
-        let (rook_attack_masks, rook_attacks) = Self::init_slider_attacks(Piece::Rook, rook_magics);
+            Self::init_slider_attacks(Piece::Bishop, bishop_magics, use_pext);
+        let (rook_attack_masks, rook_attacks) =
+            Self::init_slider_attacks(Piece::Rook, rook_magics, use_pext);
 
         Self {
             rook_attack_masks,
@@ -177,23 +185,54 @@ impl Magic {
             bishop_attacks,
             rook_magics,
             bishop_magics,
+            use_pext,
         }
     }
 
-    pub fn get_bishop_attacks(&self, square: Square, mut occupancy: Bitboard) -> Bitboard {
-        occupancy &= self.bishop_attack_masks[square as usize];
-        occupancy = occupancy.wrapping_mul(self.bishop_magics[square as usize]);
-        occupancy >>= 64 - BISHOP_RELEVANT_BITS[square as usize];
+    pub fn get_bishop_attacks(&self, square: Square, occupancy: Bitboard) -> Bitboard {
+        let index = Self::attack_index(
+            occupancy,
+            self.bishop_attack_masks[square as usize],
+            self.bishop_magics[square as usize],
+            BISHOP_RELEVANT_BITS[square as usize],
+            self.use_pext,
+        );
 
-        self.bishop_attacks[square as usize][occupancy as usize]
+        self.bishop_attacks[square as usize][index]
     }
 
-    pub fn get_rook_attacks(&self, square: Square, mut occupancy: Bitboard) -> Bitboard {
-        occupancy &= self.rook_attack_masks[square as usize];
-        occupancy = occupancy.wrapping_mul(self.rook_magics[square as usize]);
-        occupancy >>= 64 - ROOK_RELEVANT_BITS[square as usize];
+    pub fn get_rook_attacks(&self, square: Square, occupancy: Bitboard) -> Bitboard {
+        let index = Self::attack_index(
+            occupancy,
+            self.rook_attack_masks[square as usize],
+            self.rook_magics[square as usize],
+            ROOK_RELEVANT_BITS[square as usize],
+            self.use_pext,
+        );
 
-        self.rook_attacks[square as usize][occupancy as usize]
+        self.rook_attacks[square as usize][index]
+    }
+
+    // Maps the blockers on a slider's attack mask to an index into its attack table.
+    // With PEXT the blockers are packed into an index directly, so the magic isn't needed
+    #[inline]
+    fn attack_index(
+        occupancy: Bitboard,
+        mask: Bitboard,
+        magic: u64,
+        relevant_bits: usize,
+        use_pext: bool,
+    ) -> usize {
+        #[cfg(target_arch = "x86_64")]
+        if use_pext {
+            // SAFETY: PEXT is only used once the CPU was detected to support BMI2
+            return unsafe { pext(occupancy, mask) };
+        }
+
+        #[cfg(not(target_arch = "x86_64"))]
+        let _ = use_pext;
+
+        ((occupancy & mask).wrapping_mul(magic) >> (64 - relevant_bits)) as usize
     }
 
     #[allow(dead_code)]
@@ -245,7 +284,7 @@ impl Magic {
 
             // Found a magic number
             if !fail {
-                println!("Magic: 0x{},", format!("{:x}", magic));
+                println!("Magic: 0x{:x},", magic);
                 return magic;
             }
         }
@@ -257,6 +296,7 @@ impl Magic {
     fn init_slider_attacks(
         piece: Piece,
         magics: [u64; 64],
+        use_pext: bool,
     ) -> ([Bitboard; 64], Vec<Vec<Bitboard>>) {
         // Initialize variables to return
         let mut piece_masks = [Bitboard::empty(); 64];
@@ -279,14 +319,31 @@ impl Magic {
                 _ => ROOK_RELEVANT_BITS[square as usize],
             };
 
-            // Create the piece attacks by mapping a certain (occupancy * magic) shifted to the attacks
+            // Create the piece attacks by mapping each occupancy's index to the attacks
             let occupancy_variations = 1 << relevant_bits;
             for i in 0..occupancy_variations {
                 let occupancy = Self::generate_occupancy_board(i, attack_mask);
-                let magic_index =
-                    occupancy.wrapping_mul(magics[square as usize]) >> (64 - relevant_bits);
-                piece_attacks[square as usize][magic_index as usize] =
-                    Self::generate_attack_mask(piece, square, occupancy, true);
+                let index = Self::attack_index(
+                    occupancy,
+                    attack_mask,
+                    magics[square as usize],
+                    relevant_bits,
+                    use_pext,
+                );
+                let attacks = Self::generate_attack_mask(piece, square, occupancy, true);
+
+                // A slider always attacks something, so a filled slot with other attacks
+                // means a bad magic sent two occupancies to the same index
+                let slot = &mut piece_attacks[square as usize][index];
+                assert!(
+                    *slot == Bitboard::empty() || *slot == attacks,
+                    "{:?} magic {:#x} for square {} maps occupancies with different attacks to index {}",
+                    piece,
+                    magics[square as usize],
+                    square,
+                    index
+                );
+                *slot = attacks;
             }
         }
         (piece_masks, piece_attacks)
@@ -452,3 +509,148 @@ impl Magic {
         u1 | (u2 << 16) | (u3 << 32) | (u4 << 48)
     }
 }
+
+#[cfg(target_arch = "x86_64")]
+fn pext_supported() -> bool {
+    is_x86_feature_detected!("bmi2")
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn pext_supported() -> bool {
+    false
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "bmi2")]
+unsafe fn pext(occupancy: Bitboard, mask: Bitboard) -> usize {
+    core::arch::x86_64::_pext_u64(occupancy, mask) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bitboard::{Bitboard, BitboardOperations, SQUARES};
+    use crate::board::Board;
+    use crate::magic::{
+        pext_supported, Magic, BISHOP_MAGICS, BISHOP_RELEVANT_BITS, ROOK_MAGICS, ROOK_RELEVANT_BITS,
+    };
+    use crate::move_gen::MoveGenerator;
+    use crate::pieces::Piece;
+    use crate::square::Square;
+
+    const PERFT_POSITIONS: [&str; 6] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    ];
+
+    // Occupancies of the perft positions and every position one move after them
+    fn perft_occupancies() -> Vec<Bitboard> {
+        let move_gen = MoveGenerator::new();
+        let mut occupancies = Vec::new();
+
+        for fen in PERFT_POSITIONS {
+            let board = Board::new(fen);
+            occupancies.push(board.bb_all());
+
+            for mv in move_gen.generate_moves(&board) {
+                occupancies.push(board.clone_with_move(&mv).bb_all());
+            }
+        }
+        occupancies
+    }
+
+    #[test]
+    #[should_panic(expected = "Rook magic 0x0 for square 0")]
+    fn colliding_magic_panics_when_building_tables() {
+        Magic::init_slider_attacks(Piece::Rook, [0; 64], false);
+    }
+
+    fn relevant_bits(piece: Piece, square: Square) -> usize {
+        match piece {
+            Piece::Bishop => BISHOP_RELEVANT_BITS[square as usize],
+            _ => ROOK_RELEVANT_BITS[square as usize],
+        }
+    }
+
+    // Every attack table entry a magic sends each blocker occupancy of a square to
+    fn magic_attacks(piece: Piece, square: Square, magic: u64) -> Vec<Option<Bitboard>> {
+        let bits = relevant_bits(piece, square);
+        let mask = Magic::generate_attack_mask(piece, square, Bitboard::empty(), false);
+        let mut table = vec![None; 1 << bits];
+
+        for i in 0..1 << bits {
+            let occupancy = Magic::generate_occupancy_board(i, mask);
+            let attacks = Magic::generate_attack_mask(piece, square, occupancy, true);
+            let index = (occupancy.wrapping_mul(magic) >> (64 - bits)) as usize;
+
+            assert!(
+                table[index].is_none_or(|existing| existing == attacks),
+                "{:?} magic {:#x} collides on square {}",
+                piece,
+                magic,
+                square
+            );
+            table[index] = Some(attacks);
+        }
+        table
+    }
+
+    #[test]
+    fn embedded_magics_are_valid_for_every_occupancy() {
+        for square in 0..SQUARES {
+            magic_attacks(Piece::Bishop, square, BISHOP_MAGICS[square as usize]);
+            magic_attacks(Piece::Rook, square, ROOK_MAGICS[square as usize]);
+        }
+    }
+
+    #[test]
+    fn pext_and_magic_lookups_agree() {
+        if !pext_supported() {
+            return;
+        }
+
+        let pext = Magic::with_pext(true);
+        let magic = Magic::with_pext(false);
+
+        for occupancy in perft_occupancies() {
+            for square in 0..SQUARES {
+                assert_eq!(
+                    pext.get_rook_attacks(square, occupancy),
+                    magic.get_rook_attacks(square, occupancy)
+                );
+                assert_eq!(
+                    pext.get_bishop_attacks(square, occupancy),
+                    magic.get_bishop_attacks(square, occupancy)
+                );
+            }
+        }
+    }
+
+    // Checks whichever of the magic or PEXT lookups the CPU supports
+    #[test]
+    fn slider_attacks_match_ray_walk_on_perft_occupancies() {
+        let magic = Magic::new();
+
+        for occupancy in perft_occupancies() {
+            for square in 0..SQUARES {
+                assert_eq!(
+                    magic.get_rook_attacks(square, occupancy),
+                    Magic::generate_rook_attack_mask(square, occupancy, true),
+                    "Rook attacks differ on square {} with occupancy {:#x}",
+                    square,
+                    occupancy
+                );
+                assert_eq!(
+                    magic.get_bishop_attacks(square, occupancy),
+                    Magic::generate_bishop_attack_mask(square, occupancy, true),
+                    "Bishop attacks differ on square {} with occupancy {:#x}",
+                    square,
+                    occupancy
+                );
+            }
+        }
+    }
+}
@@ -1,12 +1,70 @@
 use crate::bitboard::{
-    Bitboard, BitboardIterator, BitboardOperations, BLACK_KING_SIDE, BLACK_QUEEN_SIDE, RANK_2,
-    RANK_3, RANK_6, RANK_7, WHITE_KING_SIDE, WHITE_QUEEN_SIDE,
+    Bitboard, BitboardIterator, BitboardOperations, RANK_2, RANK_3, RANK_6, RANK_7, SQUARES,
 };
-use crate::board::Board;
+use crate::board::{castling_destinations, en_passant_capture_square, Board};
 use crate::lookup::LookupTable;
 use crate::moves::{Move, MoveType, EAST, NORTH, SOUTH, WEST};
 use crate::pieces::{Color, Piece, PromotionPieceIterator};
-use crate::square::{Square, C1, C8, E1, E8, G1, G8};
+use crate::square::Square;
+use crate::zobrist::ZobristTable;
+use std::sync::OnceLock;
+
+/// Generator shared by callers that don't keep their own, built on first use
+static SHARED_MOVE_GENERATOR: OnceLock<MoveGenerator> = OnceLock::new();
+
+/// Pieces whose moves come from the lookup tables, in the order they're generated
+const NON_PAWN_PIECES: [Piece; 5] = [
+    Piece::King,
+    Piece::Knight,
+    Piece::Bishop,
+    Piece::Rook,
+    Piece::Queen,
+];
+
+/// Whether a game is still going, and how it ended if not
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GameStatus {
+    Ongoing,
+    Checkmate,
+    Stalemate,
+    DrawFiftyMove,
+    DrawInsufficientMaterial,
+    DrawRepetition,
+}
+
+/// Leaf counts from `perft_detailed`, broken down by the move that reached each leaf
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PerftStats {
+    pub nodes: usize,
+    /// Captures, including en passants and capturing promotions
+    pub captures: usize,
+    pub en_passants: usize,
+    pub castles: usize,
+    pub promotions: usize,
+    pub checks: usize,
+}
+
+impl std::ops::AddAssign for PerftStats {
+    fn add_assign(&mut self, other: Self) {
+        self.nodes += other.nodes;
+        self.captures += other.captures;
+        self.en_passants += other.en_passants;
+        self.castles += other.castles;
+        self.promotions += other.promotions;
+        self.checks += other.checks;
+    }
+}
+
+impl std::fmt::Display for PerftStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Nodes: {}", self.nodes)?;
+        writeln!(f, "Captures: {}", self.captures)?;
+        writeln!(f, "En passants: {}", self.en_passants)?;
+        writeln!(f, "Castles: {}", self.castles)?;
+        writeln!(f, "Promotions: {}", self.promotions)?;
+        write!(f, "Checks: {}", self.checks)
+    }
+}
 
 pub struct MoveGenerator {
     pub lookup: LookupTable,
@@ -19,44 +77,240 @@ impl MoveGenerator {
         }
     }
 
+    /// Gets a generator shared across the program, so its lookup tables are only built once
+    pub fn shared() -> &'static Self {
+        SHARED_MOVE_GENERATOR.get_or_init(Self::new)
+    }
+
     /// Generates all legal moves for the current position
     pub fn generate_moves(&self, board: &Board) -> Vec<Move> {
+        let checkers = self.checkers(board);
+        if checkers != 0 {
+            return self.generate_evasions(board, checkers);
+        }
+
+        self.generate_all_moves(board, checkers)
+    }
+
+    // Generates the pseudo-legal moves of every piece and filters out illegal ones
+    fn generate_all_moves(&self, board: &Board, checkers: Bitboard) -> Vec<Move> {
         let mut moves = Vec::new();
 
         // Generate moves for each piece type
         self.generate_pseudo_legal_castles(board, &mut moves);
         self.generate_pseudo_legal_pawn_moves(board, &mut moves);
-        self.generate_pseudo_legal_moves(board, Piece::King, &mut moves);
-        self.generate_pseudo_legal_moves(board, Piece::Knight, &mut moves);
-        self.generate_pseudo_legal_moves(board, Piece::Bishop, &mut moves);
-        self.generate_pseudo_legal_moves(board, Piece::Rook, &mut moves);
-        self.generate_pseudo_legal_moves(board, Piece::Queen, &mut moves);
+        for piece in NON_PAWN_PIECES {
+            self.generate_pseudo_legal_moves(board, piece, Bitboard::MAX, &mut moves);
+        }
 
-        // Filter out illegal moves
-        let king_square = self.king_square(board);
-        let pinned_pieces = self.get_pinned_pieces(board, king_square);
-        let checkers = self.attacks_to(board, king_square);
+        self.retain_legal(board, checkers, &mut moves);
+        moves
+    }
+
+    /// Generates legal captures and promotions without generating quiet moves
+    pub fn generate_captures(&self, board: &Board) -> Vec<Move> {
+        let mut moves = Vec::new();
+        let color = board.active_color();
+        let pawns = board.bb(color, Piece::Pawn);
+        let direction = PawnDirection::new(color);
+
+        self.generate_pawn_captures(board, pawns, direction, &mut moves);
+        self.generate_en_passants(board, pawns, direction, &mut moves);
+        self.generate_promotions(board, pawns, direction, &mut moves);
+        for piece in NON_PAWN_PIECES {
+            self.generate_pseudo_legal_moves(board, piece, board.bb_color(!color), &mut moves);
+        }
+
+        self.retain_legal(board, self.checkers(board), &mut moves);
+        moves
+    }
 
-        moves.retain(|mv| self.is_legal(board, mv, checkers, pinned_pieces, king_square));
+    /// Generates the legal moves out of check: king moves, captures of the
+    /// checking piece and moves that block its ray.
+    ///
+    /// Only king moves are generated against a double check, and other pieces
+    /// only generate moves to the squares that answer the check.
+    ///
+    /// # Arguments
+    /// * `board` - A position where the side to move is in check
+    /// * `checkers` - The pieces giving check, see `MoveGenerator::checkers`
+    pub fn generate_evasions(&self, board: &Board, checkers: Bitboard) -> Vec<Move> {
+        let mut moves = Vec::new();
+        self.generate_pseudo_legal_moves(board, Piece::King, Bitboard::MAX, &mut moves);
+
+        if checkers.count_ones() == 1 {
+            let king_square = self.king_square(board);
+            let checker = checkers.trailing_zeros() as Square;
+
+            // The ray is empty for knights and pawns, which can only be captured
+            let king_bb = Bitboard::square_to_bitboard(king_square);
+            let targets = (self.lookup.between(checker, king_square, true) | checkers) & !king_bb;
+
+            // Pawn moves are few enough to filter, and en passant takes a checker
+            // without landing on its square
+            let mut pawn_moves = Vec::new();
+            self.generate_pseudo_legal_pawn_moves(board, &mut pawn_moves);
+            moves.extend(pawn_moves.into_iter().filter(|mv| {
+                mv.move_type == MoveType::EnPassant
+                    || targets & Bitboard::square_to_bitboard(mv.to) != 0
+            }));
+
+            for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+                self.generate_pseudo_legal_moves(board, piece, targets, &mut moves);
+            }
+        }
 
+        self.retain_legal(board, checkers, &mut moves);
         moves
     }
 
-    /// Generates tactical moves for quiescence search (captures, promotions, checks)
-    pub fn generate_quiescence_moves(&self, board: &Board) -> Vec<Move> {
-        let mut moves = self.generate_moves(board);
+    /// Generates tactical moves for quiescence search: captures, promotions and,
+    /// if `include_checks` is set, quiet checks
+    pub fn generate_quiescence_moves(&self, board: &Board, include_checks: bool) -> Vec<Move> {
+        let mut moves = self.generate_captures(board);
 
-        moves.retain(|mv| self.is_capture(mv) || self.is_promotion(mv) || self.is_check(board, mv));
+        // Quiet checks can only be found by trying every quiet move
+        if include_checks {
+            moves.extend(self.generate_moves(board).into_iter().filter(|mv| {
+                !self.is_capture(mv) && !self.is_promotion(mv) && self.is_check(board, mv)
+            }));
+        }
 
         moves
     }
 
+    /// Returns the opponent's pieces giving check to the side to move
+    pub fn checkers(&self, board: &Board) -> Bitboard {
+        self.attacks_to(board, self.king_square(board))
+    }
+
+    /// Adjudicates a position from a game.
+    ///
+    /// Checkmate takes precedence over the draw rules, as a move that mates
+    /// ends the game even if it is also the hundredth without a capture.
+    ///
+    /// # Arguments
+    /// * `board` - Position to adjudicate
+    /// * `position_history` - Zobrist hashes, from `ZobristTable::shared`, of every
+    ///   position in the game including this one
+    pub fn game_status(&self, board: &Board, position_history: &[u64]) -> GameStatus {
+        if self.generate_moves(board).is_empty() {
+            return if self.is_in_check(board) {
+                GameStatus::Checkmate
+            } else {
+                GameStatus::Stalemate
+            };
+        }
+
+        let hash = ZobristTable::shared().hash(board);
+        let is_threefold = || position_history.iter().filter(|&&h| h == hash).count() >= 3;
+
+        if board.is_insufficient_material() {
+            GameStatus::DrawInsufficientMaterial
+        } else if board.is_fifty_move_draw() {
+            GameStatus::DrawFiftyMove
+        } else if is_threefold() {
+            GameStatus::DrawRepetition
+        } else {
+            GameStatus::Ongoing
+        }
+    }
+
     /// Returns true if the current side to move is in check
     pub fn is_in_check(&self, board: &Board) -> bool {
         let king_square = self.king_square(board);
         self.attacks_to(board, king_square) != 0
     }
 
+    /// Checks that a move can be played in a position, e.g. one parsed from UCI.
+    ///
+    /// Only the moving piece's attacks are looked at, so this is much cheaper
+    /// than looking for the move in `generate_moves`.
+    pub fn is_legal_move(&self, board: &Board, mv: &Move) -> bool {
+        if !self.is_pseudo_legal(board, mv) {
+            return false;
+        }
+
+        let king_square = self.king_square(board);
+        self.is_legal(
+            board,
+            mv,
+            self.checkers(board),
+            self.get_pinned_pieces(board, king_square),
+            king_square,
+            self.enemy_attacks(board),
+        )
+    }
+
+    // Checks that the moving piece can reach the target square with the move's type,
+    // ignoring whether it leaves the king in check
+    fn is_pseudo_legal(&self, board: &Board, mv: &Move) -> bool {
+        let color = board.active_color();
+        let from_bb = Bitboard::square_to_bitboard(mv.from);
+        let to_bb = Bitboard::square_to_bitboard(mv.to);
+        let enemy_pieces = board.bb_color(!color);
+        let empty_squares = board.bb_empty();
+
+        // Promotions hold the piece promoted to rather than the pawn
+        let piece = match mv.move_type {
+            MoveType::Promotion => Piece::Pawn,
+            _ => mv.piece_type,
+        };
+        if board.bb(color, piece) & from_bb == 0 {
+            return false;
+        }
+
+        if mv.move_type == MoveType::Castle {
+            let king_side = mv.to > mv.from;
+            let (king_side_rights, queen_side_rights) = board.castling_ability(color);
+            let rights = if king_side {
+                king_side_rights
+            } else {
+                queen_side_rights
+            };
+
+            return rights && self.pseudo_legal_castle(board, king_side) == Some(*mv);
+        }
+
+        let targets = if piece == Piece::Pawn {
+            let direction = PawnDirection::new(color);
+            let push = from_bb.shift(direction.north) & empty_squares;
+            let double_push = (push & direction.rank_3).shift(direction.north) & empty_squares;
+            let attacks =
+                from_bb.shift(direction.north + WEST) | from_bb.shift(direction.north + EAST);
+            let en_passant_target = match board.en_passant_target {
+                Some(square) => Bitboard::square_to_bitboard(square),
+                None => Bitboard::empty(),
+            };
+            let promoting = from_bb & direction.rank_7 != 0;
+
+            match mv.move_type {
+                MoveType::Quiet if !promoting => push | double_push,
+                MoveType::Capture if !promoting => attacks & enemy_pieces,
+                MoveType::EnPassant => attacks & en_passant_target,
+                MoveType::Promotion
+                    if promoting && PromotionPieceIterator::new().any(|p| p == mv.piece_type) =>
+                {
+                    push | (attacks & enemy_pieces)
+                }
+                _ => Bitboard::empty(),
+            }
+        } else {
+            let destinations = match piece {
+                Piece::Knight | Piece::King => self.lookup.non_sliding_moves(mv.from, piece),
+                _ => self.lookup.sliding_moves(mv.from, board.bb_all(), piece),
+            };
+
+            match mv.move_type {
+                MoveType::Quiet => destinations & empty_squares,
+                MoveType::Capture => destinations & enemy_pieces,
+                _ => Bitboard::empty(),
+            }
+        };
+
+        targets & to_bb != 0
+    }
+
     fn generate_pseudo_legal_pawn_moves(&self, board: &Board, moves: &mut Vec<Move>) {
         use crate::pieces::Piece::*;
 
@@ -204,12 +458,7 @@ impl MoveGenerator {
     ) {
         let iter = BitboardIterator::new(bitboard);
         for square in iter {
-            let mv = Move::new(
-                (square as i8 - offset) as u8,
-                square,
-                Piece::Pawn,
-                move_type,
-            );
+            let mv = Move::new(pawn_origin(square, offset), square, Piece::Pawn, move_type);
             moves.push(mv);
         }
     }
@@ -225,7 +474,7 @@ impl MoveGenerator {
         let promotion_pieces = PromotionPieceIterator::new();
         for square in bb_iter {
             for piece in promotion_pieces {
-                let mv = Move::new((square as i8 - offset) as u8, square, piece, move_type);
+                let mv = Move::new(pawn_origin(square, offset), square, piece, move_type);
                 moves.push(mv);
             }
         }
@@ -233,58 +482,53 @@ impl MoveGenerator {
 
     fn generate_pseudo_legal_castles(&self, board: &Board, moves: &mut Vec<Move>) {
         let color = board.active_color();
-        let all_pieces = board.bb_all();
         let (king_side_rights, queen_side_rights) = board.castling_ability(color);
 
-        let (king_side_mask, queen_side_mask) = match color {
-            Color::White => (WHITE_KING_SIDE, WHITE_QUEEN_SIDE),
-            Color::Black => (BLACK_KING_SIDE, BLACK_QUEEN_SIDE),
-        };
-
-        let king_side_occupancy = king_side_mask & all_pieces;
-        let queen_side_occupancy = queen_side_mask & all_pieces;
+        // Castle on each side they have the rights for if nothing blocks
+        if king_side_rights {
+            moves.extend(self.pseudo_legal_castle(board, true));
+        }
 
-        // Castle king side if they have the rights and nothing blocks
-        if king_side_rights && king_side_occupancy == 0 {
-            self.extract_castles(color, Piece::King, MoveType::Castle, moves);
+        if queen_side_rights {
+            moves.extend(self.pseudo_legal_castle(board, false));
         }
+    }
 
-        // Castle queen side if they have the rights and nothing blocks
-        if queen_side_rights && queen_side_occupancy == 0 {
-            self.extract_castles(color, Piece::Queen, MoveType::Castle, moves);
+    // Castles are encoded as the king moving to its rook's square, which works for
+    // Chess960 where the king can already be on the square it castles to
+    fn pseudo_legal_castle(&self, board: &Board, king_side: bool) -> Option<Move> {
+        let color = board.active_color();
+        let king_square = self.king_square(board);
+        let rook_square = board.castling_rook(color, king_side);
+
+        // A FEN can give rights for a rook that isn't there
+        if board.bb(color, Piece::Rook) & Bitboard::square_to_bitboard(rook_square) == 0 {
+            return None;
         }
+
+        // Every square either piece crosses or lands on must be empty, apart from the two of them
+        let (king_to, rook_to) = castling_destinations(color, king_side);
+        let path = self.lookup.between(king_square, king_to, true)
+            | self.lookup.between(rook_square, rook_to, true);
+        let castling_pieces =
+            Bitboard::square_to_bitboard(king_square) | Bitboard::square_to_bitboard(rook_square);
+
+        (path & board.bb_all() & !castling_pieces == 0)
+            .then(|| Move::new(king_square, rook_square, Piece::King, MoveType::Castle))
     }
 
-    fn extract_castles(
+    // Generates moves for one piece type, limited to the squares in `targets`
+    fn generate_pseudo_legal_moves(
         &self,
-        color: Color,
-        side_to_castle: Piece,
-        move_type: MoveType,
+        board: &Board,
+        piece: Piece,
+        targets: Bitboard,
         moves: &mut Vec<Move>,
     ) {
-        let (starting_square, king_side_square, queen_side_square) = match color {
-            Color::White => (E1, G1, C1),
-            Color::Black => (E8, G8, C8),
-        };
-
-        match side_to_castle {
-            Piece::King => {
-                let mv = Move::new(starting_square, king_side_square, Piece::King, move_type);
-                moves.push(mv);
-            }
-            Piece::Queen => {
-                let mv = Move::new(starting_square, queen_side_square, Piece::King, move_type);
-                moves.push(mv);
-            }
-            _ => {} // Only care about King and Queen for king side and queen side castling respectively
-        };
-    }
-
-    fn generate_pseudo_legal_moves(&self, board: &Board, piece: Piece, moves: &mut Vec<Move>) {
         let color = board.active_color();
         let pieces = board.bb(color, piece);
-        let enemy_pieces = board.bb_color(!color);
-        let empty_squares = board.bb_empty();
+        let enemy_pieces = board.bb_color(!color) & targets;
+        let empty_squares = board.bb_empty() & targets;
 
         let iter = BitboardIterator::new(pieces);
         for square in iter {
@@ -316,16 +560,87 @@ impl MoveGenerator {
         }
     }
 
-    // Returns a bitboard with all pieces attacking a certain square
-    pub fn attacks_to(&self, board: &Board, square: Square) -> Bitboard {
+    // Returns a bitboard with all of the opponent's pieces attacking a certain square
+    // Attacks pass through the active player's king, so it can't step back along a slider's ray
+    fn attacks_to(&self, board: &Board, square: Square) -> Bitboard {
         let color = board.active_color();
-        let square_bb = Bitboard::square_to_bitboard(square);
         let occupancy = board.bb_all() & !board.bb(color, Piece::King);
 
-        // Get all attacks from square
-        let pawn_attacks = match color {
-            Color::White => square_bb.shift(NORTH + WEST) | square_bb.shift(NORTH + EAST),
-            Color::Black => square_bb.shift(SOUTH + WEST) | square_bb.shift(SOUTH + EAST),
+        self.attacks_to_with_occupancy(board, square, !color, occupancy)
+    }
+
+    // Returns a bitboard with every square the opponent attacks. Like `attacks_to`,
+    // attacks pass through the active player's king.
+    fn enemy_attacks(&self, board: &Board) -> Bitboard {
+        let color = board.active_color();
+        let occupancy = board.bb_all() & !board.bb(color, Piece::King);
+
+        self.attacked_squares_with_occupancy(board, !color, occupancy)
+    }
+
+    /// Returns a bitboard with every square one color attacks.
+    ///
+    /// This is a single pass over the color's pieces, so it is cheaper than
+    /// asking `attackers_of` about many squares. Squares with a piece of either
+    /// color on them count as attacked, so defended pieces are included.
+    pub fn attacked_squares(&self, board: &Board, color: Color) -> Bitboard {
+        self.attacked_squares_with_occupancy(board, color, board.bb_all())
+    }
+
+    fn attacked_squares_with_occupancy(
+        &self,
+        board: &Board,
+        attacker: Color,
+        occupancy: Bitboard,
+    ) -> Bitboard {
+        let pawns = board.bb(attacker, Piece::Pawn);
+        let mut attacks = match attacker {
+            Color::White => pawns.shift(NORTH + WEST) | pawns.shift(NORTH + EAST),
+            Color::Black => pawns.shift(SOUTH + WEST) | pawns.shift(SOUTH + EAST),
+        };
+
+        for piece in [Piece::Knight, Piece::King] {
+            for square in BitboardIterator::new(board.bb(attacker, piece)) {
+                attacks |= self.lookup.non_sliding_moves(square, piece);
+            }
+        }
+
+        for piece in [Piece::Bishop, Piece::Rook, Piece::Queen] {
+            for square in BitboardIterator::new(board.bb(attacker, piece)) {
+                attacks |= self.lookup.sliding_moves(square, occupancy, piece);
+            }
+        }
+
+        attacks
+    }
+
+    /// Returns a bitboard with all pieces of one color attacking a square.
+    ///
+    /// Pieces of either color can stand on the square, so this also finds a
+    /// piece's defenders.
+    pub fn attackers_of(&self, board: &Board, square: Square, by_color: Color) -> Bitboard {
+        self.attacks_to_with_occupancy(board, square, by_color, board.bb_all())
+    }
+
+    /// Returns true if any piece of one color attacks a square
+    #[allow(dead_code)]
+    pub fn is_square_attacked(&self, board: &Board, square: Square, by_color: Color) -> bool {
+        self.attackers_of(board, square, by_color) != 0
+    }
+
+    fn attacks_to_with_occupancy(
+        &self,
+        board: &Board,
+        square: Square,
+        attacker: Color,
+        occupancy: Bitboard,
+    ) -> Bitboard {
+        let square_bb = Bitboard::square_to_bitboard(square);
+
+        // Get all attacks from square, pawns attack towards the opposite side
+        let pawn_attacks = match attacker {
+            Color::Black => square_bb.shift(NORTH + WEST) | square_bb.shift(NORTH + EAST),
+            Color::White => square_bb.shift(SOUTH + WEST) | square_bb.shift(SOUTH + EAST),
         };
 
         let knight_attacks = self.lookup.non_sliding_moves(square, Piece::Knight);
@@ -335,12 +650,12 @@ impl MoveGenerator {
         let king_attacks = self.lookup.non_sliding_moves(square, Piece::King);
 
         // Get relevant pieces that can attack the square
-        let pawns = pawn_attacks & board.bb(!color, Piece::Pawn);
-        let knights = knight_attacks & board.bb(!color, Piece::Knight);
-        let bishops = bishop_attacks & board.bb(!color, Piece::Bishop);
-        let rooks = rook_attacks & board.bb(!color, Piece::Rook);
-        let king = king_attacks & board.bb(!color, Piece::King);
-        let queens = queen_attacks & board.bb(!color, Piece::Queen);
+        let pawns = pawn_attacks & board.bb(attacker, Piece::Pawn);
+        let knights = knight_attacks & board.bb(attacker, Piece::Knight);
+        let bishops = bishop_attacks & board.bb(attacker, Piece::Bishop);
+        let rooks = rook_attacks & board.bb(attacker, Piece::Rook);
+        let king = king_attacks & board.bb(attacker, Piece::King);
+        let queens = queen_attacks & board.bb(attacker, Piece::Queen);
 
         pawns | knights | bishops | rooks | king | queens
     }
@@ -384,6 +699,24 @@ impl MoveGenerator {
         pinned_pieces
     }
 
+    // Removes the moves that would leave the king in check
+    fn retain_legal(&self, board: &Board, checkers: Bitboard, moves: &mut Vec<Move>) {
+        let king_square = self.king_square(board);
+        let pinned_pieces = self.get_pinned_pieces(board, king_square);
+        let enemy_attacks = self.enemy_attacks(board);
+
+        moves.retain(|mv| {
+            self.is_legal(
+                board,
+                mv,
+                checkers,
+                pinned_pieces,
+                king_square,
+                enemy_attacks,
+            )
+        });
+    }
+
     pub fn king_square(&self, board: &Board) -> Square {
         let color = board.active_color();
         board.bb(color, Piece::King).trailing_zeros() as Square
@@ -396,19 +729,21 @@ impl MoveGenerator {
         checkers: Bitboard,
         pinned_pieces: Bitboard,
         king_square: Square,
+        enemy_attacks: Bitboard,
     ) -> bool {
         let is_castle = mv.move_type == MoveType::Castle;
         let is_king = mv.piece_type == Piece::King;
 
         if is_king && !is_castle {
-            self.is_legal_king_move(board, mv)
+            self.is_legal_king_move(mv, enemy_attacks)
         } else {
             self.is_legal_non_king_move(board, mv, checkers, pinned_pieces, king_square)
         }
     }
 
-    fn is_legal_king_move(&self, board: &Board, mv: &Move) -> bool {
-        self.attacks_to(board, mv.to) == 0
+    // The king can't move to a square the opponent attacks
+    fn is_legal_king_move(&self, mv: &Move, enemy_attacks: Bitboard) -> bool {
+        enemy_attacks & Bitboard::square_to_bitboard(mv.to) == 0
     }
 
     fn is_legal_non_king_move(
@@ -480,10 +815,7 @@ impl MoveGenerator {
         let mut board = *board;
         let color = board.active_color();
 
-        let en_passant_square = match color {
-            Color::White => mv.to - 8,
-            Color::Black => mv.to + 8,
-        };
+        let en_passant_square = en_passant_capture_square(color, mv.to);
 
         let temp_move = Move::new(mv.from, mv.to, Piece::Pawn, MoveType::Capture);
 
@@ -507,25 +839,16 @@ impl MoveGenerator {
         }
 
         let color = board.active_color();
-        let (king_side_square, king_side_checks, queen_side_checks) = match color {
-            Color::White => (G1, vec![5, 6], vec![2, 3]),
-            Color::Black => (G8, vec![61, 62], vec![58, 59]),
-        };
-
-        let is_king_side = mv.to == king_side_square;
+        let (king_to, _) = castling_destinations(color, mv.to > mv.from);
 
-        let squares_to_check = match is_king_side {
-            true => king_side_checks,
-            false => queen_side_checks,
-        };
-
-        for square in squares_to_check {
-            if self.attacks_to(board, square) != 0 {
-                return false;
-            }
-        }
+        // The castling rook leaves its square, so it can't block an attack on the king's path
+        let occupancy = board.bb_all()
+            & !Bitboard::square_to_bitboard(mv.from)
+            & !Bitboard::square_to_bitboard(mv.to);
+        let path =
+            self.lookup.between(mv.from, king_to, true) | Bitboard::square_to_bitboard(king_to);
 
-        true
+        self.attacked_squares_with_occupancy(board, !color, occupancy) & path == 0
     }
 
     fn is_capture(&self, mv: &Move) -> bool {
@@ -541,12 +864,10 @@ impl MoveGenerator {
         self.attacks_to(&new_board, self.king_square(&new_board)) != 0
     }
 
-    #[allow(dead_code)]
     pub fn run_perft(&self, board: &Board, depth: usize) -> usize {
         self.perft(board, depth)
     }
 
-    #[allow(dead_code)]
     fn perft(&self, board: &Board, depth: usize) -> usize {
         let mut nodes = 0;
         let moves = self.generate_moves(board);
@@ -567,7 +888,36 @@ impl MoveGenerator {
         nodes
     }
 
-    #[allow(dead_code)]
+    /// Counts the leaf nodes `depth` moves ahead along with the kind of move that
+    /// reached each one, to compare against published perft breakdowns
+    pub fn perft_detailed(&self, board: &Board, depth: usize) -> PerftStats {
+        let mut stats = PerftStats::default();
+
+        if depth == 0 {
+            stats.nodes = 1;
+            return stats;
+        }
+
+        for mv in self.generate_moves(board) {
+            if depth > 1 {
+                stats += self.perft_detailed(&board.clone_with_move(&mv), depth - 1);
+                continue;
+            }
+
+            // Promotions don't record whether they capture, so look at the target square
+            let captures_on_target = board.get_color_at(mv.to) == Some(!board.active_color());
+
+            stats.nodes += 1;
+            stats.captures += (self.is_capture(&mv) || captures_on_target) as usize;
+            stats.en_passants += (mv.move_type == MoveType::EnPassant) as usize;
+            stats.castles += (mv.move_type == MoveType::Castle) as usize;
+            stats.promotions += self.is_promotion(&mv) as usize;
+            stats.checks += self.is_check(board, &mv) as usize;
+        }
+
+        stats
+    }
+
     pub fn divide(&self, board: &Board, depth: usize) {
         let moves = self.generate_moves(board);
         let mut total = 0;
@@ -585,6 +935,12 @@ impl MoveGenerator {
     }
 }
 
+impl Default for MoveGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Copy, Clone)]
 struct PawnDirection {
     rank_7: Bitboard,
@@ -614,10 +970,378 @@ impl PawnDirection {
     }
 }
 
+// The square a pawn moved from to reach `square`, moving `offset` squares. Pawn move
+// targets come from shifting the pawns themselves, which drops anything shifted off
+// the board or across the a- and h-files, so the origin is always on the board.
+fn pawn_origin(square: Square, offset: i8) -> Square {
+    let origin = square as i8 - offset;
+    debug_assert!(
+        (0..SQUARES as i8).contains(&origin),
+        "pawn moving {} to {} starts off the board",
+        offset,
+        square
+    );
+
+    origin as Square
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::bitboard::{Bitboard, BitboardOperations};
     use crate::board::Board;
-    use crate::move_gen::MoveGenerator;
+    use crate::move_gen::{GameStatus, MoveGenerator, PerftStats};
+    use crate::moves::{Move, MoveType};
+    use crate::pieces::{Color, Piece};
+    use crate::square::{algebraic_to_square, square_to_algebraic};
+    use crate::zobrist::ZobristTable;
+
+    // Every position reachable within `depth` moves, including the start
+    fn positions_within(move_gen: &MoveGenerator, board: &Board, depth: usize) -> Vec<Board> {
+        let mut positions = vec![*board];
+        if depth > 0 {
+            for mv in move_gen.generate_moves(board) {
+                let next = board.clone_with_move(&mv);
+                positions.extend(positions_within(move_gen, &next, depth - 1));
+            }
+        }
+        positions
+    }
+
+    fn sorted_moves(moves: Vec<crate::moves::Move>) -> Vec<String> {
+        let mut moves: Vec<String> = moves.into_iter().map(|mv| mv.to_algebraic()).collect();
+        moves.sort();
+        moves
+    }
+
+    #[test]
+    fn test_captures_and_evasions_match_legal_moves() {
+        let move_gen = MoveGenerator::new();
+        let mut evasion_positions = 0;
+
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ] {
+            for board in positions_within(&move_gen, &Board::new(fen), 2) {
+                let checkers = move_gen.checkers(&board);
+                let legal = move_gen.generate_all_moves(&board, checkers);
+
+                let captures = legal
+                    .iter()
+                    .filter(|mv| move_gen.is_capture(mv) || move_gen.is_promotion(mv))
+                    .copied()
+                    .collect();
+                assert_eq!(
+                    sorted_moves(move_gen.generate_captures(&board)),
+                    sorted_moves(captures),
+                    "{}",
+                    board.to_fen()
+                );
+
+                if checkers != 0 {
+                    evasion_positions += 1;
+                    assert_eq!(
+                        sorted_moves(move_gen.generate_evasions(&board, checkers)),
+                        sorted_moves(legal),
+                        "{}",
+                        board.to_fen()
+                    );
+                }
+            }
+        }
+
+        assert!(evasion_positions > 0);
+    }
+
+    #[test]
+    fn test_is_legal_move_matches_generated_moves() {
+        let move_gen = MoveGenerator::new();
+
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            // Chess960 castles with the king already on its castling square
+            "1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1",
+        ] {
+            for board in positions_within(&move_gen, &Board::new(fen), 1) {
+                let legal = move_gen.generate_moves(&board);
+
+                for from in 0..64 {
+                    for to in 0..64 {
+                        for promotion in ["", "q", "n"] {
+                            let alg = format!(
+                                "{}{}{}",
+                                square_to_algebraic(from),
+                                square_to_algebraic(to),
+                                promotion
+                            );
+                            let Some(mv) = Move::from_algebraic(&alg, &board) else {
+                                continue;
+                            };
+
+                            assert_eq!(
+                                move_gen.is_legal_move(&board, &mv),
+                                legal.contains(&mv),
+                                "{} in {}",
+                                alg,
+                                board.to_fen()
+                            );
+                        }
+                    }
+                }
+
+                for mv in legal {
+                    assert!(move_gen.is_legal_move(&board, &mv), "{:?}", mv);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn promotions_on_edge_files() {
+        let move_gen = MoveGenerator::new();
+        let promotions = |fen: &str| {
+            let moves = move_gen.generate_moves(&Board::new(fen));
+            sorted_moves(
+                moves
+                    .into_iter()
+                    .filter(|mv| {
+                        mv.move_type == MoveType::Promotion && mv.piece_type == Piece::Queen
+                    })
+                    .collect(),
+            )
+        };
+
+        // Pushes and captures from the a- and h-files, where captures only go one way
+        assert_eq!(
+            promotions("1n4n1/P6P/8/8/8/8/8/k3K3 w - - 0 1"),
+            ["a7a8q", "a7b8q", "h7g8q", "h7h8q"]
+        );
+        assert_eq!(
+            promotions("4k2K/8/8/8/8/8/p6p/1N4N1 b - - 0 1"),
+            ["a2a1q", "a2b1q", "h2g1q", "h2h1q"]
+        );
+    }
+
+    #[test]
+    fn en_passant_on_edge_files() {
+        let move_gen = MoveGenerator::new();
+
+        for (fen, mv, after) in [
+            (
+                "4k3/8/8/Pp6/8/8/8/4K3 w - b6 0 1",
+                "a5b6",
+                "4k3/8/1P6/8/8/8/8/4K3 b - - 0 1",
+            ),
+            (
+                "4k3/8/8/6pP/8/8/8/4K3 w - g6 0 1",
+                "h5g6",
+                "4k3/8/6P1/8/8/8/8/4K3 b - - 0 1",
+            ),
+            (
+                "4k3/8/8/8/pP6/8/8/4K3 b - b3 0 1",
+                "a4b3",
+                "4k3/8/8/8/8/1p6/8/4K3 w - - 0 2",
+            ),
+            (
+                "4k3/8/8/8/6Pp/8/8/4K3 b - g3 0 1",
+                "h4g3",
+                "4k3/8/8/8/8/6p1/8/4K3 w - - 0 2",
+            ),
+        ] {
+            let board = Board::new(fen);
+            let en_passant = move_gen
+                .generate_moves(&board)
+                .into_iter()
+                .find(|m| m.move_type == MoveType::EnPassant)
+                .unwrap();
+
+            assert_eq!(en_passant.to_algebraic(), mv);
+            assert_eq!(board.clone_with_move(&en_passant).to_fen(), after);
+        }
+    }
+
+    #[test]
+    fn attacked_squares_match_attackers_of() {
+        let move_gen = MoveGenerator::new();
+
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ] {
+            for board in positions_within(&move_gen, &Board::new(fen), 1) {
+                let enemy_attacks = move_gen.enemy_attacks(&board);
+
+                for color in [Color::White, Color::Black] {
+                    let attacked = move_gen.attacked_squares(&board, color);
+
+                    for square in 0..64 {
+                        let square_bb = Bitboard::square_to_bitboard(square);
+                        assert_eq!(
+                            attacked & square_bb != 0,
+                            move_gen.attackers_of(&board, square, color) != 0,
+                            "{} {}",
+                            board.to_fen(),
+                            square
+                        );
+
+                        if color != board.active_color() {
+                            assert_eq!(
+                                enemy_attacks & square_bb != 0,
+                                move_gen.attacks_to(&board, square) != 0,
+                                "{} {}",
+                                board.to_fen(),
+                                square
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn attackers_of_either_color() {
+        let board =
+            Board::new("r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 0 1");
+        let move_gen = MoveGenerator::new();
+        let square_bb = |alg| Bitboard::square_to_bitboard(algebraic_to_square(alg));
+        let f7 = algebraic_to_square("f7");
+
+        assert_eq!(
+            move_gen.attackers_of(&board, f7, Color::White),
+            square_bb("c4") | square_bb("f3")
+        );
+        assert_eq!(
+            move_gen.attackers_of(&board, f7, Color::Black),
+            square_bb("e8")
+        );
+
+        // Pawns attack towards the opposite side
+        let d5 = algebraic_to_square("d5");
+        assert_eq!(
+            move_gen.attackers_of(&board, d5, Color::White),
+            square_bb("e4") | square_bb("c4")
+        );
+        let d4 = algebraic_to_square("d4");
+        assert_eq!(
+            move_gen.attackers_of(&board, d4, Color::Black),
+            square_bb("e5") | square_bb("c6")
+        );
+    }
+
+    #[test]
+    fn attackers_of_each_piece_type() {
+        let board = Board::new("4k2b/8/5n2/3p4/4P3/2N5/8/R3K3 w - - 0 1");
+        let move_gen = MoveGenerator::new();
+        let square = |alg| algebraic_to_square(alg);
+        let square_bb = |alg| Bitboard::square_to_bitboard(algebraic_to_square(alg));
+
+        // Pawns and knights
+        assert_eq!(
+            move_gen.attackers_of(&board, square("e4"), Color::Black),
+            square_bb("d5") | square_bb("f6")
+        );
+        assert_eq!(
+            move_gen.attackers_of(&board, square("d5"), Color::White),
+            square_bb("e4") | square_bb("c3")
+        );
+        assert!(!move_gen.is_square_attacked(&board, square("e5"), Color::White));
+        assert!(!move_gen.is_square_attacked(&board, square("e3"), Color::Black));
+
+        // Kings
+        assert_eq!(
+            move_gen.attackers_of(&board, square("d2"), Color::White),
+            square_bb("e1")
+        );
+        assert_eq!(
+            move_gen.attackers_of(&board, square("d7"), Color::Black),
+            square_bb("e8") | square_bb("f6")
+        );
+
+        // Sliders, which are blocked by pieces in the way
+        assert_eq!(
+            move_gen.attackers_of(&board, square("a8"), Color::White),
+            square_bb("a1")
+        );
+        assert_eq!(
+            move_gen.attackers_of(&board, square("g7"), Color::Black),
+            square_bb("h8")
+        );
+        assert!(!move_gen.is_square_attacked(&board, square("a1"), Color::Black));
+    }
+
+    #[test]
+    fn game_status_of_finished_games() {
+        let move_gen = MoveGenerator::new();
+        let status = |fen| move_gen.game_status(&Board::new(fen), &[]);
+
+        assert_eq!(
+            status("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3"),
+            GameStatus::Checkmate
+        );
+        assert_eq!(
+            status("k7/8/1Q6/8/8/8/8/7K b - - 0 1"),
+            GameStatus::Stalemate
+        );
+        assert_eq!(
+            status("8/8/4k3/8/8/2N1K3/8/8 w - - 0 1"),
+            GameStatus::DrawInsufficientMaterial
+        );
+        assert_eq!(
+            status("8/8/4k3/8/8/2R1K3/8/8 w - - 100 80"),
+            GameStatus::DrawFiftyMove
+        );
+        assert_eq!(
+            status("8/8/4k3/8/8/2R1K3/8/8 w - - 99 80"),
+            GameStatus::Ongoing
+        );
+
+        // Checkmate and stalemate take precedence over the fifty-move rule
+        assert_eq!(
+            status("7k/5Q2/6K1/8/8/8/8/8 b - - 100 80"),
+            GameStatus::Stalemate
+        );
+        assert_eq!(
+            status("7k/6Q1/6K1/8/8/8/8/8 b - - 100 80"),
+            GameStatus::Checkmate
+        );
+    }
+
+    #[test]
+    fn game_status_counts_repetitions() {
+        let move_gen = MoveGenerator::new();
+        let zobrist = ZobristTable::shared();
+        let mut board = Board::default();
+        let mut history = vec![zobrist.hash(&board)];
+
+        // Knights out and back twice brings the start position up a third time
+        for (idx, alg) in ["g1f3", "g8f6", "f3g1", "f6g8"]
+            .iter()
+            .cycle()
+            .take(8)
+            .enumerate()
+        {
+            assert_eq!(
+                move_gen.game_status(&board, &history),
+                GameStatus::Ongoing,
+                "after {} moves",
+                idx
+            );
+            board.try_make_uci(alg, &move_gen).unwrap();
+            history.push(zobrist.hash(&board));
+        }
+
+        assert_eq!(
+            move_gen.game_status(&board, &history),
+            GameStatus::DrawRepetition
+        );
+        assert_eq!(board.game_status(&history), GameStatus::DrawRepetition);
+    }
 
     // Positions and results can be found here
     // https://www.chessprogramming.org/Perft_Results
@@ -648,6 +1372,36 @@ mod tests {
         assert_eq!(move_gen.run_perft(&board, 5), 193690690);
     }
 
+    #[test]
+    fn perft_detailed_position_2() {
+        let board =
+            Board::new("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        let move_gen = MoveGenerator::new();
+
+        assert_eq!(
+            move_gen.perft_detailed(&board, 1),
+            PerftStats {
+                nodes: 48,
+                captures: 8,
+                en_passants: 0,
+                castles: 2,
+                promotions: 0,
+                checks: 0,
+            }
+        );
+        assert_eq!(
+            move_gen.perft_detailed(&board, 2),
+            PerftStats {
+                nodes: 2039,
+                captures: 351,
+                en_passants: 1,
+                castles: 91,
+                promotions: 0,
+                checks: 3,
+            }
+        );
+    }
+
     #[test]
     fn perft_position_3() {
         let board = Board::new("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1");
@@ -700,4 +1454,45 @@ mod tests {
         assert_eq!(move_gen.run_perft(&board, 4), 3894594);
         assert_eq!(move_gen.run_perft(&board, 5), 164075551);
     }
+
+    #[test]
+    fn perft_chess960_positions() {
+        let move_gen = MoveGenerator::new();
+        let positions = [
+            (
+                "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+                [21, 528, 12189, 326672],
+            ),
+            (
+                "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+                [21, 807, 18002, 667366],
+            ),
+            (
+                "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+                [20, 479, 10471, 273318],
+            ),
+            (
+                "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+                [22, 593, 13440, 382958],
+            ),
+            (
+                "1rqbkrbn/1ppppp1p/1n6/p1N3p1/8/2P4P/PP1PPPP1/1RQBKRBN w FBfb - 0 9",
+                [29, 502, 14569, 287739],
+            ),
+        ];
+
+        for (fen, counts) in positions {
+            let board = Board::new(fen);
+
+            for (depth, count) in counts.into_iter().enumerate() {
+                assert_eq!(
+                    move_gen.run_perft(&board, depth + 1),
+                    count,
+                    "{} depth {}",
+                    fen,
+                    depth + 1
+                );
+            }
+        }
+    }
 }
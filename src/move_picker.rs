@@ -0,0 +1,99 @@
+use crate::moves::Move;
+
+/// Hands out moves in order of their ordering keys, lowest first
+///
+/// Most nodes cut off after the first move or two, so picking the best
+/// remaining move each time does less work than sorting the whole list up
+/// front. Moves with equal keys come out in the order they were given, just
+/// like a stable sort.
+#[derive(Debug, Clone)]
+pub struct MovePicker {
+    moves: Vec<Move>,
+    keys: Vec<i32>,
+    next: usize,
+}
+
+impl MovePicker {
+    /// Creates a picker that hands out `moves` ordered by `key`
+    ///
+    /// # Arguments
+    /// * `moves` - Moves to hand out
+    /// * `key` - Ordering key of a move, moves with lower keys are picked first
+    pub fn new(moves: Vec<Move>, key: impl FnMut(&Move) -> i32) -> Self {
+        let keys = moves.iter().map(key).collect();
+
+        Self {
+            moves,
+            keys,
+            next: 0,
+        }
+    }
+}
+
+impl Iterator for MovePicker {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        let (offset, _) = self.keys[self.next..]
+            .iter()
+            .enumerate()
+            .min_by_key(|&(_, key)| key)?;
+        let best = self.next + offset;
+
+        // Rotating the best move to the front keeps the others in their order
+        self.moves[self.next..=best].rotate_right(1);
+        self.keys[self.next..=best].rotate_right(1);
+
+        let mv = self.moves[self.next];
+        self.next += 1;
+        Some(mv)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.moves.len() - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::MoveType;
+    use crate::pieces::Piece;
+
+    fn create_test_move(from: u8, to: u8) -> Move {
+        Move::new(from, to, Piece::Pawn, MoveType::Quiet)
+    }
+
+    #[test]
+    fn test_picks_lowest_key_first() {
+        let moves: Vec<Move> = (0..5).map(|to| create_test_move(8, 16 + to)).collect();
+        let keys = [3, -1, 7, 0, -5];
+
+        let picked: Vec<Move> =
+            MovePicker::new(moves.clone(), |mv| keys[(mv.to - 16) as usize]).collect();
+
+        let expected: Vec<Move> = [4, 1, 3, 0, 2].iter().map(|&idx| moves[idx]).collect();
+        assert_eq!(picked, expected);
+    }
+
+    #[test]
+    fn test_matches_stable_sort() {
+        let moves: Vec<Move> = (0..40).map(|to| create_test_move(to % 8, to)).collect();
+        let key = |mv: &Move| (mv.to as i32 * 7) % 5 - (mv.from as i32 % 3);
+
+        let mut sorted = moves.clone();
+        sorted.sort_by_cached_key(key);
+
+        let picked: Vec<Move> = MovePicker::new(moves, key).collect();
+        assert_eq!(picked, sorted);
+    }
+
+    #[test]
+    fn test_empty_picker() {
+        let mut picker = MovePicker::new(Vec::new(), |_| 0);
+
+        assert_eq!(picker.size_hint(), (0, Some(0)));
+        assert_eq!(picker.next(), None);
+    }
+}
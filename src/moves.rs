@@ -1,4 +1,9 @@
-use crate::{pieces::Piece, square::square_to_algebraic};
+use crate::board::Board;
+use crate::move_gen::MoveGenerator;
+use crate::pieces::Piece;
+use crate::square::{
+    rank_file_to_square, square_to_algebraic, square_to_rank_file, try_algebraic_to_square,
+};
 
 pub const NORTH: i8 = 8;
 pub const EAST: i8 = 1;
@@ -23,10 +28,187 @@ impl Move {
         }
     }
 
+    /// Parses a move in coordinate notation (e.g. `e2e4`, `e7e8q`) for the given position.
+    ///
+    /// The piece and move type are inferred from the board. Returns `None` if the string is
+    /// malformed or doesn't describe a move of one of the side to move's pieces. The move is
+    /// not checked for full legality, e.g. it may leave the king in check.
+    pub fn from_algebraic(s: &str, board: &Board) -> Option<Move> {
+        if !s.is_ascii() || !(s.len() == 4 || s.len() == 5) {
+            return None;
+        }
+
+        let from = try_algebraic_to_square(&s[0..2])?;
+        let to = try_algebraic_to_square(&s[2..4])?;
+        let color = board.active_color();
+
+        if board.get_color_at(from) != Some(color) {
+            return None;
+        }
+
+        let piece = board.get_piece_at(from)?;
+
+        // In Chess960 castles are sent as the king capturing its own rook
+        if board.get_color_at(to) == Some(color) {
+            let onto_rook = piece == Piece::King && board.get_piece_at(to) == Some(Piece::Rook);
+            return onto_rook.then(|| Move::new(from, to, piece, MoveType::Castle));
+        }
+
+        if let Some(promotion_char) = s.chars().nth(4) {
+            let promotion_piece = match promotion_char {
+                'q' => Piece::Queen,
+                'r' => Piece::Rook,
+                'b' => Piece::Bishop,
+                'n' => Piece::Knight,
+                _ => return None,
+            };
+
+            if piece != Piece::Pawn {
+                return None;
+            }
+
+            return Some(Move::new(from, to, promotion_piece, MoveType::Promotion));
+        }
+
+        // Otherwise they're sent as the king moving two squares, and moves use the rook's square
+        if piece == Piece::King && from.abs_diff(to) == 2 {
+            let rook = board.castling_rook(color, to > from);
+            return Some(Move::new(from, rook, piece, MoveType::Castle));
+        }
+
+        let move_type = if piece == Piece::Pawn && board.en_passant_target == Some(to) {
+            MoveType::EnPassant
+        } else if board.get_color_at(to) == Some(!color) {
+            MoveType::Capture
+        } else {
+            MoveType::Quiet
+        };
+
+        Some(Move::new(from, to, piece, move_type))
+    }
+
+    /// Parses a move in standard algebraic notation (e.g. `Nf3`, `exd5`, `O-O`, `e8=Q+`)
+    /// for the given position.
+    ///
+    /// Check, mate and annotation suffixes are ignored. Returns `None` if no legal move
+    /// has the given notation.
+    #[allow(dead_code)]
+    pub fn from_san(s: &str, board: &Board) -> Option<Move> {
+        let san = strip_san_suffixes(s);
+
+        MoveGenerator::shared()
+            .generate_moves(board)
+            .into_iter()
+            .find(|mv| strip_san_suffixes(&mv.to_san(board)) == san)
+    }
+
+    /// Formats a legal move in standard algebraic notation, e.g. `Nbd2`, `exd5`, `O-O`
+    /// or `e8=Q#`.
+    #[allow(dead_code)]
+    pub fn to_san(self, board: &Board) -> String {
+        let move_gen = MoveGenerator::shared();
+
+        let mut san = if self.move_type == MoveType::Castle {
+            if self.to > self.from { "O-O" } else { "O-O-O" }.to_string()
+        } else {
+            let is_capture = matches!(self.move_type, MoveType::Capture | MoveType::EnPassant)
+                || board.get_piece_at(self.to).is_some();
+            let capture = if is_capture { "x" } else { "" };
+            let to = square_to_algebraic(self.to);
+
+            if self.piece_type == Piece::Pawn || self.move_type == MoveType::Promotion {
+                // Pawn captures name the file they come from
+                let from_file = if is_capture {
+                    &square_to_algebraic(self.from)[..1]
+                } else {
+                    ""
+                };
+                let promotion = if self.move_type == MoveType::Promotion {
+                    format!("={}", piece_to_san_char(self.piece_type))
+                } else {
+                    String::new()
+                };
+
+                format!("{}{}{}{}", from_file, capture, to, promotion)
+            } else {
+                format!(
+                    "{}{}{}{}",
+                    piece_to_san_char(self.piece_type),
+                    self.disambiguation(board, move_gen),
+                    capture,
+                    to
+                )
+            }
+        };
+
+        let next_board = board.clone_with_move(&self);
+        if move_gen.is_in_check(&next_board) {
+            if move_gen.generate_moves(&next_board).is_empty() {
+                san.push('#');
+            } else {
+                san.push('+');
+            }
+        }
+
+        san
+    }
+
+    /// The part of the from square needed to tell this move apart from other moves of
+    /// the same piece type to the same square: the file if that's enough, then the rank,
+    /// then both
+    fn disambiguation(&self, board: &Board, move_gen: &MoveGenerator) -> String {
+        let others: Vec<Move> = move_gen
+            .generate_moves(board)
+            .into_iter()
+            .filter(|mv| {
+                // Promotions are pawn moves even though their piece type is the new piece
+                mv.piece_type == self.piece_type
+                    && mv.move_type != MoveType::Promotion
+                    && mv.to == self.to
+                    && mv.from != self.from
+            })
+            .collect();
+
+        if others.is_empty() {
+            return String::new();
+        }
+
+        let from = square_to_algebraic(self.from);
+        let (from_rank, from_file) = square_to_rank_file(self.from);
+
+        if others
+            .iter()
+            .all(|mv| square_to_rank_file(mv.from).1 != from_file)
+        {
+            from[..1].to_string()
+        } else if others
+            .iter()
+            .all(|mv| square_to_rank_file(mv.from).0 != from_rank)
+        {
+            from[1..].to_string()
+        } else {
+            from
+        }
+    }
+
     pub fn print(&self) {
         print!("{}", self.to_algebraic());
     }
 
+    /// Formats the move in coordinate notation for a GUI. Castles are written as the
+    /// king moving to its rook's square in Chess960, and two squares otherwise.
+    pub fn to_uci(self, chess960: bool) -> String {
+        if chess960 && self.move_type == MoveType::Castle {
+            format!(
+                "{}{}",
+                square_to_algebraic(self.from),
+                square_to_algebraic(self.to)
+            )
+        } else {
+            self.to_algebraic()
+        }
+    }
+
     pub fn to_algebraic(self) -> String {
         let mut promotion_piece_char = "";
         if self.move_type == MoveType::Promotion {
@@ -38,16 +220,58 @@ impl Move {
                 _ => "",
             }
         }
+        // Castles are stored as the king moving to its rook, but written as it moving two squares
+        let to = if self.move_type == MoveType::Castle {
+            let (rank, _) = square_to_rank_file(self.from);
+            rank_file_to_square(rank, if self.to > self.from { 6 } else { 2 })
+        } else {
+            self.to
+        };
+
         let algebreaic = format!(
             "{}{}{}",
             square_to_algebraic(self.from),
-            square_to_algebraic(self.to),
+            square_to_algebraic(to),
             promotion_piece_char
         );
         algebreaic
     }
 }
 
+fn piece_to_san_char(piece: Piece) -> char {
+    match piece {
+        Piece::Pawn => 'P',
+        Piece::Knight => 'N',
+        Piece::Bishop => 'B',
+        Piece::Rook => 'R',
+        Piece::Queen => 'Q',
+        Piece::King => 'K',
+    }
+}
+
+// Removes check, mate and annotation marks like `+`, `#` or `!?` from a SAN move
+fn strip_san_suffixes(san: &str) -> &str {
+    san.trim_end_matches(['+', '#', '!', '?'])
+}
+
+/// Reasons a move from the GUI can't be played
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MoveError {
+    /// Not coordinate notation, or not a move of one of the side to move's pieces
+    Malformed(String),
+    /// Well-formed, but not legal in the position, e.g. it leaves the king in check
+    Illegal(String),
+}
+
+impl std::fmt::Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveError::Malformed(mv) => write!(f, "malformed move '{}'", mv),
+            MoveError::Illegal(mv) => write!(f, "illegal move '{}'", mv),
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Copy, Clone, Debug)]
 pub enum MoveType {
     Quiet,   // Non-capturing move
@@ -70,3 +294,109 @@ impl std::fmt::Display for MoveType {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::moves::{Move, MoveType};
+    use crate::pieces::Piece;
+
+    #[test]
+    fn parses_quiet_and_double_pawn_push() {
+        let board = Board::default();
+
+        let mv = Move::from_algebraic("e2e4", &board).unwrap();
+        assert_eq!(mv, Move::new(12, 28, Piece::Pawn, MoveType::Quiet));
+
+        let mv = Move::from_algebraic("g1f3", &board).unwrap();
+        assert_eq!(mv, Move::new(6, 21, Piece::Knight, MoveType::Quiet));
+    }
+
+    #[test]
+    fn parses_capture_en_passant_and_castle() {
+        let board = Board::new("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1");
+
+        let mv = Move::from_algebraic("e5d6", &board).unwrap();
+        assert_eq!(mv.move_type, MoveType::EnPassant);
+
+        let mv = Move::from_algebraic("a1a8", &board).unwrap();
+        assert_eq!(mv, Move::new(0, 56, Piece::Rook, MoveType::Capture));
+
+        let mv = Move::from_algebraic("e1g1", &board).unwrap();
+        assert_eq!(mv, Move::new(4, 7, Piece::King, MoveType::Castle));
+        assert_eq!(mv.to_algebraic(), "e1g1");
+        assert_eq!(mv.to_uci(true), "e1h1");
+
+        let mv = Move::from_algebraic("e1c1", &board).unwrap();
+        assert_eq!(mv.move_type, MoveType::Castle);
+    }
+
+    #[test]
+    fn parses_promotions() {
+        let board = Board::new("1n5k/P7/8/8/8/8/8/K7 w - - 0 1");
+
+        let mv = Move::from_algebraic("a7a8q", &board).unwrap();
+        assert_eq!(mv, Move::new(48, 56, Piece::Queen, MoveType::Promotion));
+
+        let mv = Move::from_algebraic("a7b8n", &board).unwrap();
+        assert_eq!(mv, Move::new(48, 57, Piece::Knight, MoveType::Promotion));
+
+        assert_eq!(mv.to_algebraic(), "a7b8n");
+    }
+
+    #[test]
+    fn rejects_malformed_or_impossible_moves() {
+        let board = Board::default();
+
+        assert_eq!(Move::from_algebraic("", &board), None);
+        assert_eq!(Move::from_algebraic("e2", &board), None);
+        assert_eq!(Move::from_algebraic("e2e9", &board), None);
+        assert_eq!(Move::from_algebraic("e2e4x", &board), None);
+        assert_eq!(Move::from_algebraic("g1e2q", &board), None);
+        // Empty square, opponent's piece and capturing own piece
+        assert_eq!(Move::from_algebraic("e4e5", &board), None);
+        assert_eq!(Move::from_algebraic("e7e5", &board), None);
+        assert_eq!(Move::from_algebraic("d1d2", &board), None);
+    }
+
+    #[test]
+    fn formats_san() {
+        let board = Board::new("r3k2r/1P6/8/3pP3/8/2N3N1/8/R3K2R w KQkq d6 0 1");
+        let san = |alg| Move::from_algebraic(alg, &board).unwrap().to_san(&board);
+
+        assert_eq!(san("e5e6"), "e6");
+        assert_eq!(san("e5d6"), "exd6");
+        assert_eq!(san("b7b8q"), "b8=Q+");
+        assert_eq!(san("b7a8n"), "bxa8=N");
+        assert_eq!(san("e1g1"), "O-O");
+        assert_eq!(san("e1c1"), "O-O-O");
+        assert_eq!(san("c3e4"), "Nce4");
+        assert_eq!(san("a1a8"), "Rxa8+");
+        assert_eq!(san("h1h8"), "Rxh8+");
+    }
+
+    #[test]
+    fn formats_san_rank_disambiguation_and_mate() {
+        let board = Board::new("6k1/R4ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let san = |alg| Move::from_algebraic(alg, &board).unwrap().to_san(&board);
+
+        assert_eq!(san("a1a4"), "R1a4");
+        assert_eq!(san("a7a4"), "R7a4");
+        assert_eq!(san("a7a8"), "Ra8#");
+    }
+
+    #[test]
+    fn parses_san() {
+        let board = Board::default();
+
+        assert_eq!(
+            Move::from_san("Nf3", &board),
+            Move::from_algebraic("g1f3", &board)
+        );
+        assert_eq!(
+            Move::from_san("e4!?", &board),
+            Move::from_algebraic("e2e4", &board)
+        );
+        assert_eq!(Move::from_san("Nd2", &board), None);
+        assert_eq!(Move::from_san("Ke2", &board), None);
+    }
+}
@@ -0,0 +1,153 @@
+use crate::board::Board;
+use crate::move_gen::MoveGenerator;
+use crate::moves::Move;
+use crate::pieces::Color;
+
+/// Longest line of movetext before wrapping, as recommended by the PGN standard
+const MAX_LINE_LENGTH: usize = 79;
+
+/// Formats a game as PGN with the seven standard tags and SAN moves.
+///
+/// Games that don't start from the initial position also get `SetUp` and `FEN`
+/// tags. The result is taken from the final position, so a game that hasn't
+/// ended by checkmate or stalemate is `*`.
+///
+/// # Arguments
+/// * `start` - The position the game started from
+/// * `start_fen` - FEN of the start position, `None` for the initial position
+/// * `moves` - Legal moves played from the start position
+pub fn to_pgn(start: &Board, start_fen: Option<&str>, moves: &[Move]) -> String {
+    let mut board = *start;
+    let mut tokens = Vec::new();
+
+    for (idx, mv) in moves.iter().enumerate() {
+        if board.active_color() == Color::White {
+            tokens.push(format!("{}.", board.fullmove_counter));
+        } else if idx == 0 {
+            // A game starting with Black's move shows the move number with an ellipsis
+            tokens.push(format!("{}...", board.fullmove_counter));
+        }
+
+        tokens.push(mv.to_san(&board));
+        board.make_move(mv);
+    }
+
+    let result = game_result(&board);
+    tokens.push(result.to_string());
+
+    let mut pgn = String::new();
+    for (tag, value) in [
+        ("Event", "?"),
+        ("Site", "?"),
+        ("Date", "????.??.??"),
+        ("Round", "?"),
+        ("White", "?"),
+        ("Black", "?"),
+        ("Result", result),
+    ] {
+        pgn.push_str(&format!("[{} \"{}\"]\n", tag, value));
+    }
+
+    if let Some(fen) = start_fen {
+        pgn.push_str("[SetUp \"1\"]\n");
+        pgn.push_str(&format!("[FEN \"{}\"]\n", fen));
+    }
+
+    pgn.push('\n');
+    pgn.push_str(&wrap_movetext(&tokens));
+    pgn
+}
+
+/// The result tag for a position: decisive on checkmate, drawn on stalemate,
+/// otherwise still in progress
+fn game_result(board: &Board) -> &'static str {
+    let move_gen = MoveGenerator::shared();
+
+    if !move_gen.generate_moves(board).is_empty() {
+        return "*";
+    }
+
+    match (move_gen.is_in_check(board), board.active_color()) {
+        (true, Color::White) => "0-1",
+        (true, Color::Black) => "1-0",
+        (false, _) => "1/2-1/2",
+    }
+}
+
+// Joins movetext tokens with spaces, starting a new line before one would get too long
+fn wrap_movetext(tokens: &[String]) -> String {
+    let mut text = String::new();
+    let mut line_length = 0;
+
+    for token in tokens {
+        if line_length > 0 && line_length + 1 + token.len() > MAX_LINE_LENGTH {
+            text.push('\n');
+            line_length = 0;
+        } else if line_length > 0 {
+            text.push(' ');
+            line_length += 1;
+        }
+
+        text.push_str(token);
+        line_length += token.len();
+    }
+
+    text.push('\n');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(board: &Board, moves: &[&str]) -> Vec<Move> {
+        let mut board = *board;
+        moves
+            .iter()
+            .map(|alg| {
+                let mv = Move::from_algebraic(alg, &board).unwrap();
+                board.make_move(&mv);
+                mv
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_formats_finished_game() {
+        let board = Board::default();
+        let moves = play(&board, &["f2f3", "e7e5", "g2g4", "d8h4"]);
+
+        let pgn = to_pgn(&board, None, &moves);
+
+        assert!(pgn.starts_with("[Event \"?\"]\n"));
+        assert!(pgn.contains("[Result \"0-1\"]\n"));
+        assert!(!pgn.contains("[FEN"));
+        assert!(pgn.ends_with("\n\n1. f3 e5 2. g4 Qh4# 0-1\n"));
+    }
+
+    #[test]
+    fn test_formats_game_from_fen_with_black_to_move() {
+        let fen = "k7/8/1K6/8/8/8/8/7Q b - - 10 40";
+        let board = Board::new(fen);
+        let moves = play(&board, &["a8b8", "h1h7", "b8a8"]);
+
+        let pgn = to_pgn(&board, Some(fen), &moves);
+
+        assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"k7/8/1K6/8/8/8/8/7Q b - - 10 40\"]\n"));
+        assert!(pgn.contains("[Result \"*\"]\n"));
+        assert!(pgn.ends_with("\n\n40... Kb8 41. Qh7 Ka8 *\n"));
+    }
+
+    #[test]
+    fn test_wraps_long_movetext() {
+        let board = Board::default();
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        let moves: Vec<&str> = shuffle.iter().cycle().take(40).copied().collect();
+
+        let pgn = to_pgn(&board, None, &play(&board, &moves));
+        let movetext = pgn.split("\n\n").nth(1).unwrap();
+
+        assert!(movetext.lines().count() > 1);
+        assert!(movetext.lines().all(|line| line.len() <= MAX_LINE_LENGTH));
+    }
+}
@@ -0,0 +1,136 @@
+use crate::moves::Move;
+use crate::search::MAX_PLY;
+
+/// Tracks the principal variation, the line of best play found by the search
+///
+/// Uses a triangular table where the line at each ply is the move
+/// found there followed by the line from the ply below it.
+#[derive(Debug, Clone)]
+pub struct PvTable {
+    lines: [[Option<Move>; MAX_PLY]; MAX_PLY],
+    lengths: [usize; MAX_PLY],
+}
+
+impl PvTable {
+    /// Creates a new empty table
+    pub fn new() -> Self {
+        Self {
+            lines: [[None; MAX_PLY]; MAX_PLY],
+            lengths: [0; MAX_PLY],
+        }
+    }
+
+    /// Clears the line at a ply, called when a node starts being searched
+    ///
+    /// # Arguments
+    /// * `ply` - The ply of the node
+    pub fn clear_ply(&mut self, ply: u8) {
+        if self.is_valid_ply(ply) {
+            self.lengths[ply as usize] = 0;
+        }
+    }
+
+    /// Sets the line at a ply to a new best move followed by the line below it
+    ///
+    /// # Arguments
+    /// * `mv` - The new best move at this ply
+    /// * `ply` - The ply where the move was found
+    pub fn update(&mut self, mv: Move, ply: u8) {
+        if !self.is_valid_ply(ply) {
+            return;
+        }
+
+        let ply_idx = ply as usize;
+        self.lines[ply_idx][0] = Some(mv);
+
+        let child_length = if self.is_valid_ply(ply + 1) {
+            self.lengths[ply_idx + 1].min(MAX_PLY - 1)
+        } else {
+            0
+        };
+
+        for i in 0..child_length {
+            self.lines[ply_idx][i + 1] = self.lines[ply_idx + 1][i];
+        }
+
+        self.lengths[ply_idx] = child_length + 1;
+    }
+
+    /// Gets the line found from the root
+    pub fn root_line(&self) -> Vec<Move> {
+        self.lines[0][..self.lengths[0]]
+            .iter()
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    /// Validates that a ply is within supported bounds
+    fn is_valid_ply(&self, ply: u8) -> bool {
+        (ply as usize) < MAX_PLY
+    }
+}
+
+impl Default for PvTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::MoveType;
+    use crate::pieces::Piece;
+
+    #[test]
+    fn test_new_table_has_empty_line() {
+        let pv = PvTable::new();
+        assert!(pv.root_line().is_empty());
+    }
+
+    #[test]
+    fn test_update_builds_line_from_child() {
+        let mut pv = PvTable::new();
+        let move1 = create_test_move(12, 28);
+        let move2 = create_test_move(52, 36);
+        let move3 = create_test_move(6, 21);
+
+        pv.clear_ply(2);
+        pv.update(move3, 2);
+        pv.update(move2, 1);
+        pv.update(move1, 0);
+
+        assert_eq!(pv.root_line(), vec![move1, move2, move3]);
+    }
+
+    #[test]
+    fn test_cleared_child_ends_line() {
+        let mut pv = PvTable::new();
+        let move1 = create_test_move(12, 28);
+        let move2 = create_test_move(52, 36);
+
+        pv.update(move2, 1);
+        pv.clear_ply(1);
+        pv.update(move1, 0);
+
+        assert_eq!(pv.root_line(), vec![move1]);
+    }
+
+    #[test]
+    fn test_invalid_ply_bounds() {
+        let mut pv = PvTable::new();
+        let move1 = create_test_move(12, 28);
+
+        // Should handle gracefully without panicking
+        pv.clear_ply(255);
+        pv.update(move1, 255);
+        pv.update(move1, (MAX_PLY - 1) as u8);
+
+        assert!(pv.root_line().is_empty());
+    }
+
+    fn create_test_move(from: u8, to: u8) -> Move {
+        Move::new(from, to, Piece::Pawn, MoveType::Quiet)
+    }
+}
@@ -2,6 +2,8 @@
 #[derive(Debug, Clone)]
 pub struct RepetitionTable {
     hashes: Vec<u64>,
+    /// Index of the search's root position, the ones before it were played in the game
+    root: usize,
 }
 
 impl RepetitionTable {
@@ -9,6 +11,7 @@ impl RepetitionTable {
     pub fn new() -> Self {
         Self {
             hashes: Vec::with_capacity(256),
+            root: 0,
         }
     }
 
@@ -25,6 +28,63 @@ impl RepetitionTable {
         self.hashes.pop();
     }
 
+    /// Marks the next position pushed as the root of a search, so positions
+    /// from the search can be told apart from the game's
+    pub fn set_root(&mut self) {
+        self.root = self.hashes.len();
+    }
+
+    /// Counts how often a position has occurred, as the number of times it was
+    /// played in the game before the root and the number of times it came up
+    /// on the line searched from the root.
+    ///
+    /// # Arguments
+    /// * `current_hash` - The zobrist hash to count
+    /// * `reversible_plies` - Plies since the last capture or pawn move, as no
+    ///   position before that can come up again
+    pub fn occurrences(&self, current_hash: u64, reversible_plies: usize) -> (usize, usize) {
+        let start = self.hashes.len().saturating_sub(reversible_plies);
+        let mut game_count = 0;
+        let mut search_count = 0;
+
+        for (idx, &hash) in self.hashes.iter().enumerate().skip(start) {
+            if hash != current_hash {
+                continue;
+            }
+
+            if idx >= self.root {
+                search_count += 1;
+            } else {
+                game_count += 1;
+            }
+        }
+
+        (game_count, search_count)
+    }
+
+    /// Checks if a position reached in the search should be scored as a draw.
+    ///
+    /// The search and the game count repetitions differently. Repeating any
+    /// position since the root is a draw, so a twofold repetition is enough, as
+    /// whoever could avoid it was happy to repeat it once and can do so again.
+    /// A position played in the game before the root follows the real rule and
+    /// has to have occurred `game_occurrences` times, two for a threefold.
+    ///
+    /// # Arguments
+    /// * `current_hash` - The zobrist hash to check for repetition
+    /// * `reversible_plies` - Plies since the last capture or pawn move, as no
+    ///   position before that can come up again
+    /// * `game_occurrences` - How often a game position has to have occurred
+    pub fn is_draw(
+        &self,
+        current_hash: u64,
+        reversible_plies: usize,
+        game_occurrences: usize,
+    ) -> bool {
+        let (game_count, search_count) = self.occurrences(current_hash, reversible_plies);
+        search_count > 0 || game_count >= game_occurrences
+    }
+
     /// Checks if a position has been repeated
     ///
     /// # Arguments
@@ -32,6 +92,7 @@ impl RepetitionTable {
     ///
     /// # Returns
     /// `true` if three-fold repetition is detected and `false` otherwise
+    #[allow(dead_code)]
     pub fn is_repetition(&self, current_hash: u64) -> bool {
         let mut count = 0;
 
@@ -47,6 +108,21 @@ impl RepetitionTable {
         false
     }
 
+    /// Checks if a position has occurred at all
+    ///
+    /// # Arguments
+    /// * `current_hash` - The zobrist hash to look for
+    #[allow(dead_code)]
+    pub fn contains(&self, current_hash: u64) -> bool {
+        self.hashes.contains(&current_hash)
+    }
+
+    /// Removes all positions from history
+    pub fn clear(&mut self) {
+        self.hashes.clear();
+        self.root = 0;
+    }
+
     /// Gets the number of positions in history
     #[allow(dead_code)]
     pub fn len(&self) -> usize {
@@ -103,6 +179,17 @@ mod tests {
         assert_eq!(history.len(), 0);
     }
 
+    #[test]
+    fn test_clear() {
+        let mut history = RepetitionTable::new();
+
+        history.push(12345);
+        history.push(67890);
+        history.clear();
+
+        assert!(history.is_empty());
+    }
+
     #[test]
     fn test_pop_empty() {
         let mut history = RepetitionTable::new();
@@ -158,4 +245,59 @@ mod tests {
 
         assert!(history.is_repetition(100));
     }
+
+    #[test]
+    fn test_contains_single_occurrence() {
+        let mut history = RepetitionTable::new();
+        history.push(12345);
+
+        assert!(history.contains(12345));
+        assert!(!history.is_repetition(12345));
+        assert!(!history.contains(54321));
+    }
+
+    #[test]
+    fn test_search_position_repeated_once_is_draw() {
+        let mut history = RepetitionTable::new();
+
+        // Played in the game once, then the search starts
+        history.push(12345);
+        history.set_root();
+        history.push(67890);
+        history.push(11111);
+
+        assert!(history.is_draw(67890, 10, 2));
+        assert!(!history.is_draw(12345, 10, 2));
+        assert!(history.is_draw(12345, 10, 1));
+    }
+
+    #[test]
+    fn test_positions_before_irreversible_move_never_repeat() {
+        let mut history = RepetitionTable::new();
+
+        history.set_root();
+        history.push(12345);
+        history.push(67890);
+        history.push(11111);
+
+        assert!(!history.is_draw(12345, 2, 1));
+        assert!(history.is_draw(67890, 2, 1));
+        assert!(!history.is_draw(67890, 0, 1));
+    }
+
+    #[test]
+    fn test_occurrences_split_game_and_search() {
+        let mut history = RepetitionTable::new();
+
+        history.push(12345);
+        history.push(12345);
+        history.set_root();
+        history.push(12345);
+        history.push(67890);
+
+        assert_eq!(history.occurrences(12345, 10), (2, 1));
+        assert_eq!(history.occurrences(67890, 10), (0, 1));
+        assert_eq!(history.occurrences(11111, 10), (0, 0));
+        assert_eq!(history.occurrences(12345, 3), (1, 1));
+    }
 }
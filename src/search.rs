@@ -3,12 +3,19 @@ use crate::eval::Evaluator;
 use crate::history::HistoryTable;
 use crate::killer_moves::KillerMoves;
 use crate::move_gen::MoveGenerator;
+use crate::move_picker::MovePicker;
 use crate::moves::{Move, MoveType};
+use crate::pieces::Piece;
+use crate::pv::PvTable;
 use crate::repetition::RepetitionTable;
-use crate::timer::SearchTimer;
-use crate::transposition::{Bounds, TranspositionTable};
+#[cfg(feature = "syzygy")]
+use crate::tablebase::{can_probe, wdl_score, Tablebase};
+use crate::timer::{SearchTimer, TimeLimits};
+use crate::transposition::{score_from_tt, score_to_tt, Bounds, TranspositionTable, MATE_BOUND};
 use crate::zobrist::ZobristTable;
 use std::cmp::{max, min};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Negative infinity for alpha-beta bounds (avoiding overflow)
@@ -17,8 +24,42 @@ const NEGATIVE_INFINITY: i32 = (i16::MIN + 1) as i32;
 /// Positive infinity for alpha-beta bounds
 const INFINITY: i32 = -NEGATIVE_INFINITY;
 
-/// Checkmate score (leaves room for mate distance)
-const CHECKMATE_SCORE: i32 = i32::MAX - 1000;
+/// Upper bound on how much longer one iteration is predicted to take than the last
+const MAX_ITERATION_GROWTH: f64 = 8.0;
+
+/// How much the soft time limit grows when the best move changes between iterations
+const BEST_MOVE_CHANGE_EXTENSION: f64 = 0.5;
+
+/// How much the soft time limit grows when the score falls sharply between iterations
+const SCORE_DROP_EXTENSION: f64 = 0.5;
+
+/// Fall in centipawns between iterations that counts as the score dropping sharply
+const PANIC_SCORE_DROP: i32 = 30;
+
+/// Minimum remaining depth for internal iterative deepening
+const IID_MIN_DEPTH: u8 = 4;
+
+/// Depth reduction for the internal iterative deepening search
+const IID_REDUCTION: u8 = 2;
+
+/// Minimum remaining depth for trying a singular extension
+const SINGULAR_MIN_DEPTH: u8 = 6;
+
+/// How far below its entry's depth a TT move can be and still be tested for singularity
+const SINGULAR_TT_DEPTH_MARGIN: u8 = 3;
+
+/// Centipawns per ply of depth the other moves must fall short of the TT move's score
+const SINGULAR_MARGIN_PER_DEPTH: i32 = 2;
+
+/// Quiescence ply after which quiet checks are no longer searched
+const MAX_QUIESCENCE_CHECK_PLY: u8 = 2;
+
+/// Quiescence ply at which long capture sequences are cut off and scored statically
+const MAX_QUIESCENCE_PLY: u8 = 10;
+
+/// Checkmate score, reduced by the ply the mate happens at so shorter mates
+/// score higher. Kept inside the root window so mates are never cut off by it.
+pub const CHECKMATE_SCORE: i32 = INFINITY - 1;
 
 /// Most Valuable Victim - Least Valuable Attacker scores for move ordering
 /// Rows: victim piece (King, Queen, Rook, Bishop, Knight, Pawn)
@@ -32,9 +73,104 @@ pub const MVV_LVA_SCORES: [[i8; 6]; 6] = [
     [10, 11, 12, 13, 14, 15],
 ];
 
+/// Bonus added to a move's MVV-LVA score when it promotes, indexed by promotion piece
+/// (Pawn, Knight, Bishop, Rook, Queen, King). A queen promotion outranks most captures.
+pub const PROMOTION_SCORES: [i8; 6] = [0, 20, 5, 10, 50, 0];
+
+/// Plies from the root the ply-indexed tables, like the killer moves and the
+/// principal variation, have room for. Deeper plies reached by extensions and
+/// quiescence search are searched but not recorded in them.
+pub const MAX_PLY: usize = 64;
+
+/// Deepest search the engine will run
+pub const MAX_SEARCH_DEPTH: u8 = MAX_PLY as u8;
+
+/// Limits for a search started with [`Searcher::analyze`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchLimits {
+    /// Maximum depth in half moves
+    pub depth: u8,
+    /// Time budget, the search is only bounded by depth without one
+    pub time: Option<TimeLimits>,
+}
+
+impl SearchLimits {
+    /// Creates limits for a search to a fixed depth
+    pub fn depth(depth: u8) -> Self {
+        Self {
+            depth: depth.min(MAX_SEARCH_DEPTH),
+            time: None,
+        }
+    }
+
+    /// Creates limits for a search that goes as deep as it can in the given time
+    pub fn time(time: TimeLimits) -> Self {
+        Self {
+            depth: MAX_SEARCH_DEPTH,
+            time: Some(time),
+        }
+    }
+}
+
+/// What a search found, from the perspective of the side to move
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /// Best move, `None` if the position has no legal moves
+    pub best_move: Option<Move>,
+    /// Score in centipawns
+    pub score: i32,
+    /// Line of best play starting with the best move
+    pub pv: Vec<Move>,
+    /// Depth of the last completed iteration
+    pub depth: u8,
+    /// Number of nodes searched
+    pub nodes: u64,
+}
+
+/// Flags for controlling a search from another thread while it runs
+#[derive(Debug, Default)]
+pub struct SearchSignals {
+    stop: AtomicBool,
+    pondering: AtomicBool,
+}
+
+impl SearchSignals {
+    /// Creates signals for a search that isn't stopped or pondering
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clears the stop flag and sets whether the next search ponders
+    pub fn reset(&self, pondering: bool) {
+        self.stop.store(false, Ordering::Relaxed);
+        self.pondering.store(pondering, Ordering::Relaxed);
+    }
+
+    /// Asks the running search to stop as soon as possible
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Checks if the search was asked to stop
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// Turns a ponder search into a normal search whose time limits start now
+    pub fn ponderhit(&self) {
+        self.pondering.store(false, Ordering::Relaxed);
+    }
+
+    /// Checks if the search is pondering, in which case it ignores its time limits
+    pub fn is_pondering(&self) -> bool {
+        self.pondering.load(Ordering::Relaxed)
+    }
+}
+
 /// The main chess position searcher.
 pub struct Searcher {
-    move_generator: MoveGenerator,
+    /// The shared generator, so each searcher doesn't build its own lookup tables
+    move_generator: &'static MoveGenerator,
     evaluator: Evaluator,
     zobrist: ZobristTable,
     transposition_table: TranspositionTable,
@@ -42,13 +178,37 @@ pub struct Searcher {
     timer: SearchTimer,
     repetition: RepetitionTable,
     history: HistoryTable,
+    pv: PvTable,
+    principal_variation: Vec<Move>,
+    /// The `info` lines printed by the last search, one per completed depth
+    info_lines: Vec<String>,
+    /// Deepest ply reached in the current iteration, including quiescence search
+    seldepth: u8,
+    /// Depth of the last iteration that completed
+    completed_depth: u8,
+    /// Depth of the current iteration, extensions stop at twice this many plies
+    root_depth: u8,
+    contempt: i32,
+    /// Whether quiescence search tries quiet checks as well as captures
+    qsearch_checks: bool,
+    /// Whether PV nodes without a TT move search shallower first to find one
+    internal_iterative_deepening: bool,
+    /// Whether a TT move much better than every other move is searched deeper
+    singular_extensions: bool,
+    /// Whether castles are printed as the king moving to its rook, for Chess960
+    chess960: bool,
+    signals: Arc<SearchSignals>,
+    time_limits: Option<TimeLimits>,
+    pondering: bool,
+    #[cfg(feature = "syzygy")]
+    tablebase: Option<Arc<dyn Tablebase>>,
 }
 
 impl Searcher {
     /// Creates a new searcher with all components initialized
     pub fn new() -> Self {
         Self {
-            move_generator: MoveGenerator::new(),
+            move_generator: MoveGenerator::shared(),
             evaluator: Evaluator::new(),
             zobrist: ZobristTable::new(),
             transposition_table: TranspositionTable::new(),
@@ -56,7 +216,121 @@ impl Searcher {
             timer: SearchTimer::new(),
             repetition: RepetitionTable::new(),
             history: HistoryTable::new(),
+            pv: PvTable::new(),
+            principal_variation: Vec::new(),
+            info_lines: Vec::new(),
+            seldepth: 0,
+            completed_depth: 0,
+            root_depth: 0,
+            contempt: 0,
+            qsearch_checks: true,
+            internal_iterative_deepening: true,
+            singular_extensions: true,
+            chess960: false,
+            signals: Arc::new(SearchSignals::new()),
+            time_limits: None,
+            pondering: false,
+            #[cfg(feature = "syzygy")]
+            tablebase: None,
+        }
+    }
+
+    /// Sets the contempt in centipawns.
+    ///
+    /// A positive contempt makes draws score below zero for the side the
+    /// search was started for, so it prefers to keep playing.
+    pub fn set_contempt(&mut self, contempt: i32) {
+        self.contempt = contempt;
+    }
+
+    /// Sets whether quiescence search tries quiet checks.
+    ///
+    /// Checks find more tactics near the horizon, but testing every move for
+    /// check is slow, so leaving them out searches deeper in the same time.
+    pub fn set_qsearch_checks(&mut self, enabled: bool) {
+        self.qsearch_checks = enabled;
+    }
+
+    /// Sets whether internal iterative deepening is used, for testing how much
+    /// it helps. On by default.
+    pub fn set_internal_iterative_deepening(&mut self, enabled: bool) {
+        self.internal_iterative_deepening = enabled;
+    }
+
+    /// Sets whether singular extensions are used, for testing how much they
+    /// help. On by default.
+    pub fn set_singular_extensions(&mut self, enabled: bool) {
+        self.singular_extensions = enabled;
+    }
+
+    /// Sets the endgame tablebases to probe, or `None` to stop probing.
+    ///
+    /// A root position in the tables plays the tablebase move without searching,
+    /// and positions reached by a capture or pawn move score as won, drawn or lost.
+    #[cfg(feature = "syzygy")]
+    pub fn set_tablebase(&mut self, tablebase: Option<Arc<dyn Tablebase>>) {
+        self.tablebase = tablebase;
+    }
+
+    /// Sets whether moves are printed for Chess960, where castles are written as
+    /// the king moving to its rook's square.
+    pub fn set_chess960(&mut self, enabled: bool) {
+        self.chess960 = enabled;
+    }
+
+    /// Whether moves are printed for Chess960
+    pub fn chess960(&self) -> bool {
+        self.chess960
+    }
+
+    /// Clears everything learned from previous searches so a new game starts fresh.
+    ///
+    /// The precomputed move generation and zobrist tables are kept, as are
+    /// options like contempt and piece values.
+    pub fn reset(&mut self) {
+        self.clear_hash();
+        self.repetition.clear();
+        self.evaluator.clear_cache();
+        self.principal_variation.clear();
+    }
+
+    /// Clears the transposition table along with the killer and history move
+    /// ordering tables, keeping the game's positions.
+    pub fn clear_hash(&mut self) {
+        self.transposition_table.clear();
+        self.killer_moves.clear();
+        self.history.clear();
+    }
+
+    /// Records a position played in the game before the search starts, so lines
+    /// that repeat it can be scored as draws.
+    ///
+    /// Positions before an irreversible move can never come up again, so the
+    /// history is cleared when one is reached.
+    ///
+    /// # Arguments
+    /// * `board` - A position played in the game, not including the one to search
+    pub fn push_game_position(&mut self, board: &Board) {
+        if board.halfmove_clock == 0 {
+            self.repetition.clear();
         }
+
+        self.push_position(board);
+    }
+
+    /// Forgets the positions played in the game, called when a new position is set up
+    pub fn clear_game_positions(&mut self) {
+        self.repetition.clear();
+    }
+
+    /// Gets the signals used to stop or ponderhit the search from another thread
+    pub fn signals(&self) -> Arc<SearchSignals> {
+        Arc::clone(&self.signals)
+    }
+
+    /// Gets the evaluator so its parameters can be tuned
+    pub fn evaluator_mut(&mut self) -> &mut Evaluator {
+        &mut self.evaluator
     }
 
     /// Finds the best move in the current position.
@@ -65,10 +339,19 @@ impl Searcher {
     /// This helps with move ordering since deeper searches can use results
     /// from shallower searches.
     ///
+    /// A new depth is not started once the soft time limit has passed, or when
+    /// the growth of previous iterations predicts it won't finish before the hard
+    /// limit. The hard limit aborts an iteration that is already in progress.
+    /// The soft limit is stretched towards the hard limit when the best move
+    /// changes or the score drops between iterations.
+    ///
+    /// With a single legal move there is nothing to decide, so only depth 1 is
+    /// searched to give it a score.
+    ///
     /// # Arguments
     /// * `board` - The current position
     /// * `max_depth` - Maximum search depth in half moves
-    /// * `time_limit` - Optional time limit for search
+    /// * `time_limits` - Optional soft and hard time limits for search
     ///
     /// # Returns
     /// Tuple of (evaluation score, best move)
@@ -76,50 +359,290 @@ impl Searcher {
         &mut self,
         board: &Board,
         max_depth: u8,
-        time_limit: Option<Duration>,
+        time_limits: Option<TimeLimits>,
     ) -> (i32, Option<Move>) {
-        self.timer.start(time_limit);
+        let max_depth = if self.move_generator.generate_moves(board).len() == 1 {
+            max_depth.min(1)
+        } else {
+            max_depth
+        };
+
+        // Time limits only start counting once a ponder search gets a ponderhit
+        self.pondering = self.signals.is_pondering();
+        self.time_limits = time_limits;
+        let hard_limit = time_limits
+            .filter(|_| !self.pondering)
+            .map(|limits| limits.hard);
+
+        self.timer.start(hard_limit);
         self.history.age();
+        self.transposition_table.new_search();
 
         let mut best_score = NEGATIVE_INFINITY;
         let mut best_move = None;
+        self.principal_variation.clear();
+        self.info_lines.clear();
+        self.completed_depth = 0;
+
+        #[cfg(feature = "syzygy")]
+        if let Some(result) = self.probe_root_tablebase(board) {
+            return result;
+        }
+
+        let mut previous_iteration_time = None;
+        let mut last_iteration_time = None;
+        let mut soft_scale = 1.0;
 
         for current_depth in 1..=max_depth {
-            if self.timer.should_stop() {
+            // The first iteration always runs so there is a move to play
+            let has_move = self.completed_depth > 0;
+            if has_move && self.should_stop() {
                 break;
             }
 
-            let result = self.search_position(board, current_depth);
+            let iteration_start = self.timer.elapsed();
+            if let (false, true, Some(limits)) = (self.pondering, has_move, self.time_limits) {
+                let limits = limits.scale_soft(soft_scale);
+                let iteration_times = (previous_iteration_time, last_iteration_time);
+                if !self.should_start_iteration(limits, iteration_start, iteration_times) {
+                    break;
+                }
+            }
+
+            self.seldepth = 0;
+            let (result, bound) =
+                self.search_position(board, current_depth, NEGATIVE_INFINITY, INFINITY);
 
             // Only update if search completed
-            if !self.timer.should_stop() {
+            if !self.should_stop() {
+                if self.completed_depth > 0 {
+                    soft_scale = Self::instability_scale((best_score, best_move), &result);
+                }
+
                 best_score = result.score;
                 best_move = result.best_move;
-
-                self.cache_search_result(board, &result, current_depth);
-                self.timer
-                    .print_info(current_depth, result.score, result.best_move);
+                self.completed_depth = current_depth;
+
+                // A root transposition table hit leaves no line behind
+                let line = self.pv.root_line();
+                self.principal_variation = if line.first() == result.best_move.as_ref() {
+                    line
+                } else {
+                    result.best_move.into_iter().collect()
+                };
+
+                self.cache_search_result(board, &result, current_depth, bound);
+                self.report_info(current_depth, result.score, bound);
+
+                previous_iteration_time = last_iteration_time;
+                // The clock restarts on ponderhit, so it may now be behind `iteration_start`
+                last_iteration_time = Some(self.timer.elapsed().saturating_sub(iteration_start));
+            } else {
+                if best_move.is_none() {
+                    // Stopped before the first iteration finished, the move it was
+                    // searching first still beats having no move to play
+                    best_move = result.best_move;
+                    best_score = if result.score > NEGATIVE_INFINITY {
+                        result.score
+                    } else {
+                        0
+                    };
+                }
+                break;
             }
         }
 
         (best_score, best_move)
     }
 
-    /// Searches a position to a given depth using negamax with alpha-beta.
-    fn search_position(&mut self, board: &Board, depth: u8) -> SearchResult {
-        self.repetition.push(self.zobrist.hash(board));
+    /// Searches a position within the given limits and reports what was found.
+    ///
+    /// Like [`Searcher::find_best_move`], but also returns the principal
+    /// variation, the depth reached and the number of nodes searched.
+    ///
+    /// # Examples
+    /// ```
+    /// use flounder::{Board, MoveGenerator, SearchLimits, Searcher};
+    ///
+    /// let board = Board::default();
+    /// let mut searcher = Searcher::new();
+    ///
+    /// let result = searcher.analyze(&board, SearchLimits::depth(3));
+    ///
+    /// let best_move = result.best_move.unwrap();
+    /// assert!(MoveGenerator::new().generate_moves(&board).contains(&best_move));
+    /// assert_eq!(result.pv[0], best_move);
+    /// assert_eq!(result.depth, 3);
+    /// ```
+    pub fn analyze(&mut self, board: &Board, limits: SearchLimits) -> SearchResult {
+        let (score, best_move) = self.find_best_move(board, limits.depth, limits.time);
+
+        SearchResult {
+            best_move,
+            score,
+            pv: self.principal_variation.clone(),
+            depth: self.completed_depth,
+            nodes: self.nodes(),
+        }
+    }
+
+    /// Gets the `info` lines the last search printed, one per completed depth
+    pub fn info_lines(&self) -> &[String] {
+        &self.info_lines
+    }
+
+    /// Gets the depth of the last iteration the last search completed
+    pub fn completed_depth(&self) -> u8 {
+        self.completed_depth
+    }
+
+    /// Gets the number of nodes visited by the last search
+    pub fn nodes(&self) -> u64 {
+        self.timer.nodes()
+    }
+
+    /// Gets the line of best play found by the last completed iteration,
+    /// starting with the best move
+    pub fn principal_variation(&self) -> &[Move] {
+        &self.principal_variation
+    }
+
+    /// Checks if the search should stop because it was told to or ran out of time.
+    ///
+    /// A ponder search never runs out of time. When it gets a ponderhit the clock
+    /// restarts with the search's hard limit.
+    fn should_stop(&mut self) -> bool {
+        if self.signals.is_stopped() {
+            return true;
+        }
+
+        if self.pondering {
+            if self.signals.is_pondering() {
+                return false;
+            }
+
+            self.pondering = false;
+            self.timer
+                .restart(self.time_limits.map(|limits| limits.hard));
+        }
+
+        self.timer.should_stop()
+    }
+
+    /// Plays the tablebase move when the root position is in the tables
+    #[cfg(feature = "syzygy")]
+    fn probe_root_tablebase(&mut self, board: &Board) -> Option<(i32, Option<Move>)> {
+        let tablebase = self.tablebase.as_ref()?;
+        if !can_probe(tablebase.as_ref(), board) {
+            return None;
+        }
+
+        let (best_move, wdl) = tablebase.probe_root(board)?;
+        let score = wdl_score(wdl, 0);
+
+        self.principal_variation = vec![best_move];
+        self.completed_depth = 1;
+        self.seldepth = 0;
+        self.report_info(1, score, Bounds::Exact);
+
+        Some((score, Some(best_move)))
+    }
+
+    /// Scores a position from the tablebases.
+    ///
+    /// Only positions just reached by a capture or pawn move are probed, since the
+    /// tables assume the fifty-move counter has just been reset.
+    #[cfg(feature = "syzygy")]
+    fn probe_tablebase(&self, board: &Board, ply: u8) -> Option<i32> {
+        let tablebase = self.tablebase.as_ref()?;
+        if board.halfmove_clock != 0 || !can_probe(tablebase.as_ref(), board) {
+            return None;
+        }
+
+        tablebase.probe_wdl(board).map(|wdl| wdl_score(wdl, ply))
+    }
 
-        let result = self.negamax(
-            board,
+    /// Prints the `info` line for a completed depth and keeps it for `info_lines`
+    fn report_info(&mut self, depth: u8, score: i32, bound: Bounds) {
+        let line = self.timer.info_line(
             depth,
-            0,
-            NEGATIVE_INFINITY,
-            INFINITY,
-            SearchContext::new(),
+            self.seldepth,
+            &uci_score(score, bound),
+            self.transposition_table.hashfull(),
+            &self.principal_variation,
+            self.chess960,
         );
 
-        self.repetition.pop();
-        result
+        println!("{}", line);
+        self.info_lines.push(line);
+    }
+
+    /// How far to stretch the soft time limit after an iteration, given the score
+    /// and best move of the one before it.
+    ///
+    /// A new best move or a sharply falling score means the search has just found
+    /// a problem, and stopping now could play a move it is about to refute.
+    fn instability_scale(previous: (i32, Option<Move>), result: &NodeResult) -> f64 {
+        let (previous_score, previous_move) = previous;
+        let mut scale = 1.0;
+
+        if result.best_move != previous_move {
+            scale += BEST_MOVE_CHANGE_EXTENSION;
+        }
+
+        if result.score <= previous_score - PANIC_SCORE_DROP {
+            scale += SCORE_DROP_EXTENSION;
+        }
+
+        scale
+    }
+
+    /// Decides whether there is enough time left to start another iteration.
+    ///
+    /// The next iteration's duration is predicted by growing the last completed
+    /// iteration's duration by the same factor it grew from the one before it.
+    ///
+    /// # Arguments
+    /// * `limits` - The search's soft and hard time limits
+    /// * `elapsed` - Time spent searching so far
+    /// * `iteration_times` - Durations of the previous and last completed iterations
+    fn should_start_iteration(
+        &self,
+        limits: TimeLimits,
+        elapsed: Duration,
+        iteration_times: (Option<Duration>, Option<Duration>),
+    ) -> bool {
+        if elapsed >= limits.soft {
+            return false;
+        }
+
+        let (Some(previous), Some(last)) = iteration_times else {
+            return true;
+        };
+
+        let growth = last.as_secs_f64() / previous.as_secs_f64().max(f64::EPSILON);
+        let predicted = last.mul_f64(growth.clamp(1.0, MAX_ITERATION_GROWTH));
+
+        elapsed + predicted <= limits.hard
+    }
+
+    /// Searches a position to a given depth using negamax with alpha-beta.
+    ///
+    /// Returns the root's result along with whether its score is exact or only
+    /// a bound, from failing high or low against the `alpha`-`beta` window.
+    fn search_position(
+        &mut self,
+        board: &Board,
+        depth: u8,
+        alpha: i32,
+        beta: i32,
+    ) -> (NodeResult, Bounds) {
+        self.root_depth = depth;
+        self.repetition.set_root();
+
+        let result = self.negamax(board, depth, 0, alpha, beta, SearchContext::new());
+        let bound = self.determine_bound(result.score, alpha, beta);
+        (result, bound)
     }
 
     /// Negamax search with alpha-beta pruning.
@@ -144,53 +667,110 @@ impl Searcher {
         depth: u8,
         ply: u8,
         mut alpha: i32,
-        beta: i32,
+        mut beta: i32,
         mut context: SearchContext,
-    ) -> SearchResult {
+    ) -> NodeResult {
         self.timer.increment_nodes();
-        let original_alpha = alpha;
+        self.seldepth = self.seldepth.max(ply);
+        self.pv.clear_ply(ply);
+
+        if ply > 0 {
+            if self.is_draw_by_repetition(board, ply) || self.is_draw_by_rule(board) {
+                return NodeResult::new(self.draw_score(ply), None);
+            }
+
+            #[cfg(feature = "syzygy")]
+            if let Some(score) = self.probe_tablebase(board, ply) {
+                return NodeResult::new(score, None);
+            }
 
-        if ply > 0 && self.is_draw_by_repetition(board) {
-            return SearchResult::new(0, None);
+            // Mate distance pruning: no line from here can beat being mated now
+            // or mating next move, so the window can shrink to those bounds
+            alpha = max(alpha, -CHECKMATE_SCORE + ply as i32);
+            beta = min(beta, CHECKMATE_SCORE - ply as i32 - 1);
+            if alpha >= beta {
+                return NodeResult::new(alpha, None);
+            }
         }
 
+        let original_alpha = alpha;
+
         // Check if we've already seen this position
         if let Some(cached_result) =
-            self.probe_transposition_table(board, depth, alpha, beta, &mut context)
+            self.probe_transposition_table(board, depth, ply, alpha, beta, &mut context)
         {
             return cached_result;
         }
 
         // Quiescence search checks, captures, and promotions
         if depth == 0 {
-            let score = self.search_until_quiet(board, alpha, beta);
-            return SearchResult::new(score, None);
+            let score = self.search_until_quiet(board, alpha, beta, ply, 0);
+            return NodeResult::new(score, None);
+        }
+
+        // Internal iterative deepening: without a TT move ordering is poor, so
+        // find one with a shallower search first
+        let is_pv_node = beta - alpha > 1;
+        if self.internal_iterative_deepening
+            && is_pv_node
+            && depth >= IID_MIN_DEPTH
+            && context.tt_best_move.is_none()
+        {
+            let reduced_depth = depth - IID_REDUCTION;
+            let iid_result =
+                self.negamax(board, reduced_depth, ply, alpha, beta, SearchContext::new());
+            context.tt_best_move = iid_result.best_move;
+            self.pv.clear_ply(ply);
         }
 
         // Generate and order moves (best moves first for better pruning)
-        let mut moves = self.move_generator.generate_moves(board);
+        let moves = self.move_generator.generate_moves(board);
 
         // Check for checkmate/stalemate
         if moves.is_empty() {
-            return self.handle_terminal_position(board, depth);
+            return self.handle_terminal_position(board, ply);
         }
 
-        self.order_moves(board, &mut moves, context.tt_best_move, ply);
+        let moves = self.order_moves(board, moves, context.tt_best_move, ply);
+
+        // Every line below this node can see it was reached, until the loop pops it
+        self.push_position(board);
+
+        // Singular extension: a TT move that is much better than every other
+        // move is likely an only move, so it gets searched one ply deeper
+        let singular_move = match (context.tt_best_move, context.tt_score) {
+            (Some(tt_move), Some(tt_score))
+                if self.singular_extensions
+                    && ply > 0
+                    && ply < 2 * self.root_depth
+                    && depth >= SINGULAR_MIN_DEPTH
+                    && context.tt_depth + SINGULAR_TT_DEPTH_MARGIN >= depth
+                    && context.tt_bounds != Some(Bounds::Upper)
+                    && tt_score.abs() < MATE_BOUND =>
+            {
+                let singular_beta = tt_score - SINGULAR_MARGIN_PER_DEPTH * depth as i32;
+                self.is_singular(board, moves.clone(), tt_move, singular_beta, depth, ply)
+                    .then_some(tt_move)
+            }
+            _ => None,
+        };
 
-        let mut best_result = SearchResult::worst(moves[0]);
+        let mut moves = moves.peekable();
+        let mut best_result = NodeResult::worst(*moves.peek().expect("moves are not empty"));
 
         for current_move in moves {
-            if self.timer.should_stop() {
+            if self.should_stop() {
                 break;
             }
 
             let next_position = board.clone_with_move(&current_move);
+            let extension = u8::from(singular_move == Some(current_move));
 
             // Recursively search, flip the sign because we're switching sides
             let score = -self
                 .negamax(
                     &next_position,
-                    depth - 1,
+                    depth - 1 + extension,
                     ply + 1,
                     -beta,
                     -alpha,
@@ -203,57 +783,140 @@ impl Searcher {
                 best_result.best_move = Some(current_move);
             }
 
+            if score > alpha {
+                self.pv.update(current_move, ply);
+            }
+
             alpha = max(alpha, score);
             if alpha >= beta {
                 if current_move.move_type == MoveType::Quiet {
                     self.killer_moves.store(current_move, ply);
-                    self.history.record_cutoff(&current_move, depth);
+                    self.history
+                        .record_cutoff(board.active_color(), &current_move, depth);
                 }
                 break;
             }
         }
 
+        self.repetition.pop();
+
         let bound = self.determine_bound(best_result.score, original_alpha, beta);
-        self.store_in_transposition_table(board, &best_result, depth, bound);
+        self.store_in_transposition_table(board, &best_result, depth, ply, bound);
 
         best_result
     }
 
+    /// Checks if every move other than the TT move fails low against `singular_beta`.
+    ///
+    /// The other moves are searched to half the depth with a null window, so
+    /// this is much cheaper than the full search it decides whether to extend.
+    fn is_singular(
+        &mut self,
+        board: &Board,
+        moves: impl IntoIterator<Item = Move>,
+        tt_move: Move,
+        singular_beta: i32,
+        depth: u8,
+        ply: u8,
+    ) -> bool {
+        let reduced_depth = (depth - 1) / 2;
+
+        for mv in moves.into_iter().filter(|&mv| mv != tt_move) {
+            if self.should_stop() {
+                return false;
+            }
+
+            let next_position = board.clone_with_move(&mv);
+            let score = -self
+                .negamax(
+                    &next_position,
+                    reduced_depth,
+                    ply + 1,
+                    -singular_beta,
+                    -singular_beta + 1,
+                    SearchContext::new(),
+                )
+                .score;
+
+            if score >= singular_beta {
+                return false;
+            }
+        }
+
+        true
+    }
+
     /// Searches until position is "quiet" (no captures, checks, or promotions)
     ///
     /// This prevents the "horizon effect" where the engine stops searching right
     /// before a capture sequence, leading to bad evaluations.
-    fn search_until_quiet(&mut self, board: &Board, mut alpha: i32, beta: i32) -> i32 {
+    ///
+    /// In check every evasion is searched rather than standing pat. Quiet checks
+    /// followed by all their evasions would blow up the tree, so quiet checks, if
+    /// enabled, are only tried in the first `MAX_QUIESCENCE_CHECK_PLY` plies.
+    fn search_until_quiet(
+        &mut self,
+        board: &Board,
+        mut alpha: i32,
+        beta: i32,
+        ply: u8,
+        qply: u8,
+    ) -> i32 {
         self.timer.increment_nodes();
-        let currently_in_check = self.move_generator.is_in_check(board);
+        self.seldepth = self.seldepth.max(ply);
+
+        // Trust the evaluation at the end of a long enough capture sequence
+        if qply >= MAX_QUIESCENCE_PLY {
+            return self
+                .evaluator
+                .evaluate_cached(board, self.zobrist.hash(board));
+        }
 
-        let mut moves = if currently_in_check {
-            self.move_generator.generate_moves(board)
+        let checkers = self.move_generator.checkers(board);
+        let currently_in_check = checkers != 0;
+
+        let moves = if currently_in_check {
+            self.move_generator.generate_evasions(board, checkers)
         } else {
-            self.move_generator.generate_quiescence_moves(board)
+            let include_checks = self.qsearch_checks && qply < MAX_QUIESCENCE_CHECK_PLY;
+            self.move_generator
+                .generate_quiescence_moves(board, include_checks)
         };
 
-        self.order_captures(&mut moves, board);
+        if currently_in_check {
+            // Checkmate detection
+            if moves.is_empty() {
+                return -CHECKMATE_SCORE + ply as i32;
+            }
+        } else {
+            // Standing pat isn't an option in check, every evasion has to be searched
+            let stand_pat = self
+                .evaluator
+                .evaluate_cached(board, self.zobrist.hash(board));
+            if stand_pat >= beta {
+                return beta;
+            }
 
-        // Checkmate detection
-        if moves.is_empty() && currently_in_check {
-            return -CHECKMATE_SCORE;
-        }
+            // Having no captures or promotions doesn't mean having no moves. Only
+            // check for a stalemate when it would score better than standing pat
+            let draw_score = self.draw_score(ply);
+            if moves.is_empty()
+                && stand_pat < draw_score
+                && self.move_generator.generate_moves(board).is_empty()
+            {
+                return draw_score;
+            }
 
-        let stand_pat = self.evaluator.evaluate(board);
-        if stand_pat >= beta {
-            return beta;
+            alpha = max(alpha, stand_pat);
         }
 
-        alpha = max(alpha, stand_pat);
-
-        for mv in moves {
-            if self.timer.should_stop() {
+        for mv in self.order_captures(moves, board) {
+            if self.should_stop() {
                 break;
             }
 
             let next_position = board.clone_with_move(&mv);
-            let score = -self.search_until_quiet(&next_position, -beta, -alpha);
+            let score = -self.search_until_quiet(&next_position, -beta, -alpha, ply + 1, qply + 1);
 
             if score >= beta {
                 return beta;
@@ -265,9 +928,44 @@ impl Searcher {
         alpha
     }
 
-    fn is_draw_by_repetition(&self, board: &Board) -> bool {
-        let current_hash = self.zobrist.hash(board);
-        self.repetition.is_repetition(current_hash)
+    /// Score of a drawn position from the perspective of the side to move at `ply`.
+    ///
+    /// The root side to move sees `-contempt` and its opponent sees `contempt`.
+    fn draw_score(&self, ply: u8) -> i32 {
+        if ply.is_multiple_of(2) {
+            -self.contempt
+        } else {
+            self.contempt
+        }
+    }
+
+    /// Checks if the fifty-move rule or a lack of mating material makes the position
+    /// a draw. A mate on the move the fifty moves run out still wins, so the
+    /// fifty-move rule only applies when the side to move isn't checkmated.
+    fn is_draw_by_rule(&self, board: &Board) -> bool {
+        if board.is_insufficient_material() {
+            return true;
+        }
+
+        board.is_fifty_move_draw()
+            && !(self.move_generator.is_in_check(board)
+                && self.move_generator.generate_moves(board).is_empty())
+    }
+
+    /// Checks if a position should be scored as a draw by repetition.
+    ///
+    /// Any position already on the current line is a draw. Deeper in the tree a
+    /// position from the game needs to have occurred twice before. Right after
+    /// the root, once is enough, as playing a move that repeats one only gives
+    /// the opponent the chance to claim the draw.
+    fn is_draw_by_repetition(&self, board: &Board, ply: u8) -> bool {
+        let game_occurrences = if ply == 1 { 1 } else { 2 };
+
+        self.repetition.is_draw(
+            self.zobrist.hash(board),
+            board.halfmove_clock as usize,
+            game_occurrences,
+        )
     }
 
     /// Checks if we've already searched this position
@@ -275,15 +973,20 @@ impl Searcher {
         &self,
         board: &Board,
         depth: u8,
+        ply: u8,
         mut alpha: i32,
         mut beta: i32,
         context: &mut SearchContext,
-    ) -> Option<SearchResult> {
+    ) -> Option<NodeResult> {
         let position_hash = self.zobrist.hash(board);
         let entry = self.transposition_table.retrieve(position_hash)?;
+        let eval = score_from_tt(entry.eval, ply);
 
         // Store TT move for move ordering even if depth is insufficient
         context.tt_best_move = entry.best_move;
+        context.tt_score = Some(eval);
+        context.tt_depth = entry.depth;
+        context.tt_bounds = Some(entry.bounds);
 
         // Only use entry if it was searched to sufficient depth
         if entry.depth < depth {
@@ -292,18 +995,18 @@ impl Searcher {
 
         match entry.bounds {
             Bounds::Exact => {
-                return Some(SearchResult::new(entry.eval, entry.best_move));
+                return Some(NodeResult::new(eval, entry.best_move));
             }
             Bounds::Lower => {
-                alpha = max(alpha, entry.eval);
+                alpha = max(alpha, eval);
             }
             Bounds::Upper => {
-                beta = min(beta, entry.eval);
+                beta = min(beta, eval);
             }
         }
 
         if alpha >= beta {
-            return Some(SearchResult::new(entry.eval, entry.best_move));
+            return Some(NodeResult::new(eval, entry.best_move));
         }
 
         // Can't use this entry
@@ -314,18 +1017,26 @@ impl Searcher {
     fn store_in_transposition_table(
         &mut self,
         board: &Board,
-        result: &SearchResult,
+        result: &NodeResult,
         depth: u8,
+        ply: u8,
         bound: Bounds,
     ) {
         let position_hash = self.zobrist.hash(board);
+        let score = score_to_tt(result.score, ply);
         self.transposition_table
-            .store(position_hash, result.score, result.best_move, depth, bound);
+            .store(position_hash, score, result.best_move, depth, bound);
     }
 
     /// Caches the result from iterative deepening for move ordering.
-    fn cache_search_result(&mut self, board: &Board, result: &SearchResult, depth: u8) {
-        self.store_in_transposition_table(board, result, depth, Bounds::Exact);
+    fn cache_search_result(
+        &mut self,
+        board: &Board,
+        result: &NodeResult,
+        depth: u8,
+        bound: Bounds,
+    ) {
+        self.store_in_transposition_table(board, result, depth, 0, bound);
     }
 
     /// Determines the bound type for a transposition table entry.
@@ -340,33 +1051,54 @@ impl Searcher {
     }
 
     /// Handles terminal positions
-    fn handle_terminal_position(&self, board: &Board, depth: u8) -> SearchResult {
+    fn handle_terminal_position(&self, board: &Board, ply: u8) -> NodeResult {
         if self.move_generator.is_in_check(board) {
             // Prefer shorter mates
-            let mate_score = -CHECKMATE_SCORE + depth as i32;
-            SearchResult::checkmate(mate_score)
+            let mate_score = -CHECKMATE_SCORE + ply as i32;
+            NodeResult::checkmate(mate_score)
         } else {
-            SearchResult::stalemate()
+            NodeResult::stalemate(self.draw_score(ply))
         }
     }
 
-    /// Orders moves for better alpha-beta pruning.
+    /// Orders moves for better alpha-beta pruning, picking each one as it is
+    /// needed so nodes that cut off early don't pay for sorting every move.
     ///
     /// Priority:
     /// 1. Transposition table move
-    /// 2. Captures (MVV-LVA)
-    /// 3. Killer moves
-    /// 4. Promotions
-    /// 5. History heuristic
-    /// 6. Other moves
-    fn order_moves(&self, board: &Board, moves: &mut [Move], tt_move: Option<Move>, ply: u8) {
-        moves.sort_by_cached_key(|mv| {
+    /// 2. Queen promotions
+    /// 3. Captures (MVV-LVA)
+    /// 4. Killer moves
+    /// 5. Knight promotions
+    /// 6. History heuristic
+    /// 7. Other moves
+    /// 8. Rook and bishop promotions
+    fn order_moves(
+        &self,
+        board: &Board,
+        moves: Vec<Move>,
+        tt_move: Option<Move>,
+        ply: u8,
+    ) -> MovePicker {
+        MovePicker::new(moves, |mv| {
             if let Some(best_move) = tt_move {
                 if *mv == best_move {
                     return i32::MIN;
                 }
             }
 
+            if mv.move_type == MoveType::Promotion {
+                return match mv.piece_type {
+                    Piece::Queen => {
+                        let capture_score = self.calculate_capture_score(board, mv).unwrap_or(0);
+                        -(capture_score as i32) - 2000
+                    }
+                    Piece::Knight => -400,
+                    // Underpromoting to a rook or bishop is almost never better than a queen
+                    _ => 100,
+                };
+            }
+
             if mv.move_type == MoveType::Capture || mv.move_type == MoveType::EnPassant {
                 if let Some(score) = self.calculate_capture_score(board, mv) {
                     return -(score as i32) - 1000;
@@ -377,29 +1109,24 @@ impl Searcher {
                 return -500;
             }
 
-            if mv.move_type == MoveType::Promotion {
-                return -400;
-            }
-
             if mv.move_type == MoveType::Quiet {
-                return -self.history.get_score(mv);
+                return -self.history.ordering_score(board.active_color(), mv);
             }
 
             0
-        });
+        })
     }
 
-    /// Orders captures using MVV-LVA
-    fn order_captures(&self, moves: &mut [Move], board: &Board) {
-        moves.sort_by_cached_key(|mv| {
+    /// Orders captures using MVV-LVA, with promotions boosted by the promoted piece
+    fn order_captures(&self, moves: Vec<Move>, board: &Board) -> MovePicker {
+        MovePicker::new(moves, |mv| {
             if mv.move_type == MoveType::EnPassant {
                 return -10;
             }
 
-            self.calculate_capture_score(board, mv)
-                .map(|score| -score)
-                .unwrap_or(0)
-        });
+            let capture_score = self.calculate_capture_score(board, mv).unwrap_or(0);
+            -i32::from(capture_score + self.calculate_promotion_score(mv))
+        })
     }
 
     /// Calculates the capture score for MVV-LVA ordering
@@ -410,8 +1137,16 @@ impl Searcher {
         Some(MVV_LVA_SCORES[victim.index()][attacker.index()])
     }
 
+    /// Calculates the promotion bonus for a move, zero if it is not a promotion
+    fn calculate_promotion_score(&self, mv: &Move) -> i8 {
+        if mv.move_type != MoveType::Promotion {
+            return 0;
+        }
+
+        PROMOTION_SCORES[mv.piece_type.index()]
+    }
+
     /// Updates position repetition (for repetition detection)
-    #[allow(dead_code)]
     fn push_position(&mut self, board: &Board) {
         self.repetition.push(self.zobrist.hash(board));
     }
@@ -423,14 +1158,14 @@ impl Default for Searcher {
     }
 }
 
-/// Result of a search operation
+/// Score and best move found for a single node of the search
 #[derive(Debug, Clone, Copy)]
-struct SearchResult {
+struct NodeResult {
     score: i32,
     best_move: Option<Move>,
 }
 
-impl SearchResult {
+impl NodeResult {
     fn new(score: i32, best_move: Option<Move>) -> Self {
         Self { score, best_move }
     }
@@ -449,9 +1184,9 @@ impl SearchResult {
         }
     }
 
-    fn stalemate() -> Self {
+    fn stalemate(draw_score: i32) -> Self {
         Self {
-            score: 0,
+            score: draw_score,
             best_move: None,
         }
     }
@@ -461,21 +1196,70 @@ impl SearchResult {
 #[derive(Debug, Clone, Copy)]
 struct SearchContext {
     tt_best_move: Option<Move>,
+    /// Score, depth and bound of the TT entry, used to decide on singular extensions
+    tt_score: Option<i32>,
+    tt_depth: u8,
+    tt_bounds: Option<Bounds>,
 }
 
 impl SearchContext {
     fn new() -> Self {
-        Self { tt_best_move: None }
+        Self {
+            tt_best_move: None,
+            tt_score: None,
+            tt_depth: 0,
+            tt_bounds: None,
+        }
+    }
+}
+
+/// Formats a root score for an `info` line, marking scores that are only a
+/// bound because the root failed high or low
+fn uci_score(score: i32, bound: Bounds) -> String {
+    match bound {
+        Bounds::Exact => format!("cp {}", score),
+        Bounds::Lower => format!("cp {} lowerbound", score),
+        Bounds::Upper => format!("cp {} upperbound", score),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::pieces::{Color, ColorIterator, PieceIterator};
 
     const SEARCH_DEPTH: u8 = 6;
 
-    /// Helper function to test if engine finds the correct move in positions.
+    /// Three piece tables where a lone extra piece always wins, enough for KRvK
+    #[cfg(feature = "syzygy")]
+    struct ExtraPieceTablebase;
+
+    #[cfg(feature = "syzygy")]
+    impl Tablebase for ExtraPieceTablebase {
+        fn max_pieces(&self) -> u32 {
+            3
+        }
+
+        fn probe_wdl(&self, board: &Board) -> Option<crate::tablebase::Wdl> {
+            use crate::tablebase::Wdl;
+
+            let color = board.active_color();
+            let pieces = |color| board.bb_color(color).count_ones();
+            Some(match pieces(color).cmp(&pieces(!color)) {
+                std::cmp::Ordering::Greater => Wdl::Win,
+                std::cmp::Ordering::Less => Wdl::Loss,
+                std::cmp::Ordering::Equal => Wdl::Draw,
+            })
+        }
+
+        fn probe_root(&self, board: &Board) -> Option<(Move, crate::tablebase::Wdl)> {
+            let wdl = self.probe_wdl(board)?;
+            let best_move = MoveGenerator::shared().generate_moves(board)[0];
+            Some((best_move, wdl))
+        }
+    }
+
+    /// Helper function to test if engine finds the correct move in positions.
     fn assert_finds_move(fen: &str, expected_move: &str) {
         let board = Board::new(fen);
         let mut searcher = Searcher::new();
@@ -490,6 +1274,56 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_mates_with_queen_against_lone_king() {
+        // Mate is well beyond the search horizon, so the engine has to make progress
+        let mut board = Board::new("8/8/8/3k4/8/8/8/Q6K w - - 0 1");
+        let mut searcher = Searcher::new();
+
+        for _ in 0..40 {
+            let (_, best_move) = searcher.find_best_move(&board, 4, None);
+            let Some(mv) = best_move else {
+                break;
+            };
+            board.make_move(&mv);
+        }
+
+        let move_generator = MoveGenerator::new();
+        assert!(move_generator.generate_moves(&board).is_empty());
+        assert!(move_generator.is_in_check(&board));
+        assert_eq!(board.active_color(), Color::Black);
+    }
+
+    #[test]
+    fn test_prefers_shorter_mate() {
+        // Ra8 mates at once, while longer mates are also found within the depth
+        let board = Board::new("7k/6pp/8/8/8/8/6PP/R5K1 w - - 0 1");
+        let mut searcher = Searcher::new();
+        let (score, best_move) = searcher.find_best_move(&board, 4, None);
+
+        assert_eq!(best_move.unwrap().to_algebraic(), "a1a8");
+        assert_eq!(score, CHECKMATE_SCORE - 1);
+    }
+
+    #[test]
+    fn test_mate_scores_depend_only_on_distance_to_mate() {
+        let mate_in_one = Board::new("k7/2K5/8/8/8/8/8/7R w - - 0 1");
+        let mate_in_three = Board::new("k7/8/8/3K4/8/8/8/7R w - - 0 1");
+        let stalemate = Board::new("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1");
+        let mut searcher = Searcher::new();
+
+        for depth in [5, 6] {
+            let (mate_in_one_score, _) = searcher.find_best_move(&mate_in_one, depth, None);
+            let (mate_in_three_score, _) = searcher.find_best_move(&mate_in_three, depth, None);
+            let (stalemate_score, _) = searcher.find_best_move(&stalemate, depth, None);
+
+            assert_eq!(mate_in_one_score, CHECKMATE_SCORE - 1);
+            assert_eq!(mate_in_three_score, CHECKMATE_SCORE - 5);
+            assert!(mate_in_one_score > mate_in_three_score);
+            assert_eq!(stalemate_score, 0);
+        }
+    }
+
     #[test]
     fn test_king_move_while_in_check() {
         assert_finds_move(
@@ -633,16 +1467,555 @@ mod tests {
     }
 
     #[test]
-    fn test_repetition_detection() {
+    fn test_quiescence_searches_evasions_instead_of_standing_pat() {
+        // White is up a queen but in check, and every evasion loses material
+        let board = Board::new("5k2/8/8/7r/8/8/1bPPP3/Q6K w - - 0 1");
+        let mut searcher = Searcher::new();
+
+        assert!(searcher.evaluator.evaluate(&board) > 0);
+
+        let score = searcher.search_until_quiet(&board, NEGATIVE_INFINITY, INFINITY, 0, 0);
+        assert!(score < 0, "Stood pat in check: {}", score);
+
+        // A stand-pat cutoff against a beta below the static evaluation would hide the loss too
+        let beta = 1;
+        let score = searcher.search_until_quiet(&board, beta - 1, beta, 0, 0);
+        assert!(score < beta, "Cut off on stand pat in check: {}", score);
+    }
+
+    #[test]
+    fn test_quiescence_never_stands_pat_in_check_late_in_a_sequence() {
+        // White is a queen and knight up, but back rank mated
+        let board = Board::new("4N1Q1/8/k7/8/8/8/5PPP/r5K1 w - - 0 1");
+        let mut searcher = Searcher::new();
+
+        assert!(searcher.evaluator.evaluate(&board) > 100);
+
+        // Checks from captures can come at any ply of the sequence, not just the first few
+        for qply in 2..MAX_QUIESCENCE_PLY {
+            let score = searcher.search_until_quiet(&board, -100, 100, 4, qply);
+            assert_eq!(
+                score,
+                -CHECKMATE_SCORE + 4,
+                "stood pat in check at qply {}",
+                qply
+            );
+        }
+    }
+
+    #[test]
+    fn test_quiescence_scores_stalemate_as_draw() {
+        // Black is a queen down with no captures, and no legal moves at all
+        let board = Board::new("k7/8/1Q6/8/8/8/8/7K b - - 0 1");
+        let mut searcher = Searcher::new();
+
+        assert!(searcher.evaluator.evaluate(&board) < 0);
+
+        let score = searcher.search_until_quiet(&board, NEGATIVE_INFINITY, INFINITY, 0, 0);
+        assert_eq!(score, 0);
+    }
+
+    #[test]
+    fn test_quiescence_ply_limit() {
+        // Bishops and rooks take turns capturing on b2 and b7, a chain longer than the limit
+        let board = Board::new("kr5B/1r4b1/1R3B2/4b3/3B4/2b5/1b6/BQR3K1 w - - 0 1");
+        let mut searcher = Searcher::new();
+
+        let start = std::time::Instant::now();
+        searcher.search_until_quiet(&board, NEGATIVE_INFINITY, INFINITY, 0, 0);
+
+        assert_eq!(searcher.seldepth, MAX_QUIESCENCE_PLY);
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        // At the limit the position is scored without searching its captures
+        searcher.timer.reset();
+        let score = searcher.search_until_quiet(
+            &board,
+            NEGATIVE_INFINITY,
+            INFINITY,
+            MAX_QUIESCENCE_PLY,
+            MAX_QUIESCENCE_PLY,
+        );
+        assert_eq!(score, searcher.evaluator.evaluate(&board));
+        assert_eq!(searcher.nodes(), 1);
+    }
+
+    #[test]
+    fn test_search_at_max_ply() {
+        // Nodes past the end of the ply tables are searched without recording killers or a PV
+        let board =
+            Board::new("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10");
+        let mut searcher = Searcher::new();
+
+        let ply = (MAX_PLY - 1) as u8;
+        let result = searcher.negamax(
+            &board,
+            3,
+            ply,
+            NEGATIVE_INFINITY,
+            INFINITY,
+            SearchContext::new(),
+        );
+
+        assert!(result.best_move.is_some());
+        assert!(result.score.abs() < CHECKMATE_SCORE - MAX_PLY as i32);
+        assert!(searcher.seldepth > ply);
+        assert!(searcher.pv.root_line().is_empty());
+    }
+
+    #[test]
+    fn test_quiescence_checks_can_be_disabled() {
+        // Qh8 is mate, which quiescence only finds when it searches checks
+        let board = Board::new("k7/8/1K6/8/8/8/7Q/8 w - - 0 1");
+        let mut searcher = Searcher::new();
+
+        let score = searcher.search_until_quiet(&board, NEGATIVE_INFINITY, INFINITY, 0, 0);
+        assert_eq!(score, CHECKMATE_SCORE - 1);
+
+        searcher.set_qsearch_checks(false);
+        let score = searcher.search_until_quiet(&board, NEGATIVE_INFINITY, INFINITY, 0, 0);
+        assert!(score < CHECKMATE_SCORE - 255);
+    }
+
+    #[test]
+    fn test_quiescence_mate_scores_count_plies() {
+        // Qxf7 is mate, a capture quiescence searches even without checks
+        let board =
+            Board::new("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4");
+        let mut searcher = Searcher::new();
+        searcher.set_qsearch_checks(false);
+
+        let score = searcher.search_until_quiet(&board, NEGATIVE_INFINITY, INFINITY, 2, 0);
+        assert_eq!(score, CHECKMATE_SCORE - 3);
+
+        // The mated side sees the mate at the ply it happens on
+        let mated = board.clone_with_move(&Move::from_algebraic("h5f7", &board).unwrap());
+        let score = searcher.search_until_quiet(&mated, NEGATIVE_INFINITY, INFINITY, 3, 1);
+        assert_eq!(score, -CHECKMATE_SCORE + 3);
+    }
+
+    #[test]
+    fn test_quiescence_orders_queen_promotion_before_pawn_capture() {
+        let board = Board::new("7k/4P3/8/8/3p4/4P3/8/K7 w - - 0 1");
+        let searcher = Searcher::new();
+
+        let moves = searcher
+            .move_generator
+            .generate_quiescence_moves(&board, true);
+        let moves: Vec<Move> = searcher.order_captures(moves, &board).collect();
+
+        let position = |alg: &str| moves.iter().position(|mv| mv.to_algebraic() == alg);
+        let queen_promotion = position("e7e8q").expect("Queen promotion should be generated");
+        let pawn_capture = position("e3d4").expect("Pawn capture should be generated");
+
+        assert!(queen_promotion < pawn_capture);
+    }
+
+    #[test]
+    fn test_queen_promotion_ordered_first() {
+        let board = Board::new("3r3k/4P3/8/8/8/8/8/K7 w - - 0 1");
+        let searcher = Searcher::new();
+
+        let moves = searcher.move_generator.generate_moves(&board);
+        let moves: Vec<Move> = searcher.order_moves(&board, moves, None, 0).collect();
+
+        assert_eq!(moves[0].to_algebraic(), "e7d8q");
+
+        let position = |alg: &str| {
+            moves
+                .iter()
+                .position(|mv| mv.to_algebraic() == alg)
+                .unwrap()
+        };
+        assert!(position("e7e8q") < position("e7d8n"));
+        assert!(position("e7d8n") < position("e7d8r"));
+        assert!(position("a1a2") < position("e7e8b"));
+    }
+
+    #[test]
+    fn test_disabling_selective_search_keeps_best_move() {
+        // Nc7+ forks the king and rook
+        let board = Board::new("r3k3/8/8/1N6/8/8/8/4K3 w - - 0 1");
+
+        let mut searcher = Searcher::new();
+        let (_, best_move) = searcher.find_best_move(&board, 5, None);
+        assert_eq!(best_move.unwrap().to_algebraic(), "b5c7");
+
+        let mut plain_searcher = Searcher::new();
+        plain_searcher.set_qsearch_checks(false);
+        plain_searcher.set_internal_iterative_deepening(false);
+        plain_searcher.set_singular_extensions(false);
+        let (_, plain_best_move) = plain_searcher.find_best_move(&board, 5, None);
+        assert_eq!(plain_best_move, best_move);
+    }
+
+    #[test]
+    fn test_contempt_avoids_stalemate() {
+        // White is losing with Black's pawns blocked, and Kf7 stalemates Black
+        let board = Board::new("5N1k/p1p5/p1p2K2/p1p5/p1p5/p1p5/P1P5/8 w - - 0 1");
+
         let mut searcher = Searcher::new();
+        let (score, best_move) = searcher.find_best_move(&board, 4, None);
+        assert_eq!(best_move.unwrap().to_algebraic(), "f6f7");
+        assert_eq!(score, 0);
+
+        let mut searcher = Searcher::new();
+        searcher.set_contempt(500);
+        let (score, best_move) = searcher.find_best_move(&board, 4, None);
+        assert_ne!(best_move.unwrap().to_algebraic(), "f6f7");
+        assert!(score > -500);
+    }
+
+    #[test]
+    fn test_contempt_avoids_fifty_move_draw() {
+        // White is a knight for a pawn down, and any king move completes fifty moves
+        let board = Board::new("1n2k3/8/8/8/8/8/P7/4K3 w - - 99 80");
+
+        let mut searcher = Searcher::new();
+        let (score, best_move) = searcher.find_best_move(&board, 4, None);
+        assert_eq!(best_move.unwrap().piece_type, Piece::King);
+        assert_eq!(score, 0);
+
+        let mut searcher = Searcher::new();
+        searcher.set_contempt(500);
+        let (score, best_move) = searcher.find_best_move(&board, 4, None);
+        assert_eq!(best_move.unwrap().piece_type, Piece::Pawn);
+        assert!(score > -500);
+    }
+
+    #[test]
+    fn test_fifty_move_rule_does_not_override_mate() {
+        // Ra8 mates on the move that completes fifty moves
+        let board = Board::new("6k1/5ppp/8/8/8/8/8/R5K1 w - - 99 80");
+        let mut searcher = Searcher::new();
+
+        let (score, best_move) = searcher.find_best_move(&board, 2, None);
+        assert_eq!(best_move.unwrap().to_algebraic(), "a1a8");
+        assert_eq!(score, CHECKMATE_SCORE - 1);
+    }
+
+    #[test]
+    fn test_draw_score_is_relative_to_root_side() {
+        let mut searcher = Searcher::new();
+        searcher.set_contempt(30);
+
+        assert_eq!(searcher.draw_score(0), -30);
+        assert_eq!(searcher.draw_score(1), 30);
+        assert_eq!(searcher.draw_score(4), -30);
+    }
+
+    #[test]
+    fn test_principal_variation_starts_with_best_move() {
+        let board =
+            Board::new("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        let mut searcher = Searcher::new();
+
+        let (_, best_move) = searcher.find_best_move(&board, 4, None);
+        let pv = searcher.principal_variation();
+
+        assert!(pv.len() >= 2, "PV too short: {:?}", pv);
+        assert_eq!(pv.first(), best_move.as_ref());
+
+        // Every move in the line is legal in the position it is played from
+        let mut position = board;
+        for mv in pv {
+            assert!(searcher
+                .move_generator
+                .generate_moves(&position)
+                .contains(mv));
+            position.make_move(mv);
+        }
+    }
+
+    #[test]
+    fn test_reset_clears_search_tables() {
         let board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let mut searcher = Searcher::new();
+        searcher.set_contempt(25);
+
+        searcher.find_best_move(&board, 4, None);
+        searcher.push_position(&board);
+
+        let hash = searcher.zobrist.hash(&board);
+        let moves: Vec<(Color, Move)> = ColorIterator::new()
+            .flat_map(|color| {
+                PieceIterator::new().flat_map(move |piece| {
+                    (0..64).map(move |to| (color, Move::new(0, to, piece, MoveType::Quiet)))
+                })
+            })
+            .collect();
+        assert!(searcher.transposition_table.retrieve(hash).is_some());
+        assert!(moves
+            .iter()
+            .any(|(color, mv)| searcher.history.get_score(*color, mv) > 0));
+        assert!((0..4).any(|ply| searcher
+            .killer_moves
+            .get_killers(ply)
+            .iter()
+            .any(Option::is_some)));
+
+        searcher.reset();
+
+        assert!(searcher.transposition_table.retrieve(hash).is_none());
+        assert!(moves
+            .iter()
+            .all(|(color, mv)| searcher.history.get_score(*color, mv) == 0));
+        assert!((0..4).all(|ply| searcher
+            .killer_moves
+            .get_killers(ply)
+            .iter()
+            .all(Option::is_none)));
+        assert!(searcher.repetition.is_empty());
+        assert!(searcher.principal_variation().is_empty());
+
+        // Hashing is unchanged and options survive the reset
+        assert_eq!(searcher.zobrist.hash(&board), hash);
+        assert_eq!(searcher.contempt, 25);
+    }
+
+    #[test]
+    fn test_clear_hash_keeps_game_positions() {
+        let board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let mut searcher = Searcher::new();
+
+        searcher.find_best_move(&board, 4, None);
+        searcher.push_game_position(&board);
+
+        let hash = searcher.zobrist.hash(&board);
+        assert!(searcher.transposition_table.retrieve(hash).is_some());
+
+        searcher.clear_hash();
+
+        assert!(searcher.transposition_table.retrieve(hash).is_none());
+        assert_eq!(searcher.repetition.len(), 1);
+    }
+
+    #[test]
+    fn test_repetition_detection() {
+        let mut searcher = Searcher::new();
+        // The knights have gone out and back, so the position can repeat
+        let board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 4 3");
 
         // Simulate three-fold repetition
         searcher.push_position(&board);
         searcher.push_position(&board);
         searcher.push_position(&board);
 
-        assert!(searcher.is_draw_by_repetition(&board));
+        assert!(searcher.is_draw_by_repetition(&board, 2));
+    }
+
+    #[test]
+    fn test_perpetual_check_is_a_draw() {
+        // Black is a queen and rook up and threatens Qg2 mate, but White can check forever
+        let board = Board::new("6k1/5p1p/8/4Q3/8/8/qr6/6K1 w - - 0 1");
+        let mut searcher = Searcher::new();
+
+        // Black's king has enough squares that the draw only shows at depth 8
+        let (score, best_move) = searcher.find_best_move(&board, 8, None);
+
+        assert_eq!(best_move.unwrap().to_algebraic(), "e5g5");
+        assert_eq!(score, 0);
+        assert!(searcher.repetition.is_empty());
+    }
+
+    #[test]
+    fn test_game_positions_allow_claiming_repetition() {
+        // Black is a queen down, but Kg8 repeats a position for the third time
+        let board = Board::new("7k/8/8/8/8/8/8/KQ6 b - - 4 3");
+        let repeated = Board::new("6k1/8/8/8/8/8/8/KQ6 w - - 1 2");
+        let mut searcher = Searcher::new();
+
+        let (losing_score, _) = searcher.find_best_move(&board, 3, None);
+        assert!(losing_score < -500);
+
+        searcher.reset();
+        searcher.push_game_position(&repeated);
+        searcher.push_game_position(&repeated);
+        let (score, best_move) = searcher.find_best_move(&board, 3, None);
+
+        assert_eq!(best_move.unwrap().to_algebraic(), "h8g8");
+        assert_eq!(score, 0);
+    }
+
+    #[test]
+    fn test_search_repeats_twofold_and_game_threefold() {
+        let board = Board::new("6k1/8/8/8/8/8/8/KQ6 w - - 10 8");
+        let mut searcher = Searcher::new();
+
+        // Played once in the game, the position can still be repeated deep in the search
+        searcher.push_game_position(&board);
+        searcher.repetition.set_root();
+        assert!(!searcher.is_draw_by_repetition(&board, 3));
+
+        // Coming up once on the searched line is already a draw
+        searcher.push_position(&board);
+        assert!(searcher.is_draw_by_repetition(&board, 3));
+
+        // A second game occurrence makes the next one the third
+        searcher.reset();
+        searcher.push_game_position(&board);
+        searcher.push_game_position(&board);
+        searcher.repetition.set_root();
+        assert!(searcher.is_draw_by_repetition(&board, 3));
+    }
+
+    #[test]
+    fn test_root_avoids_repeating_game_position_when_winning() {
+        let board = Board::new("7k/8/8/8/8/8/8/KQ6 w - - 5 20");
+        let mut searcher = Searcher::new();
+
+        let (_, first_choice) = searcher.find_best_move(&board, 3, None);
+        let first_choice = first_choice.unwrap();
+
+        // The position after the preferred move has been played before, so
+        // playing it again would let Black claim a draw
+        searcher.reset();
+        searcher.push_game_position(&board.clone_with_move(&first_choice));
+        let (score, best_move) = searcher.find_best_move(&board, 3, None);
+
+        assert_ne!(best_move, Some(first_choice));
+        assert!(score > 500);
+    }
+
+    #[test]
+    fn test_irreversible_game_position_clears_history() {
+        let repeated = Board::new("6k1/8/8/8/8/8/8/KQ6 w - - 1 2");
+        let after_capture = Board::new("6k1/8/8/8/8/8/8/KQ6 w - - 0 2");
+        let mut searcher = Searcher::new();
+
+        searcher.push_game_position(&repeated);
+        searcher.push_game_position(&repeated);
+        searcher.push_game_position(&after_capture);
+
+        assert_eq!(searcher.repetition.len(), 1);
+    }
+
+    #[test]
+    fn test_pushes_connected_passed_pawns() {
+        // The kings are far from the pawns, so advancing them is the plan
+        for fen in [
+            "8/8/8/3PP3/8/8/k7/6K1 w - - 0 1",
+            "6k1/8/1K6/8/8/4pp2/8/8 b - - 0 1",
+        ] {
+            let board = Board::new(fen);
+            let mut searcher = Searcher::new();
+
+            let (_, best_move) = searcher.find_best_move(&board, 5, None);
+
+            assert_eq!(best_move.unwrap().piece_type, Piece::Pawn, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_seldepth_includes_quiescence() {
+        // Plenty of pieces hang or can be traded, so captures go on past the nominal depth
+        let board =
+            Board::new("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        let mut searcher = Searcher::new();
+
+        searcher.find_best_move(&board, 2, None);
+
+        assert!(searcher.seldepth > 2, "seldepth {}", searcher.seldepth);
+    }
+
+    #[test]
+    fn test_defends_king_against_mating_attack() {
+        // Qxh2# is threatened, taking the knight stops it
+        assert_finds_move(
+            "r1b2rk1/ppp2ppp/8/8/6nq/2N5/PPPPBPPP/R1BQR1K1 w - - 0 1",
+            "e2g4",
+        );
+    }
+
+    #[test]
+    fn test_only_winning_move_is_singular() {
+        // Taking the queen is far better than anything else
+        let board = Board::new("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1");
+        let mut searcher = Searcher::new();
+        let moves = searcher.move_generator.generate_moves(&board);
+        let capture = Move::from_algebraic("d1d5", &board).unwrap();
+        let king_move = Move::from_algebraic("e1f2", &board).unwrap();
+
+        assert!(searcher.is_singular(&board, moves.clone(), capture, 300, 6, 1));
+        assert!(!searcher.is_singular(&board, moves, king_move, -800, 6, 1));
+    }
+
+    /// Depth of the first iteration that sees a forced mate, searching one depth at a time
+    fn mate_depth(searcher: &mut Searcher, board: &Board, max_depth: u8) -> Option<u8> {
+        (1..=max_depth).find(|&depth| searcher.find_best_move(board, depth, None).0 > MATE_BOUND)
+    }
+
+    #[test]
+    fn test_singular_extension_finds_mate_sooner() {
+        // The only king moves that keep the pawn safe get extended, so the
+        // promotion and mate come into reach at a lower depth
+        let board = Board::new("7k/5K2/5P2/8/8/8/8/8 w - - 0 1");
+
+        let mut searcher = Searcher::new();
+        let depth = mate_depth(&mut searcher, &board, 16).expect("the mate is found");
+
+        let mut plain_searcher = Searcher::new();
+        plain_searcher.set_singular_extensions(false);
+        let plain_depth = mate_depth(&mut plain_searcher, &board, 16);
+
+        assert!(
+            plain_depth.is_none_or(|plain_depth| depth < plain_depth),
+            "mate at depth {} with extensions, {:?} without",
+            depth,
+            plain_depth
+        );
+    }
+
+    #[test]
+    fn test_singular_extensions_are_capped() {
+        // Pawn races are long forced lines full of only moves
+        let board = Board::new("8/ppp5/8/PPP5/8/8/8/k6K w - - 0 1");
+        let mut searcher = Searcher::new();
+
+        let (_, best_move) = searcher.find_best_move(&board, 10, None);
+        assert_eq!(best_move.unwrap().to_algebraic(), "b5b6");
+
+        // Extensions stop at twice the depth, so the line ends well short of the ply limit
+        assert!(
+            searcher.seldepth < MAX_SEARCH_DEPTH,
+            "seldepth {}",
+            searcher.seldepth
+        );
+    }
+
+    #[test]
+    fn test_internal_iterative_deepening_saves_nodes() {
+        // A single search on an empty table has no TT moves, so IID finds them
+        let board = Board::default();
+
+        let mut searcher = Searcher::new();
+        let (result, _) = searcher.search_position(&board, 6, NEGATIVE_INFINITY, INFINITY);
+
+        let mut plain_searcher = Searcher::new();
+        plain_searcher.set_internal_iterative_deepening(false);
+        let (plain_result, _) =
+            plain_searcher.search_position(&board, 6, NEGATIVE_INFINITY, INFINITY);
+
+        assert_eq!(result.score, plain_result.score);
+        assert!(
+            searcher.nodes() < plain_searcher.nodes(),
+            "{} nodes with IID, {} without",
+            searcher.nodes(),
+            plain_searcher.nodes()
+        );
+    }
+
+    #[test]
+    fn test_single_legal_move_returns_immediately() {
+        // The king's only way out of check is Kg2
+        let board = Board::new("7k/8/8/8/8/8/7P/r6K w - - 0 1");
+        let mut searcher = Searcher::new();
+
+        let start = std::time::Instant::now();
+        let (_, best_move) = searcher.find_best_move(&board, 64, None);
+
+        assert_eq!(best_move.unwrap().to_algebraic(), "h1g2");
+        assert!(start.elapsed() < Duration::from_millis(100));
     }
 
     #[test]
@@ -657,6 +2030,232 @@ mod tests {
         assert!(duration.as_secs() < 10, "Search too slow: {:?}", duration);
     }
 
+    #[test]
+    fn test_zero_budget_still_returns_move() {
+        let board = Board::default();
+        let mut searcher = Searcher::new();
+
+        let (score, best_move) =
+            searcher.find_best_move(&board, 64, Some(TimeLimits::fixed(Duration::ZERO)));
+
+        let best_move = best_move.expect("Engine should find a move");
+        assert!(searcher
+            .move_generator
+            .generate_moves(&board)
+            .contains(&best_move));
+        assert!(score > NEGATIVE_INFINITY, "score {}", score);
+
+        // The stopped first iteration ends the search instead of starting the next depths
+        assert_eq!(searcher.completed_depth(), 0);
+        assert_eq!(searcher.nodes(), 1);
+    }
+
+    #[test]
+    fn test_tight_budget_respects_hard_limit() {
+        let board =
+            Board::new("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10");
+        let mut searcher = Searcher::new();
+
+        let limits = TimeLimits::new(Duration::from_millis(20), Duration::from_millis(60));
+        let start = std::time::Instant::now();
+        let (_, best_move) = searcher.find_best_move(&board, 64, Some(limits));
+        let duration = start.elapsed();
+
+        assert!(best_move.is_some(), "Engine should find a move");
+        // Depth 64 would take far longer, so the search must have been cut
+        // short. The slack is generous so a loaded machine can't fail this
+        assert!(searcher.completed_depth() < 64);
+        assert!(
+            duration <= limits.hard + Duration::from_secs(2),
+            "Exceeded hard limit: {:?} vs {:?}",
+            duration,
+            limits.hard
+        );
+    }
+
+    #[test]
+    fn test_skips_iteration_predicted_to_exceed_hard_limit() {
+        let searcher = Searcher::new();
+        let limits = TimeLimits::new(Duration::from_millis(100), Duration::from_millis(300));
+        let elapsed = Duration::from_millis(50);
+
+        // Last iteration took 4x the previous so the next is predicted to take 400ms
+        let iteration_times = (
+            Some(Duration::from_millis(25)),
+            Some(Duration::from_millis(100)),
+        );
+        assert!(!searcher.should_start_iteration(limits, elapsed, iteration_times));
+
+        // Growing by 2x predicts 200ms which still fits within the hard limit
+        let iteration_times = (
+            Some(Duration::from_millis(50)),
+            Some(Duration::from_millis(100)),
+        );
+        assert!(searcher.should_start_iteration(limits, elapsed, iteration_times));
+
+        // Past the soft limit no iteration is started
+        assert!(!searcher.should_start_iteration(limits, Duration::from_millis(100), (None, None)));
+    }
+
+    #[cfg(feature = "syzygy")]
+    #[test]
+    fn test_plays_tablebase_move_at_root() {
+        use crate::tablebase::TB_WIN_SCORE;
+
+        let board = Board::new("8/8/8/4k3/8/8/8/R3K3 w - - 0 1");
+        let mut searcher = Searcher::new();
+        searcher.set_tablebase(Some(Arc::new(ExtraPieceTablebase)));
+
+        let (score, best_move) = searcher.find_best_move(&board, SEARCH_DEPTH, None);
+        let expected = ExtraPieceTablebase.probe_root(&board).unwrap().0;
+
+        assert_eq!(best_move, Some(expected));
+        assert_eq!(score, TB_WIN_SCORE);
+        assert_eq!(searcher.nodes(), 0);
+    }
+
+    #[cfg(feature = "syzygy")]
+    #[test]
+    fn test_tablebase_scores_capture_into_krvk() {
+        use crate::tablebase::TB_WIN_SCORE;
+
+        // Too many pieces for the tables until the knight is taken
+        let board = Board::new("8/8/8/3k4/8/8/3n4/3RK3 w - - 0 1");
+        let mut searcher = Searcher::new();
+        searcher.set_tablebase(Some(Arc::new(ExtraPieceTablebase)));
+
+        let (score, best_move) = searcher.find_best_move(&board, 2, None);
+
+        assert_eq!(score, TB_WIN_SCORE - 1);
+        assert_eq!(
+            best_move.unwrap().to,
+            crate::square::algebraic_to_square("d2")
+        );
+    }
+
+    #[test]
+    fn test_prints_info_for_every_depth() {
+        let mut searcher = Searcher::new();
+        searcher.find_best_move(&Board::default(), 5, None);
+
+        let field = |line: &str, name: &str| -> u64 {
+            let mut words = line.split_whitespace();
+            words.find(|&word| word == name);
+            words.next().unwrap().parse().unwrap()
+        };
+
+        let lines = searcher.info_lines();
+        assert_eq!(lines.len(), 5);
+        for (idx, line) in lines.iter().enumerate() {
+            assert!(line.starts_with("info depth "), "{}", line);
+            assert_eq!(field(line, "depth"), idx as u64 + 1);
+            assert!(line.contains(" pv "), "{}", line);
+        }
+
+        // Node counts are for the whole search so far
+        let nodes: Vec<u64> = lines.iter().map(|line| field(line, "nodes")).collect();
+        assert!(nodes.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(*nodes.last().unwrap(), searcher.nodes());
+    }
+
+    #[test]
+    fn test_reported_score_is_from_side_to_move() {
+        let reported_score = |fen: &str| -> i32 {
+            let mut searcher = Searcher::new();
+            searcher.find_best_move(&Board::new(fen), 3, None);
+
+            let line = searcher.info_lines().last().unwrap();
+            let mut words = line.split_whitespace();
+            words.find(|&word| word == "cp");
+            words.next().unwrap().parse().unwrap()
+        };
+
+        // White is a queen up
+        assert!(reported_score("4k3/pp6/8/8/8/8/PP6/3QK3 w - - 0 1") > 500);
+        assert!(reported_score("4k3/pp6/8/8/8/8/PP6/3QK3 b - - 0 1") < -500);
+
+        // Black is a rook up
+        assert!(reported_score("r3k3/pp6/8/8/8/8/PP6/4K3 b - - 0 1") > 300);
+        assert!(reported_score("r3k3/pp6/8/8/8/8/PP6/4K3 w - - 0 1") < -300);
+    }
+
+    #[test]
+    fn test_fail_high_root_reports_lowerbound() {
+        let board = Board::default();
+        let mut searcher = Searcher::new();
+
+        // The start position is far better than a queen down, so the root fails high
+        let (result, bound) = searcher.search_position(&board, 2, NEGATIVE_INFINITY, -900);
+        assert_eq!(bound, Bounds::Lower);
+
+        searcher.report_info(2, result.score, bound);
+        let line = searcher.info_lines().last().unwrap();
+        assert!(
+            line.contains(&format!(" score cp {} lowerbound ", result.score)),
+            "{}",
+            line
+        );
+
+        // A fail low reports an upper bound, and a full window an exact score
+        let (result, bound) = searcher.search_position(&board, 2, 900, INFINITY);
+        searcher.report_info(2, result.score, bound);
+        assert!(searcher
+            .info_lines()
+            .last()
+            .unwrap()
+            .contains(" upperbound "));
+
+        let (result, bound) = searcher.search_position(&board, 2, NEGATIVE_INFINITY, INFINITY);
+        searcher.report_info(2, result.score, bound);
+        let line = searcher.info_lines().last().unwrap();
+        assert!(!line.contains("bound"), "{}", line);
+    }
+
+    #[test]
+    fn test_soft_limit_stops_between_iterations() {
+        let board = Board::default();
+        let mut searcher = Searcher::new();
+
+        // Past the soft limit only the first iteration runs, however long the hard limit is
+        let limits = TimeLimits::new(Duration::ZERO, Duration::from_secs(10));
+        let result = searcher.analyze(
+            &board,
+            SearchLimits {
+                depth: 10,
+                time: Some(limits),
+            },
+        );
+
+        assert!(result.best_move.is_some());
+        assert_eq!(result.depth, 1);
+    }
+
+    #[test]
+    fn test_instability_extends_soft_limit() {
+        let e2e4 = Move::new(12, 28, Piece::Pawn, MoveType::Quiet);
+        let d2d4 = Move::new(11, 27, Piece::Pawn, MoveType::Quiet);
+        let previous = (50, Some(e2e4));
+
+        let scale =
+            |score, mv| Searcher::instability_scale(previous, &NodeResult::new(score, Some(mv)));
+        assert_eq!(scale(40, e2e4), 1.0);
+        assert_eq!(scale(60, d2d4), 1.0 + BEST_MOVE_CHANGE_EXTENSION);
+        assert_eq!(
+            scale(50 - PANIC_SCORE_DROP, e2e4),
+            1.0 + SCORE_DROP_EXTENSION
+        );
+        assert_eq!(
+            scale(-100, d2d4),
+            1.0 + BEST_MOVE_CHANGE_EXTENSION + SCORE_DROP_EXTENSION
+        );
+
+        // The stretched soft limit never passes the hard limit
+        let limits = TimeLimits::new(Duration::from_millis(100), Duration::from_millis(180));
+        assert_eq!(limits.scale_soft(1.5).soft, Duration::from_millis(150));
+        assert_eq!(limits.scale_soft(2.0).soft, limits.hard);
+        assert_eq!(limits.scale_soft(2.0).hard, limits.hard);
+    }
+
     #[test]
     fn test_time_management() {
         let board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
@@ -664,7 +2263,7 @@ mod tests {
 
         let time_limit = Duration::from_millis(100);
         let start = std::time::Instant::now();
-        searcher.find_best_move(&board, 10, Some(time_limit));
+        searcher.find_best_move(&board, 10, Some(TimeLimits::fixed(time_limit)));
         let duration = start.elapsed();
 
         // Should respect time limit
@@ -1,21 +1,15 @@
 pub type Square = u8;
 
-// Castling squares
-pub const A1: Square = 0;
+// Squares the king and rook end up on after castling
 pub const C1: Square = 2;
 pub const D1: Square = 3;
-pub const E1: Square = 4;
 pub const F1: Square = 5;
 pub const G1: Square = 6;
-pub const H1: Square = 7;
 
-pub const A8: Square = 56;
 pub const C8: Square = 58;
 pub const D8: Square = 59;
-pub const E8: Square = 60;
 pub const F8: Square = 61;
 pub const G8: Square = 62;
-pub const H8: Square = 63;
 
 pub fn rank_file_to_square(rank: u8, file: u8) -> Square {
     rank * 8 + file
@@ -40,22 +34,33 @@ pub fn algebraic_to_square(alg: &str) -> Square {
     let mut s = alg.chars();
     let file = s.next().unwrap();
     let rank = s.next().unwrap();
-    let file = file as u8 - 'a' as u8;
-    let rank = rank as u8 - '1' as u8;
+    let file = file as u8 - b'a';
+    let rank = rank as u8 - b'1';
     rank_file_to_square(rank, file)
 }
 
+// Same as algebraic_to_square but returns None when the string isn't a valid square
+pub fn try_algebraic_to_square(alg: &str) -> Option<Square> {
+    let mut s = alg.chars();
+    match (s.next(), s.next(), s.next()) {
+        (Some(file @ 'a'..='h'), Some(rank @ '1'..='8'), None) => {
+            Some(rank_file_to_square(rank as u8 - b'1', file as u8 - b'a'))
+        }
+        _ => None,
+    }
+}
+
 pub fn square_to_algebraic(square: Square) -> String {
     let file = square_to_file(square);
     let rank = square_to_rank(square);
-    let file_char = ('a' as u8 + file) as char;
-    let rank_char = ('1' as u8 + rank) as char;
+    let file_char = (b'a' + file) as char;
+    let rank_char = (b'1' + rank) as char;
     format!("{}{}", file_char, rank_char)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::square::{square_to_algebraic, algebraic_to_square, rank_file_to_square, square_to_rank_file};
+    use crate::square::{square_to_algebraic, algebraic_to_square, rank_file_to_square, square_to_rank_file, try_algebraic_to_square};
 
 
     #[test]
@@ -74,6 +79,16 @@ mod tests {
         assert_eq!(63, algebraic_to_square("h8"));
     }
 
+    #[test]
+    fn test_try_algebraic_to_square() {
+        assert_eq!(Some(0), try_algebraic_to_square("a1"));
+        assert_eq!(Some(63), try_algebraic_to_square("h8"));
+        assert_eq!(None, try_algebraic_to_square("i1"));
+        assert_eq!(None, try_algebraic_to_square("a9"));
+        assert_eq!(None, try_algebraic_to_square("a10"));
+        assert_eq!(None, try_algebraic_to_square("a"));
+    }
+
     #[test]
     fn test_rank_file_to_square() {
         assert_eq!(0, rank_file_to_square(0, 0));
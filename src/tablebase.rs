@@ -0,0 +1,68 @@
+use crate::board::Board;
+use crate::moves::Move;
+use crate::pieces::Color;
+use crate::transposition::MATE_BOUND;
+
+/// Score of a tablebase win at the root, below every mate score so a real mate
+/// is still preferred
+pub const TB_WIN_SCORE: i32 = MATE_BOUND - 1;
+
+/// Result of a tablebase position for the side to move.
+///
+/// Cursed wins and blessed losses are wins and losses that the fifty-move rule
+/// turns into draws.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+/// Endgame tablebases the search can probe, e.g. Syzygy tables read by an
+/// external prober.
+pub trait Tablebase: Send + Sync {
+    /// Most pieces, kings included, in a position the tables cover
+    fn max_pieces(&self) -> u32;
+
+    /// Looks up the result of a position, `None` if it isn't in the tables
+    fn probe_wdl(&self, board: &Board) -> Option<Wdl>;
+
+    /// Picks the move that keeps the best result at the root, using distance to
+    /// zeroing so a won position makes progress instead of shuffling
+    fn probe_root(&self, board: &Board) -> Option<(Move, Wdl)>;
+}
+
+/// Whether a position can be looked up, which needs few enough pieces and no
+/// castling rights since the tables don't cover castling
+pub fn can_probe(tablebase: &dyn Tablebase, board: &Board) -> bool {
+    let no_castling = [Color::White, Color::Black]
+        .into_iter()
+        .all(|color| board.castling_ability(color) == (false, false));
+
+    no_castling && board.bb_all().count_ones() <= tablebase.max_pieces()
+}
+
+/// Scores a tablebase result found `ply` half moves from the root, so wins
+/// that are reached sooner are preferred
+pub fn wdl_score(wdl: Wdl, ply: u8) -> i32 {
+    match wdl {
+        Wdl::Win => TB_WIN_SCORE - ply as i32,
+        Wdl::Loss => -TB_WIN_SCORE + ply as i32,
+        Wdl::BlessedLoss | Wdl::Draw | Wdl::CursedWin => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wdl_score() {
+        assert_eq!(wdl_score(Wdl::Win, 0), TB_WIN_SCORE);
+        assert_eq!(wdl_score(Wdl::Loss, 3), -TB_WIN_SCORE + 3);
+        assert_eq!(wdl_score(Wdl::CursedWin, 2), 0);
+        assert!(wdl_score(Wdl::Win, 0) < MATE_BOUND);
+    }
+}
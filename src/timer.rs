@@ -1,6 +1,32 @@
 use crate::moves::Move;
 use std::time::{Duration, Instant};
 
+/// Time budget for a single search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeLimits {
+    /// No new iteration is started once this much time has passed
+    pub soft: Duration,
+    /// The search is aborted once this much time has passed
+    pub hard: Duration,
+}
+
+impl TimeLimits {
+    /// Creates limits with separate soft and hard durations
+    pub fn new(soft: Duration, hard: Duration) -> Self {
+        Self { soft, hard }
+    }
+
+    /// Creates limits for a fixed time search where soft and hard are the same
+    pub fn fixed(limit: Duration) -> Self {
+        Self::new(limit, limit)
+    }
+
+    /// Stretches the soft limit by a factor, never past the hard limit
+    pub fn scale_soft(self, factor: f64) -> Self {
+        Self::new(self.soft.mul_f64(factor).min(self.hard), self.hard)
+    }
+}
+
 /// Manages search timing and statistics
 #[derive(Debug, Clone)]
 pub struct SearchTimer {
@@ -29,6 +55,15 @@ impl SearchTimer {
         self.nodes_searched = 0;
     }
 
+    /// Restarts the clock with a new time limit while keeping the node count
+    ///
+    /// # Arguments
+    /// * `time_limit` - Optional max duration for the rest of the search
+    pub fn restart(&mut self, time_limit: Option<Duration>) {
+        self.start_time = Some(Instant::now());
+        self.time_limit = time_limit;
+    }
+
     /// Resets the timer without changing the time limit
     #[allow(dead_code)]
     pub fn reset(&mut self) {
@@ -65,7 +100,6 @@ impl SearchTimer {
     }
 
     /// Gets the number of nodes searched
-    #[allow(dead_code)]
     pub fn nodes(&self) -> u64 {
         self.nodes_searched
     }
@@ -81,7 +115,6 @@ impl SearchTimer {
     }
 
     /// Gets the elapsed time as a Duration
-    #[allow(dead_code)]
     pub fn elapsed(&self) -> Duration {
         self.start_time
             .map(|start| start.elapsed())
@@ -111,26 +144,40 @@ impl SearchTimer {
         )
     }
 
-    /// Prints UCI-formatted search information
+    /// Formats UCI search information
     ///
     /// # Arguments
     /// * `depth` - Current search depth
-    /// * `score` - Current best score (in centipawns)
-    /// * `best_move` - Current best move
-    pub fn print_info(&self, depth: u8, score: i32, best_move: Option<Move>) {
-        print!(
-            "info depth {} score cp {} nodes {} time {} nps {}",
+    /// * `seldepth` - Deepest ply reached, including quiescence search
+    /// * `score` - Current best score in UCI form, like `cp 25` or `cp 40 lowerbound`
+    /// * `hashfull` - Permille of the transposition table used by this search
+    /// * `pv` - Current principal variation
+    /// * `chess960` - Whether castles are written as the king moving to its rook
+    pub fn info_line(
+        &self,
+        depth: u8,
+        seldepth: u8,
+        score: &str,
+        hashfull: usize,
+        pv: &[Move],
+        chess960: bool,
+    ) -> String {
+        let mut line = format!(
+            "info depth {} seldepth {} score {} nodes {} time {} nps {} hashfull {}",
             depth,
+            seldepth,
             score,
             self.nodes_searched,
             self.elapsed_ms(),
-            self.nps()
+            self.nps(),
+            hashfull
         );
 
-        if let Some(mv) = best_move {
-            print!(" pv {}", mv.to_algebraic());
+        if !pv.is_empty() {
+            let moves: Vec<String> = pv.iter().map(|mv| mv.to_uci(chess960)).collect();
+            line.push_str(&format!(" pv {}", moves.join(" ")));
         }
-        println!();
+        line
     }
 
     /// Checks if a search has started
@@ -297,6 +344,21 @@ mod tests {
         assert_eq!(timer.time_limit(), Some(Duration::from_secs(10))); // Limit preserved
     }
 
+    #[test]
+    fn test_restart_keeps_nodes() {
+        let mut timer = SearchTimer::new();
+        timer.start(None);
+
+        timer.add_nodes(100);
+        thread::sleep(Duration::from_millis(10));
+
+        timer.restart(Some(Duration::from_secs(5)));
+
+        assert_eq!(timer.nodes(), 100);
+        assert!(timer.elapsed_ms() < 5);
+        assert_eq!(timer.time_limit(), Some(Duration::from_secs(5)));
+    }
+
     #[test]
     fn test_stats_string() {
         let mut timer = SearchTimer::new();
@@ -1,41 +1,123 @@
-use std::collections::HashMap;
-use crate::moves::Move;
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::moves::{Move, MoveType};
+use crate::pieces::PieceIterator;
+use crate::search::CHECKMATE_SCORE;
+
+// Scores at least this close to the checkmate score are mates, the distance being how many plies away
+pub const MATE_BOUND: i32 = CHECKMATE_SCORE - u8::MAX as i32;
+
+// Number of entries in the table (must be a power of two)
+const DEFAULT_SIZE: usize = 1 << 20;
+
+// Number of slots sampled to estimate how full the table is, hashfull is reported in permille
+const HASHFULL_SAMPLE: usize = 1000;
+
+// Layout of an entry's data packed into a u64
+const EVAL_SHIFT: u32 = 0;
+const DEPTH_SHIFT: u32 = 16;
+const BOUNDS_SHIFT: u32 = 24;
+const HAS_MOVE_SHIFT: u32 = 26;
+const FROM_SHIFT: u32 = 27;
+const TO_SHIFT: u32 = 33;
+const PIECE_SHIFT: u32 = 39;
+const MOVE_TYPE_SHIFT: u32 = 42;
+const GENERATION_SHIFT: u32 = 45;
+const OCCUPIED_SHIFT: u32 = 63;
+
+// Each slot holds the packed data and the hash key XORed with it, so entries can be
+// read and written from several threads without locking. A read that races a write
+// gets a key and data from different entries, which no longer XOR to the probed key
+// and is thrown away
+struct Slot {
+    key_xor_data: AtomicU64,
+    data: AtomicU64,
+}
 
 pub struct TranspositionTable {
-    table: HashMap<u64, Entry>,
+    slots: Vec<Slot>,
+    // Stamped on every stored entry and advanced for each search, so entries left
+    // over from earlier moves of the game can be told apart and replaced first
+    generation: u8,
 }
 
 impl TranspositionTable {
     pub fn new() -> Self {
-        Self {
-            table: HashMap::new(),
-        }
+        Self::with_size(DEFAULT_SIZE)
+    }
+
+    // Creates a table with a number of entries, which must be a power of two
+    pub fn with_size(size: usize) -> Self {
+        assert!(size.is_power_of_two(), "Transposition table size must be a power of two");
+
+        let slots = (0..size)
+            .map(|_| Slot { key_xor_data: AtomicU64::new(0), data: AtomicU64::new(0) })
+            .collect();
+
+        Self { slots, generation: 0 }
+    }
+
+    // Starts a new generation, called before searching each root position
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
     }
 
-    pub fn store(&mut self, hash_key: u64, eval: i32, best_move: Option<Move>, depth: u8, bounds: Bounds) {
+    pub fn store(&self, hash_key: u64, eval: i32, best_move: Option<Move>, depth: u8, bounds: Bounds) {
         let entry = Entry {
             hash_key,
             eval,
             best_move,
             depth,
             bounds,
+            generation: self.generation,
+        };
+        let slot = self.slot(hash_key);
+
+        // Entries from older searches are always replaced, ones from this search are depth-preferred
+        let should_replace = match Self::load(slot) {
+            Some(prev_entry) => prev_entry.generation != self.generation || prev_entry.depth <= depth,
+            None => true,
         };
 
-        // Depth-Preferred Replacement
-        let prev_entry = self.table.get(&hash_key);
-        if prev_entry.is_none() {
-            self.table.insert(hash_key, entry);
-        } else if prev_entry.is_some() && prev_entry.unwrap().depth <= depth {
-            self.table.insert(hash_key, entry);
+        if should_replace {
+            let data = entry.pack();
+            slot.key_xor_data.store(hash_key ^ data, Ordering::Relaxed);
+            slot.data.store(data, Ordering::Relaxed);
         }
     }
 
-    pub fn retrieve(&self, key: u64) -> Option<&Entry> {
-        let entry = self.table.get(&key);
-        if entry.is_some() && entry.unwrap().hash_key == key {
-            return entry;
+    pub fn clear(&mut self) {
+        for slot in &self.slots {
+            slot.key_xor_data.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
         }
-        return None;
+    }
+
+    pub fn retrieve(&self, key: u64) -> Option<Entry> {
+        Self::load(self.slot(key)).filter(|entry| entry.hash_key == key)
+    }
+
+    // Permille of the table holding entries from the current search, estimated from the first slots
+    pub fn hashfull(&self) -> usize {
+        let sample = HASHFULL_SAMPLE.min(self.slots.len());
+        let used = self.slots[..sample]
+            .iter()
+            .filter_map(Self::load)
+            .filter(|entry| entry.generation == self.generation)
+            .count();
+
+        used * 1000 / sample
+    }
+
+    // Reads whichever entry is in a slot, with the key it was stored under
+    fn load(slot: &Slot) -> Option<Entry> {
+        let key_xor_data = slot.key_xor_data.load(Ordering::Relaxed);
+        let data = slot.data.load(Ordering::Relaxed);
+
+        Entry::unpack(key_xor_data ^ data, data)
+    }
+
+    fn slot(&self, key: u64) -> &Slot {
+        &self.slots[(key as usize) & (self.slots.len() - 1)]
     }
 }
 
@@ -46,6 +128,105 @@ pub struct Entry {
     pub best_move: Option<Move>,
     pub depth: u8,
     pub bounds: Bounds,
+    pub generation: u8,
+}
+
+impl Entry {
+    // Packs everything but the key into a u64, evals are clamped to an i16
+    fn pack(&self) -> u64 {
+        let eval = self.eval.clamp(i16::MIN as i32, i16::MAX as i32) as i16 as u16 as u64;
+        let bounds = match self.bounds {
+            Bounds::Exact => 0,
+            Bounds::Lower => 1,
+            Bounds::Upper => 2,
+        };
+
+        let mut data = (1 << OCCUPIED_SHIFT)
+            | (eval << EVAL_SHIFT)
+            | ((self.depth as u64) << DEPTH_SHIFT)
+            | (bounds << BOUNDS_SHIFT)
+            | ((self.generation as u64) << GENERATION_SHIFT);
+
+        if let Some(mv) = self.best_move {
+            let move_type = match mv.move_type {
+                MoveType::Quiet => 0,
+                MoveType::Capture => 1,
+                MoveType::EnPassant => 2,
+                MoveType::Castle => 3,
+                MoveType::Promotion => 4,
+            };
+
+            data |= (1 << HAS_MOVE_SHIFT)
+                | ((mv.from as u64) << FROM_SHIFT)
+                | ((mv.to as u64) << TO_SHIFT)
+                | ((mv.piece_type.index() as u64) << PIECE_SHIFT)
+                | (move_type << MOVE_TYPE_SHIFT);
+        }
+
+        data
+    }
+
+    // Unpacks data written by pack, returning None for an empty slot
+    fn unpack(hash_key: u64, data: u64) -> Option<Self> {
+        let field = |shift: u32, bits: u32| (data >> shift) & ((1 << bits) - 1);
+
+        if field(OCCUPIED_SHIFT, 1) == 0 {
+            return None;
+        }
+
+        let bounds = match field(BOUNDS_SHIFT, 2) {
+            0 => Bounds::Exact,
+            1 => Bounds::Lower,
+            _ => Bounds::Upper,
+        };
+
+        let best_move = if field(HAS_MOVE_SHIFT, 1) == 1 {
+            let move_type = match field(MOVE_TYPE_SHIFT, 3) {
+                0 => MoveType::Quiet,
+                1 => MoveType::Capture,
+                2 => MoveType::EnPassant,
+                3 => MoveType::Castle,
+                _ => MoveType::Promotion,
+            };
+            let piece = PieceIterator::new().nth(field(PIECE_SHIFT, 3) as usize)?;
+
+            Some(Move::new(field(FROM_SHIFT, 6) as u8, field(TO_SHIFT, 6) as u8, piece, move_type))
+        } else {
+            None
+        };
+
+        Some(Self {
+            hash_key,
+            eval: field(EVAL_SHIFT, 16) as u16 as i16 as i32,
+            best_move,
+            depth: field(DEPTH_SHIFT, 8) as u8,
+            bounds,
+            generation: field(GENERATION_SHIFT, 8) as u8,
+        })
+    }
+}
+
+// Mate scores count plies from the root, so they are stored counting from the node
+// instead. The same position can then be reached at another ply and report the right distance
+pub fn score_to_tt(score: i32, ply: u8) -> i32 {
+    if score >= MATE_BOUND {
+        score + ply as i32
+    } else if score <= -MATE_BOUND {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+// Rebases a stored mate score on the ply it is retrieved at, undoing score_to_tt
+pub fn score_from_tt(score: i32, ply: u8) -> i32 {
+    if score >= MATE_BOUND {
+        score - ply as i32
+    } else if score <= -MATE_BOUND {
+        score + ply as i32
+    } else {
+        score
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -57,7 +238,8 @@ pub enum Bounds {
 
 #[cfg(test)]
 mod tests {
-    use crate::transposition::{TranspositionTable, Bounds};
+    use crate::transposition::{TranspositionTable, Bounds, score_to_tt, score_from_tt};
+    use crate::search::CHECKMATE_SCORE;
     use crate::zobrist::ZobristTable;
     use crate::pieces::Piece;
     use crate::moves::{Move, MoveType};
@@ -65,7 +247,7 @@ mod tests {
 
     #[test]
     fn retrieve_position_in_table() {
-        let mut tt = TranspositionTable::new();
+        let tt = TranspositionTable::new();
         let zobrist = ZobristTable::new();
         let board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
 
@@ -97,7 +279,7 @@ mod tests {
 
     #[test]
     fn update_entry_with_new_greater_depth() {
-        let mut tt = TranspositionTable::new();
+        let tt = TranspositionTable::new();
         let zobrist = ZobristTable::new();
         let board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
 
@@ -126,7 +308,7 @@ mod tests {
 
     #[test]
     fn keep_entry_with_old_greater_depth() {
-        let mut tt = TranspositionTable::new();
+        let tt = TranspositionTable::new();
         let zobrist = ZobristTable::new();
         let board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
 
@@ -152,5 +334,114 @@ mod tests {
         assert_eq!(greater_depth_depth, entry.unwrap().depth);
         assert_eq!(greater_depth_bounds, entry.unwrap().bounds);
     }
-    
-}
\ No newline at end of file
+
+    #[test]
+    fn mate_score_is_rebased_on_retrieval_ply() {
+        let tt = TranspositionTable::new();
+        let zobrist = ZobristTable::new();
+        let board = Board::new("k7/8/8/3K4/8/8/8/7R w - - 0 1");
+
+        // Found at ply 3 with mate 5 plies after it, so mate 8 plies from the root
+        let store_ply = 3;
+        let mate_score = CHECKMATE_SCORE - 8;
+        tt.store(zobrist.hash(&board), score_to_tt(mate_score, store_ply), None, 6, Bounds::Exact);
+
+        // Reached at ply 1 through another line, mate is still 5 plies away
+        let retrieve_ply = 1;
+        let entry = tt.retrieve(zobrist.hash(&board)).unwrap();
+        assert_eq!(score_from_tt(entry.eval, retrieve_ply), CHECKMATE_SCORE - 6);
+
+        // The same holds for being mated
+        assert_eq!(score_from_tt(score_to_tt(-mate_score, store_ply), retrieve_ply), -CHECKMATE_SCORE + 6);
+    }
+
+    #[test]
+    fn non_mate_score_is_unchanged() {
+        assert_eq!(score_to_tt(150, 10), 150);
+        assert_eq!(score_from_tt(-150, 10), -150);
+    }
+
+    #[test]
+    fn entry_fields_survive_packing() {
+        let tt = TranspositionTable::new();
+        let moves = [
+            Move::new(63, 0, Piece::King, MoveType::Castle),
+            Move::new(52, 61, Piece::Knight, MoveType::Promotion),
+            Move::new(33, 40, Piece::Pawn, MoveType::EnPassant),
+        ];
+
+        for (idx, mv) in moves.into_iter().enumerate() {
+            let key = 0xdead_beef + idx as u64;
+            tt.store(key, -CHECKMATE_SCORE, Some(mv), 255, Bounds::Upper);
+
+            let entry = tt.retrieve(key).unwrap();
+            assert_eq!(entry.eval, -CHECKMATE_SCORE);
+            assert_eq!(entry.best_move, Some(mv));
+            assert_eq!(entry.depth, 255);
+            assert_eq!(entry.bounds, Bounds::Upper);
+        }
+
+        tt.store(42, 0, None, 0, Bounds::Exact);
+        assert_eq!(tt.retrieve(42).unwrap().best_move, None);
+    }
+
+    #[test]
+    fn concurrent_access_never_returns_mismatched_entry() {
+        // A small table so the threads keep overwriting each other's slots
+        let tt = TranspositionTable::with_size(64);
+
+        // Every field is derived from the key, so a torn entry can be spotted
+        let expected_eval = |key: u64| (key % 20_000) as i32 - 10_000;
+        let expected_depth = |key: u64| (key % 200) as u8;
+
+        std::thread::scope(|scope| {
+            for thread in 0..4u64 {
+                let tt = &tt;
+                scope.spawn(move || {
+                    for i in 0..50_000u64 {
+                        let key = ((thread << 32) | i).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+                        tt.store(key, expected_eval(key), None, expected_depth(key), Bounds::Exact);
+
+                        let probe = ((thread << 32) | (i / 2)).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+                        if let Some(entry) = tt.retrieve(probe) {
+                            assert_eq!(entry.hash_key, probe);
+                            assert_eq!(entry.eval, expected_eval(probe));
+                            assert_eq!(entry.depth, expected_depth(probe));
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn stale_entries_are_evicted_before_fresh_deep_ones() {
+        let mut tt = TranspositionTable::with_size(1);
+
+        // Both keys share the only slot, a shallow entry can't push out a deep one from this search
+        tt.store(1, 100, None, 10, Bounds::Exact);
+        tt.store(2, 200, None, 2, Bounds::Exact);
+        assert_eq!(tt.retrieve(1).unwrap().depth, 10);
+        assert!(tt.retrieve(2).is_none());
+
+        // Once the deep entry is from an older search it gives way
+        tt.new_search();
+        tt.store(2, 200, None, 2, Bounds::Exact);
+        assert!(tt.retrieve(1).is_none());
+        assert_eq!(tt.retrieve(2).unwrap().generation, 1);
+    }
+
+    #[test]
+    fn hashfull_counts_entries_from_current_search() {
+        let mut tt = TranspositionTable::with_size(1024);
+        assert_eq!(tt.hashfull(), 0);
+
+        for key in 0..500 {
+            tt.store(key, 0, None, 1, Bounds::Exact);
+        }
+        assert_eq!(tt.hashfull(), 500);
+
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+    }
+}
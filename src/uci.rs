@@ -1,31 +1,251 @@
+use crate::bench::{run_bench, DEFAULT_BENCH_DEPTH};
 use crate::board::Board;
-use crate::move_gen::MoveGenerator;
-use crate::pieces::Color;
-use crate::search::Searcher;
+use crate::epd::{parse_epd, run_suite, EpdEntry};
+use crate::eval::{
+    game_phase, EvalBreakdown, EvalWeight, DEFAULT_ENDGAME_VALUES, DEFAULT_OPENING_VALUES,
+    EVAL_WEIGHTS, MAX_PHASE,
+};
+use crate::move_gen::{GameStatus, MoveGenerator};
+use crate::moves::Move;
+use crate::pgn::to_pgn;
+use crate::pieces::{Color, Piece};
+use crate::search::{SearchSignals, Searcher, MAX_SEARCH_DEPTH};
+use crate::timer::TimeLimits;
+use crate::zobrist::ZobristTable;
+use std::io::BufRead;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
 use std::time::Duration;
 
+/// Largest contempt in centipawns accepted by the Contempt option
+const MAX_CONTEMPT: i32 = 1000;
+
+/// Stack size for the background search thread, which recurses deeply and
+/// carries the whole searcher
+const SEARCH_THREAD_STACK_SIZE: usize = 16 * 1024 * 1024;
+
+/// Largest piece value in centipawns accepted by the piece value options
+const MAX_PIECE_VALUE: i32 = 5000;
+
+/// Largest value accepted by the evaluation weight options
+const MAX_EVAL_WEIGHT: i32 = 1000;
+
+/// Milliseconds of the clock `calculate_move_time` tries to keep in reserve
+const MOVE_TIME_RESERVE_MS: u64 = 5_000;
+
+/// Fewest milliseconds `calculate_move_time` allocates, however little is on the clock
+const MIN_MOVE_TIME_MS: u64 = 10;
+
+/// Time to search when a command gives neither a depth nor a way to time the search
+const DEFAULT_MOVE_TIME: Duration = Duration::from_secs(1);
+
+/// Maps a lowercase option name like `kingsafety` or `phalanxendgame` to the
+/// evaluation weight it configures and whether it sets the endgame value
+fn eval_weight_option(name: &str) -> Option<(EvalWeight, bool)> {
+    let (weight_name, is_endgame) = match name.strip_suffix("endgame") {
+        Some(weight_name) => (weight_name, true),
+        None => (name, false),
+    };
+
+    EVAL_WEIGHTS
+        .into_iter()
+        .find(|weight| weight.to_string().to_lowercase() == weight_name)
+        .map(|weight| (weight, is_endgame))
+}
+
+/// Maps a lowercase option name like `pawnvalue` or `queenvalueendgame` to the piece
+/// it configures and whether it sets the endgame value
+fn piece_value_option(name: &str) -> Option<(Piece, bool)> {
+    let (piece_name, is_endgame) = match name.strip_suffix("valueendgame") {
+        Some(piece_name) => (piece_name, true),
+        None => (name.strip_suffix("value")?, false),
+    };
+
+    let piece = match piece_name {
+        "pawn" => Piece::Pawn,
+        "knight" => Piece::Knight,
+        "bishop" => Piece::Bishop,
+        "rook" => Piece::Rook,
+        "queen" => Piece::Queen,
+        _ => return None,
+    };
+
+    Some((piece, is_endgame))
+}
+
+/// Declares the engine's options, printed in response to `uci`
+fn option_declarations() -> Vec<String> {
+    let mut options = vec![
+        "option name Ponder type check default false".to_string(),
+        "option name Clear Hash type button".to_string(),
+        "option name QSearchChecks type check default true".to_string(),
+        "option name InternalIterativeDeepening type check default true".to_string(),
+        "option name SingularExtensions type check default true".to_string(),
+        "option name Debug type check default false".to_string(),
+        "option name UCI_Chess960 type check default false".to_string(),
+        format!(
+            "option name Contempt type spin default 0 min {} max {}",
+            -MAX_CONTEMPT, MAX_CONTEMPT
+        ),
+    ];
+
+    for piece in [
+        Piece::Pawn,
+        Piece::Knight,
+        Piece::Bishop,
+        Piece::Rook,
+        Piece::Queen,
+    ] {
+        options.push(format!(
+            "option name {}Value type spin default {} min 0 max {}",
+            piece,
+            DEFAULT_OPENING_VALUES[piece.index()],
+            MAX_PIECE_VALUE
+        ));
+        options.push(format!(
+            "option name {}ValueEndgame type spin default {} min 0 max {}",
+            piece,
+            DEFAULT_ENDGAME_VALUES[piece.index()],
+            MAX_PIECE_VALUE
+        ));
+    }
+
+    for weight in EVAL_WEIGHTS {
+        let (opening, endgame) = weight.default_value();
+        options.push(format!(
+            "option name {} type spin default {} min 0 max {}",
+            weight, opening, MAX_EVAL_WEIGHT
+        ));
+        options.push(format!(
+            "option name {}Endgame type spin default {} min 0 max {}",
+            weight, endgame, MAX_EVAL_WEIGHT
+        ));
+    }
+
+    options
+}
+
+/// Searches a position and prints the best move along with the expected reply.
+///
+/// A ponder search that finishes early waits for `ponderhit` or `stop` before
+/// printing, as the GUI doesn't expect a best move while pondering.
+fn run_search(
+    mut searcher: Searcher,
+    board: Board,
+    depth: u8,
+    time_limit: Option<TimeLimits>,
+    infinite: bool,
+) -> Searcher {
+    let (_, best_move) = searcher.find_best_move(&board, depth, time_limit);
+
+    // Hold the best move back until the GUI ends the ponder or infinite search
+    let signals = searcher.signals();
+    while (infinite || signals.is_pondering()) && !signals.is_stopped() {
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    println!(
+        "{}",
+        best_move_line(
+            best_move,
+            searcher.principal_variation(),
+            searcher.chess960()
+        )
+    );
+
+    searcher
+}
+
+/// Formats the root evaluation as an `info string` line, with the untapered
+/// opening and endgame scores and the phase they're blended by
+fn eval_info_line(breakdown: &EvalBreakdown) -> String {
+    let (opening, endgame) = breakdown.scores();
+    format!(
+        "info string eval opening {} endgame {} phase {}/{} total {}",
+        opening,
+        endgame,
+        breakdown.phase,
+        MAX_PHASE,
+        breakdown.total()
+    )
+}
+
+/// Formats the `bestmove` line, naming the expected reply from the principal
+/// variation as the move to ponder on if there is one
+fn best_move_line(best_move: Option<Move>, pv: &[Move], chess960: bool) -> String {
+    let Some(mv) = best_move else {
+        // No legal moves
+        return "bestmove 0000".to_string();
+    };
+
+    match pv {
+        [first, reply, ..] if *first == mv => {
+            format!(
+                "bestmove {} ponder {}",
+                mv.to_uci(chess960),
+                reply.to_uci(chess960)
+            )
+        }
+        _ => format!("bestmove {}", mv.to_uci(chess960)),
+    }
+}
+
 /// Main UCI protocol handler
 pub struct Flounder {
     board: Board,
-    searcher: Searcher,
+    /// Taken by the search thread while a background search runs
+    searcher: Option<Searcher>,
+    search_thread: Option<JoinHandle<Searcher>>,
+    signals: Arc<SearchSignals>,
+    zobrist: ZobristTable,
+    position_history: Vec<u64>,
+    /// Where the game given by `position` started, with its FEN unless it's the initial position
+    game_start: (Board, Option<String>),
+    /// Moves played since the game start
+    game_moves: Vec<Move>,
+    /// Whether to print diagnostics like the root evaluation before each search
+    debug: bool,
 }
 
 impl Flounder {
     pub fn new() -> Self {
+        let board = Board::default();
+        let zobrist = ZobristTable::new();
+        let position_history = vec![zobrist.hash(&board)];
+        let searcher = Searcher::new();
+
         Self {
-            board: Board::default(),
-            searcher: Searcher::new(),
+            board,
+            signals: searcher.signals(),
+            searcher: Some(searcher),
+            search_thread: None,
+            zobrist,
+            position_history,
+            game_start: (board, None),
+            game_moves: Vec::new(),
+            debug: false,
         }
     }
 
     /// Main UCI loop that reads and processes commands
     pub fn uci_loop(&mut self) {
+        self.process_input(std::io::stdin().lock());
+    }
+
+    /// Reads and processes commands until the input is closed
+    fn process_input<R: BufRead>(&mut self, mut input: R) {
         loop {
             let mut command = String::new();
-            if std::io::stdin().read_line(&mut command).is_ok() {
-                command = command.trim().to_string();
-                if !command.is_empty() {
-                    self.handle_command(&command);
+            match input.read_line(&mut command) {
+                // Reading zero bytes means the input reached EOF (e.g. the GUI disconnected)
+                Ok(0) => break,
+                // A bad line, e.g. one that isn't UTF-8, shouldn't end the session
+                Err(err) => println!("info string could not read command: {}", err),
+                Ok(_) => {
+                    let command = command.trim();
+                    if !command.is_empty() {
+                        self.handle_command(command);
+                    }
                 }
             }
         }
@@ -44,6 +264,14 @@ impl Flounder {
             "ucinewgame" => self.handle_ucinewgame_command(),
             "position" => self.handle_position_command(&parts),
             "go" => self.handle_go_command(&parts),
+            "setoption" => self.handle_setoption_command(&parts),
+            "stop" => self.finish_search(),
+            "ponderhit" => self.signals.ponderhit(),
+            "epd" => self.handle_epd_command(&parts),
+            "eval" => self.handle_eval_command(),
+            "bench" => self.handle_bench_command(&parts),
+            "perft" => self.handle_perft_command(&parts),
+            "d" => self.handle_display_command(&parts),
             "quit" => std::process::exit(0),
             _ => {
                 // Handle unknown command
@@ -55,6 +283,9 @@ impl Flounder {
     fn handle_uci_command(&self) {
         println!("id name Flounder");
         println!("id author Zachary Garwood");
+        for option in option_declarations() {
+            println!("{}", option);
+        }
         println!("uciok");
     }
 
@@ -63,10 +294,122 @@ impl Flounder {
         println!("readyok");
     }
 
+    /// Runs a test suite of the form `epd <file> [depth <depth>] [movetime <ms>]`,
+    /// reporting which positions the engine finds the best move in
+    fn handle_epd_command(&mut self, parts: &[&str]) {
+        let Some(path) = parts.get(1) else {
+            return;
+        };
+
+        let mut depth = MAX_SEARCH_DEPTH;
+        let mut time_limit = None;
+        for option in parts[2..].chunks(2) {
+            match option {
+                ["depth", value] => {
+                    depth = value
+                        .parse::<u8>()
+                        .map_or(depth, |d| d.min(MAX_SEARCH_DEPTH))
+                }
+                ["movetime", value] => {
+                    time_limit = value
+                        .parse()
+                        .ok()
+                        .map(|ms| TimeLimits::fixed(Duration::from_millis(ms)))
+                }
+                _ => {}
+            }
+        }
+
+        // Without any limit the search would never finish
+        if depth == MAX_SEARCH_DEPTH && time_limit.is_none() {
+            time_limit = Some(TimeLimits::fixed(DEFAULT_MOVE_TIME));
+        }
+
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                println!("info string could not read {}: {}", path, err);
+                return;
+            }
+        };
+
+        let mut entries: Vec<EpdEntry> = Vec::new();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            match parse_epd(line) {
+                Ok(entry) => entries.push(entry),
+                Err(err) => println!("info string skipping epd line '{}': {}", line, err),
+            }
+        }
+
+        run_suite(self.searcher(), &entries, depth, time_limit);
+        self.searcher().reset();
+    }
+
+    /// Searches the built-in benchmark positions, as `bench [depth]`, and prints the
+    /// total nodes and speed
+    fn handle_bench_command(&mut self, parts: &[&str]) {
+        let depth = parts
+            .get(1)
+            .and_then(|d| d.parse::<u8>().ok())
+            .map_or(DEFAULT_BENCH_DEPTH, |d| d.clamp(1, MAX_SEARCH_DEPTH));
+
+        let result = run_bench(self.searcher(), depth);
+        println!("{} nodes {} nps", result.nodes, result.nps());
+    }
+
+    /// Counts the moves from the current position, as `perft <depth> [detailed]`.
+    ///
+    /// Prints the count below each root move, or with `detailed` the totals broken
+    /// down by captures, en passants, castles, promotions and checks.
+    fn handle_perft_command(&mut self, parts: &[&str]) {
+        let depth = parts
+            .get(1)
+            .and_then(|d| d.parse::<usize>().ok())
+            .map_or(1, |d| d.max(1));
+        let move_gen = MoveGenerator::shared();
+
+        if parts.get(2) == Some(&"detailed") {
+            println!("{}", move_gen.perft_detailed(&self.board, depth));
+        } else {
+            move_gen.divide(&self.board, depth);
+        }
+    }
+
+    /// Prints the static evaluation of the current position term by term
+    fn handle_eval_command(&mut self) {
+        let board = self.board;
+        let evaluator = self.searcher().evaluator_mut();
+        let breakdown = evaluator.evaluate_breakdown(&board);
+        println!("{}", breakdown);
+
+        debug_assert!(
+            evaluator.is_symmetric(&board),
+            "{} evaluates differently from its mirror",
+            board.to_fen()
+        );
+    }
+
+    /// Displays details of the current game, `d pgn` prints it as PGN
+    fn handle_display_command(&mut self, parts: &[&str]) {
+        if parts.get(1) == Some(&"pgn") {
+            println!("{}", self.pgn());
+        }
+    }
+
+    /// Formats the game set up by `position` as PGN
+    pub fn pgn(&self) -> String {
+        let (start, start_fen) = &self.game_start;
+        to_pgn(start, start_fen.as_deref(), &self.game_moves)
+    }
+
     /// Prepares a new game
     fn handle_ucinewgame_command(&mut self) {
         self.board = Board::default();
-        self.searcher = Searcher::new();
+        self.searcher().reset();
+        self.position_history = vec![self.zobrist.hash(&self.board)];
+        self.game_start = (self.board, None);
+        self.game_moves.clear();
+        self.searcher().clear_game_positions();
     }
 
     /// Sets up the board position
@@ -76,66 +419,190 @@ impl Flounder {
         }
 
         let position_type = parts[1];
+        let moves_idx = parts.iter().position(|&x| x == "moves");
 
-        match position_type {
+        let start_fen = match position_type {
             "startpos" => {
                 self.board = Board::default();
+                None
+            }
+            "fen" => {
+                // The FEN is everything up to the moves, as its move counters are optional
+                let fen_end = moves_idx.unwrap_or(parts.len());
+                let fen = parts[2..fen_end].join(" ");
+                self.board = match Board::from_fen(&fen) {
+                    Ok(board) => board,
+                    Err(err) => {
+                        println!("info string invalid fen: {}", err);
+                        return;
+                    }
+                };
+                Some(fen)
+            }
+            _ => return,
+        };
+
+        self.game_start = (self.board, start_fen);
+        self.game_moves.clear();
+        self.position_history = vec![self.zobrist.hash(&self.board)];
+        self.searcher().clear_game_positions();
+
+        if let Some(moves_idx) = moves_idx {
+            self.make_moves(&parts[moves_idx + 1..]);
+        }
+
+        if self.is_threefold_repetition() {
+            println!("info string threefold repetition reached");
+        }
+    }
+
+    /// Why the current game position is a draw, or `None` if the game goes on
+    fn draw_reason(&self) -> Option<&'static str> {
+        match self.board.game_status(&self.position_history) {
+            GameStatus::Stalemate => Some("stalemate"),
+            GameStatus::DrawInsufficientMaterial => Some("insufficient material"),
+            GameStatus::DrawFiftyMove => Some("the fifty-move rule"),
+            GameStatus::DrawRepetition => Some("threefold repetition"),
+            GameStatus::Ongoing | GameStatus::Checkmate => None,
+        }
+    }
 
-                if let Some(moves_idx) = parts.iter().position(|&x| x == "moves") {
-                    self.make_moves(&parts[moves_idx + 1..]);
+    /// Checks if the current position has occurred at least three times in the game
+    fn is_threefold_repetition(&self) -> bool {
+        let current_hash = self.zobrist.hash(&self.board);
+        let occurrences = self
+            .position_history
+            .iter()
+            .filter(|&&hash| hash == current_hash)
+            .count();
+
+        occurrences >= 3
+    }
+
+    /// Applies an engine option of the form `setoption name <name> [value <value>]`
+    fn handle_setoption_command(&mut self, parts: &[&str]) {
+        let Some(name_idx) = parts.iter().position(|&x| x == "name") else {
+            return;
+        };
+        let value_idx = parts.iter().position(|&x| x == "value");
+
+        let name = parts[name_idx + 1..value_idx.unwrap_or(parts.len())].join(" ");
+        let value = value_idx.map(|idx| parts[idx + 1..].join(" "));
+
+        // Option names are case insensitive
+        match name.to_lowercase().as_str() {
+            "ponder" => {
+                // Pondering is controlled by the GUI through `go ponder`, nothing to configure
+            }
+            "clear hash" => self.searcher().clear_hash(),
+            "debug" => {
+                if let Some(enabled) = value.and_then(|v| v.parse::<bool>().ok()) {
+                    self.debug = enabled;
                 }
             }
-            "fen" => {
-                if parts.len() < 8 {
-                    return;
+            "uci_chess960" => {
+                if let Some(enabled) = value.and_then(|v| v.parse::<bool>().ok()) {
+                    self.searcher().set_chess960(enabled);
                 }
+            }
+            "qsearchchecks" => {
+                if let Some(enabled) = value.and_then(|v| v.parse::<bool>().ok()) {
+                    self.searcher().set_qsearch_checks(enabled);
+                }
+            }
+            "internaliterativedeepening" => {
+                if let Some(enabled) = value.and_then(|v| v.parse::<bool>().ok()) {
+                    self.searcher().set_internal_iterative_deepening(enabled);
+                }
+            }
+            "singularextensions" => {
+                if let Some(enabled) = value.and_then(|v| v.parse::<bool>().ok()) {
+                    self.searcher().set_singular_extensions(enabled);
+                }
+            }
+            "contempt" => {
+                if let Some(contempt) = value.and_then(|v| v.parse::<i32>().ok()) {
+                    self.searcher()
+                        .set_contempt(contempt.clamp(-MAX_CONTEMPT, MAX_CONTEMPT));
+                }
+            }
+            option => {
+                let Some(value) = value.and_then(|v| v.parse().ok()) else {
+                    return;
+                };
 
-                let fen = parts[2..8].join(" ");
-                self.board = Board::new(&fen);
-
-                if let Some(moves_idx) = parts.iter().position(|&x| x == "moves") {
-                    self.make_moves(&parts[moves_idx + 1..]);
+                if let Some((piece, is_endgame)) = piece_value_option(option) {
+                    self.set_piece_value(piece, is_endgame, value);
+                } else if let Some((weight, is_endgame)) = eval_weight_option(option) {
+                    self.set_eval_weight(weight, is_endgame, value);
                 }
+                // Ignore unknown options
             }
-            _ => {}
+        }
+    }
+
+    /// Updates either the opening or endgame value of an evaluation weight, keeping the other
+    fn set_eval_weight(&mut self, weight: EvalWeight, is_endgame: bool, value: i32) {
+        let evaluator = self.searcher().evaluator_mut();
+        let (opening, endgame) = evaluator.weight(weight);
+        let value = value.clamp(0, MAX_EVAL_WEIGHT);
+
+        if is_endgame {
+            evaluator.set_weight(weight, opening, value);
+        } else {
+            evaluator.set_weight(weight, value, endgame);
+        }
+    }
+
+    /// Updates either the opening or endgame value of a piece, keeping the other
+    fn set_piece_value(&mut self, piece: Piece, is_endgame: bool, value: i32) {
+        let evaluator = self.searcher().evaluator_mut();
+        let (opening, endgame) = evaluator.piece_value(piece);
+        let value = value.clamp(0, MAX_PIECE_VALUE);
+
+        if is_endgame {
+            evaluator.set_piece_value(piece, opening, value);
+        } else {
+            evaluator.set_piece_value(piece, value, endgame);
         }
     }
 
     /// Starts the search with time controls
     fn handle_go_command(&mut self, parts: &[&str]) {
-        let mut depth = 64; // High depth will get cut off by timer
+        let mut depth = MAX_SEARCH_DEPTH; // High depth will get cut off by timer
         let mut time_limit = None;
+        let mut ponder = false;
+        let mut infinite = false;
 
         let mut i = 1;
         while i < parts.len() {
             match parts[i] {
-                "depth" => {
-                    if i + 1 < parts.len() {
-                        if let Ok(d) = parts[i + 1].parse::<u8>() {
-                            depth = d.min(64);
-                        }
-                        i += 2;
-                    } else {
-                        i += 1;
+                "depth" if i + 1 < parts.len() => {
+                    if let Ok(d) = parts[i + 1].parse::<u8>() {
+                        depth = d.min(MAX_SEARCH_DEPTH);
                     }
+                    i += 2;
                 }
-                "movetime" => {
-                    if i + 1 < parts.len() {
-                        if let Ok(ms) = parts[i + 1].parse::<u64>() {
-                            time_limit = Some(Duration::from_millis(ms));
-                        }
-                        i += 2;
-                    } else {
-                        i += 1;
+                "movetime" if i + 1 < parts.len() => {
+                    if let Ok(ms) = parts[i + 1].parse::<u64>() {
+                        time_limit = Some(TimeLimits::fixed(Duration::from_millis(ms)));
                     }
+                    i += 2;
                 }
-                "wtime" | "btime" | "winc" | "binc" => {
-                    time_limit = self.calculate_move_time(parts, i);
-                    i += 8;
+                "wtime" | "btime" | "winc" | "binc" | "movestogo" => {
+                    if time_limit.is_none() {
+                        time_limit = self.calculate_move_time(parts);
+                    }
+                    i += 2;
                 }
                 "infinite" => {
-                    depth = 64;
+                    depth = MAX_SEARCH_DEPTH;
                     time_limit = None;
+                    infinite = true;
+                    i += 1;
+                }
+                "ponder" => {
+                    ponder = true;
                     i += 1;
                 }
                 _ => {
@@ -144,26 +611,98 @@ impl Flounder {
             }
         }
 
-        let (_, best_move) = self.searcher.find_best_move(&self.board, depth, time_limit);
+        // The search stops at whichever of the depth and time limits it reaches first,
+        // so without either it would never finish
+        if !infinite && depth == MAX_SEARCH_DEPTH && time_limit.is_none() {
+            time_limit = Some(TimeLimits::fixed(DEFAULT_MOVE_TIME));
+        }
 
-        if let Some(mv) = best_move {
-            println!("bestmove {}", mv.to_algebraic());
-        } else {
-            // No legal moves
+        if let Some(reason) = self.draw_reason() {
+            println!("info string position is drawn by {}", reason);
+        }
+
+        if self.debug {
+            let board = self.board;
+            let breakdown = self.searcher().evaluator_mut().evaluate_breakdown(&board);
+            println!("{}", eval_info_line(&breakdown));
+        }
+
+        // There is nothing to search in checkmate or stalemate
+        if MoveGenerator::shared()
+            .generate_moves(&self.board)
+            .is_empty()
+        {
             println!("bestmove 0000");
+            return;
+        }
+
+        // Every move keeps a dead draw drawn, so any legal move will do
+        if self.board.is_insufficient_material() {
+            depth = 1;
         }
+
+        let searcher = self.take_searcher();
+        let board = self.board;
+
+        self.signals = searcher.signals();
+        self.signals.reset(ponder);
+
+        // Ponder and infinite searches keep running until `ponderhit` or `stop`, so they
+        // run in the background to keep reading commands
+        if ponder || infinite {
+            let handle = thread::Builder::new()
+                .name("search".to_string())
+                .stack_size(SEARCH_THREAD_STACK_SIZE)
+                .spawn(move || run_search(searcher, board, depth, time_limit, infinite))
+                .expect("failed to spawn search thread");
+            self.search_thread = Some(handle);
+        } else {
+            self.searcher = Some(run_search(searcher, board, depth, time_limit, false));
+        }
+    }
+
+    /// Stops a running background search and waits for it to print its best move
+    fn finish_search(&mut self) {
+        if let Some(handle) = self.search_thread.take() {
+            self.signals.stop();
+            self.searcher = Some(handle.join().expect("search thread panicked"));
+        }
+    }
+
+    /// Gets the searcher, stopping any background search that is using it
+    fn searcher(&mut self) -> &mut Searcher {
+        self.finish_search();
+        self.searcher
+            .as_mut()
+            .expect("searcher is only missing while a search thread runs")
+    }
+
+    /// Takes the searcher to run a search with, stopping any background search
+    fn take_searcher(&mut self) -> Searcher {
+        self.finish_search();
+        self.searcher
+            .take()
+            .expect("searcher is only missing while a search thread runs")
     }
 
     /// Calculates how much time to use for this move
-    fn calculate_move_time(&self, parts: &[&str], start_idx: usize) -> Option<Duration> {
+    ///
+    /// With `movestogo` the remaining time is split across the moves left until the
+    /// next time control. Otherwise the number of moves left is estimated from the
+    /// game phase, expecting fewer moves once material comes off the board.
+    ///
+    /// The allocation is used as the soft limit, and the hard limit allows the
+    /// current iteration to run over it by a bounded amount.
+    fn calculate_move_time(&self, parts: &[&str]) -> Option<TimeLimits> {
         let color = self.board.active_color();
 
         let mut wtime = 0u64;
         let mut btime = 0u64;
         let mut winc = 0u64;
         let mut binc = 0u64;
+        let mut moves_to_go = None;
 
-        let mut i = start_idx;
+        let mut i = 1;
         while i < parts.len() {
             match parts[i] {
                 "wtime" => {
@@ -190,6 +729,12 @@ impl Flounder {
                     }
                     i += 2;
                 }
+                "movestogo" => {
+                    if i + 1 < parts.len() {
+                        moves_to_go = parts[i + 1].parse::<u64>().ok();
+                    }
+                    i += 2;
+                }
                 _ => {
                     i += 1;
                 }
@@ -201,20 +746,50 @@ impl Flounder {
             Color::Black => (btime, binc),
         };
 
-        let reserve = 5_000; // Try to always keep 5 seconds
-        let available = time_left.saturating_sub(reserve);
-        let base_time = available / 25;
+        // Try to always keep 5 seconds, but a short clock can't spare that much
+        let reserve = MOVE_TIME_RESERVE_MS.min(time_left / 10);
+        let available = time_left - reserve;
+
+        let base_time = match moves_to_go {
+            // Keep a buffer of 2 moves so the last move before the time control isn't starved
+            Some(moves) => available / (moves + 2),
+            None => {
+                // Expect ~30 more moves in the opening down to ~15 in the endgame
+                let phase = game_phase(&self.board) as u64;
+                available / (15 + phase * 15 / MAX_PHASE as u64)
+            }
+        };
         let allocated = base_time + increment;
 
-        Some(Duration::from_millis(allocated))
+        // Never spend more than a third of the remaining clock on a single move
+        let max_allocation = time_left / 3;
+
+        let soft = allocated.min(max_allocation).max(MIN_MOVE_TIME_MS);
+        let hard = (allocated * 3).min(max_allocation).max(soft);
+
+        Some(TimeLimits::new(
+            Duration::from_millis(soft),
+            Duration::from_millis(hard),
+        ))
     }
 
     fn make_moves(&mut self, move_strs: &[&str]) {
-        let move_gen = MoveGenerator::new();
+        // Building a generator fills every lookup table, so share one across commands
+        let move_gen = MoveGenerator::shared();
         for mv_str in move_strs.iter() {
-            let moves = move_gen.generate_moves(&self.board);
-            let mv = moves.iter().find(|m| m.to_algebraic() == *mv_str);
-            self.board.make_move(mv.unwrap());
+            let board = self.board;
+            let mv = match self.board.try_make_uci(mv_str, move_gen) {
+                Ok(mv) => mv,
+                Err(err) => {
+                    // Later moves were played from this one, so none of them can be applied
+                    println!("info string {}", err);
+                    break;
+                }
+            };
+
+            self.searcher().push_game_position(&board);
+            self.game_moves.push(mv);
+            self.position_history.push(self.zobrist.hash(&self.board));
         }
     }
 }
@@ -228,6 +803,8 @@ impl Default for Flounder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::moves::MoveType;
+    use std::time::Instant;
 
     #[test]
     fn test_uci_initialization() {
@@ -243,6 +820,617 @@ mod tests {
         // No panics
     }
 
+    #[test]
+    fn test_threefold_repetition_from_game_history() {
+        let mut flounder = Flounder::new();
+
+        flounder.handle_command("position startpos moves g1f3 g8f6 f3g1 f6g8");
+        assert!(!flounder.is_threefold_repetition());
+
+        flounder.handle_command("position startpos moves g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8");
+        assert!(flounder.is_threefold_repetition());
+
+        // A new position command starts a fresh history
+        flounder.handle_command("position startpos moves e2e4");
+        assert!(!flounder.is_threefold_repetition());
+    }
+
+    #[test]
+    fn test_position_moves_seed_search_repetitions() {
+        let mut flounder = Flounder::new();
+
+        // Black is a queen down, but Kg8 repeats a position for the third time
+        flounder.handle_command(
+            "position fen 7k/8/8/8/8/8/8/KQ6 b - - 0 1 moves h8g8 a1a2 g8h8 a2a1 h8g8 a1a2 g8h8 a2a1",
+        );
+        let board = flounder.board;
+        let (score, best_move) = flounder.searcher().find_best_move(&board, 3, None);
+
+        assert_eq!(best_move.unwrap().to_algebraic(), "h8g8");
+        assert_eq!(score, 0);
+
+        // The same position without the moves that led to it is just lost
+        flounder.handle_command("ucinewgame");
+        flounder.handle_command("position fen 7k/8/8/8/8/8/8/KQ6 b - - 8 5");
+        assert_eq!(flounder.board.to_fen(), board.to_fen());
+
+        let (score, _) = flounder.searcher().find_best_move(&board, 3, None);
+        assert!(score < -500);
+    }
+
+    #[test]
+    fn test_position_fen_without_move_counters() {
+        let mut flounder = Flounder::new();
+
+        flounder.handle_command("position fen 4k3/8/8/8/8/8/4P3/4K3 b - -");
+        assert_eq!(flounder.board.active_color(), Color::Black);
+        assert_eq!(flounder.board.fullmove_counter, 1);
+
+        // The moves keyword right after a short FEN isn't taken as a counter
+        flounder.handle_command("position fen 4k3/8/8/8/8/8/4P3/4K3 w - - moves e2e4");
+        assert_eq!(flounder.board.active_color(), Color::Black);
+        assert_eq!(flounder.board.en_passant_target, Some(20));
+    }
+
+    #[test]
+    fn test_position_fen_followed_by_moves() {
+        let mut flounder = Flounder::new();
+
+        flounder.handle_command("position fen 4k3/8/8/8/8/8/4P3/4K3 w - - 3 40 moves e2e4 e8d7");
+        assert_eq!(flounder.board.active_color(), Color::White);
+        assert_eq!(flounder.board.halfmove_clock, 1);
+        assert_eq!(flounder.board.fullmove_counter, 41);
+    }
+
+    #[test]
+    fn test_position_fen_tolerates_spacing_and_empty_moves() {
+        let mut flounder = Flounder::new();
+
+        flounder.handle_command("position   fen  4k3/8/8/8/8/8/4P3/4K3   b - -  7 12 ");
+        assert_eq!(flounder.board.active_color(), Color::Black);
+        assert_eq!(flounder.board.halfmove_clock, 7);
+        assert_eq!(flounder.board.fullmove_counter, 12);
+
+        // A moves keyword with nothing after it leaves the position as given
+        flounder.handle_command("position fen 4k3/8/8/8/8/8/4P3/4K3 w - - moves");
+        assert_eq!(flounder.board.active_color(), Color::White);
+        assert!(flounder.game_moves.is_empty());
+
+        // A missing FEN is rejected rather than panicking
+        flounder.handle_command("position fen moves e2e4");
+        flounder.handle_command("position fen");
+        assert_eq!(flounder.board.active_color(), Color::White);
+    }
+
+    #[test]
+    fn test_position_stops_at_illegal_move() {
+        let mut flounder = Flounder::new();
+
+        // The king can't move two squares forward, so d2d4 is never played
+        flounder.handle_command("position startpos moves e2e4 e7e5 e1e3 d2d4");
+        assert_eq!(flounder.game_moves.len(), 2);
+        assert_eq!(flounder.board.active_color(), Color::White);
+
+        flounder.handle_command("position startpos moves e2e4 nonsense");
+        assert_eq!(flounder.game_moves.len(), 1);
+    }
+
+    #[test]
+    fn test_go_in_stalemate_skips_search() {
+        let mut flounder = Flounder::new();
+        flounder.handle_command("position fen k7/8/1QK5/8/8/8/8/8 b - - 0 1");
+
+        assert_eq!(flounder.draw_reason(), Some("stalemate"));
+
+        let start = Instant::now();
+        flounder.handle_command("go infinite");
+        assert!(start.elapsed() < Duration::from_millis(100));
+        assert!(flounder.searcher.is_some());
+    }
+
+    #[test]
+    fn test_go_with_insufficient_material_plays_quickly() {
+        let mut flounder = Flounder::new();
+        flounder.handle_command("position fen 8/8/4k3/8/8/3KN3/8/8 w - - 0 1");
+
+        assert_eq!(flounder.draw_reason(), Some("insufficient material"));
+
+        let start = Instant::now();
+        flounder.handle_command("go infinite");
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        flounder.handle_command("position fen 8/8/4k3/8/8/3KR3/8/8 w - - 100 80");
+        assert_eq!(flounder.draw_reason(), Some("the fifty-move rule"));
+
+        flounder.handle_command("position startpos moves g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8");
+        assert_eq!(flounder.draw_reason(), Some("threefold repetition"));
+
+        flounder.handle_command("position startpos");
+        assert_eq!(flounder.draw_reason(), None);
+    }
+
+    #[test]
+    fn test_clear_hash_keeps_position() {
+        let mut flounder = Flounder::new();
+        flounder.handle_command("position startpos moves e2e4");
+        flounder.handle_command("go depth 3");
+
+        flounder.handle_command("setoption name Clear Hash");
+
+        assert_eq!(flounder.board.active_color(), Color::Black);
+        assert!(flounder.board.en_passant_target.is_some());
+    }
+
+    #[test]
+    fn test_replaying_long_game_reuses_move_generator() {
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        let moves: Vec<&str> = shuffle.iter().cycle().take(80).copied().collect();
+        let command = format!("position startpos moves {}", moves.join(" "));
+        let mut flounder = Flounder::new();
+        flounder.handle_command(&command);
+
+        let generator = MoveGenerator::shared();
+
+        // Replaying all 40 moves uses the generator that is already built
+        flounder.handle_command(&command);
+        assert!(std::ptr::eq(generator, MoveGenerator::shared()));
+        assert_eq!(flounder.game_moves.len(), 80);
+        assert_eq!(
+            flounder.zobrist.hash(&flounder.board),
+            flounder.zobrist.hash(&Board::default())
+        );
+    }
+
+    #[test]
+    fn test_pgn_records_position_moves() {
+        let mut flounder = Flounder::new();
+        flounder.handle_command("position startpos moves e2e4 e7e5 g1f3");
+
+        assert!(flounder.pgn().ends_with("\n\n1. e4 e5 2. Nf3 *\n"));
+
+        // A new position replaces the recorded game
+        flounder.handle_command("position fen 7k/6pp/8/8/8/8/8/R6K w - - 0 1 moves a1a8");
+        let pgn = flounder.pgn();
+        assert!(pgn.contains("[FEN \"7k/6pp/8/8/8/8/8/R6K w - - 0 1\"]"));
+        assert!(pgn.ends_with("\n\n1. Ra8# 1-0\n"));
+    }
+
+    #[test]
+    fn test_option_declarations() {
+        let options = option_declarations();
+
+        assert!(options.contains(&"option name Ponder type check default false".to_string()));
+        assert!(options.contains(&"option name Clear Hash type button".to_string()));
+        assert!(options.contains(&"option name QSearchChecks type check default true".to_string()));
+        assert!(
+            options.contains(&"option name SingularExtensions type check default true".to_string())
+        );
+        assert!(options
+            .contains(&"option name Contempt type spin default 0 min -1000 max 1000".to_string()));
+        assert!(options.contains(
+            &"option name QueenValueEndgame type spin default 936 min 0 max 5000".to_string()
+        ));
+
+        // Every declared spin option is accepted back by setoption
+        let mut flounder = Flounder::new();
+        for option in options.iter().filter(|option| option.contains("Value")) {
+            let name = option.split_whitespace().nth(2).unwrap();
+            flounder.handle_command(&format!("setoption name {} value 123", name));
+        }
+        assert_eq!(
+            flounder
+                .searcher()
+                .evaluator_mut()
+                .piece_value(Piece::Bishop),
+            (123, 123)
+        );
+    }
+
+    #[test]
+    fn test_epd_command_runs_suite_file() {
+        let path = std::env::temp_dir().join("flounder_test_suite.epd");
+        std::fs::write(
+            &path,
+            "4k3/5p2/8/6B1/8/8/8/3R2K1 w - - bm Rd8#; id \"back rank\";\nnot an epd line\n",
+        )
+        .unwrap();
+
+        let mut flounder = Flounder::new();
+        flounder.handle_command("position startpos moves e2e4");
+        flounder.handle_command(&format!("epd {} depth 3", path.display()));
+
+        // The suite doesn't change the current position
+        assert_eq!(flounder.board.active_color(), Color::Black);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_fen_keeps_previous_position() {
+        let mut flounder = Flounder::new();
+        flounder.handle_command("position startpos moves e2e4");
+        flounder.handle_command(
+            "position fen rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1",
+        );
+
+        // No panics and the last valid position is kept
+        assert_eq!(flounder.board.active_color(), Color::Black);
+    }
+
+    #[test]
+    fn test_position_keeps_and_updates_move_counters() {
+        let mut flounder = Flounder::new();
+        let fen = "r3k2r/pppq1ppp/2n2n2/3pp3/3PP3/2N2N2/PPPQ1PPP/R3K2R w KQkq - 7 12";
+
+        flounder.handle_command(&format!("position fen {}", fen));
+        assert_eq!(flounder.board.halfmove_clock, 7);
+        assert_eq!(flounder.board.fullmove_counter, 12);
+
+        // Quiet piece moves count up, and the number goes up after Black moves
+        flounder.handle_command(&format!("position fen {} moves e1g1 e8c8", fen));
+        assert_eq!(flounder.board.halfmove_clock, 9);
+        assert_eq!(flounder.board.fullmove_counter, 13);
+
+        // Captures and pawn moves reset the clock
+        flounder.handle_command(&format!("position fen {} moves d4e5 h7h6", fen));
+        assert_eq!(flounder.board.halfmove_clock, 0);
+        assert_eq!(flounder.board.fullmove_counter, 13);
+
+        flounder.handle_command(&format!("position fen {} moves d4e5 c6e5", fen));
+        assert_eq!(flounder.board.halfmove_clock, 0);
+    }
+
+    #[test]
+    fn test_input_eof_ends_loop() {
+        let mut flounder = Flounder::new();
+        let input = std::io::Cursor::new("position startpos moves e2e4\nisready\n");
+
+        // Returns instead of spinning forever once the input is exhausted
+        flounder.process_input(input);
+        assert_eq!(flounder.board.active_color(), Color::Black);
+    }
+
+    #[test]
+    fn test_unreadable_line_is_skipped() {
+        let mut flounder = Flounder::new();
+        let input = std::io::Cursor::new(b"position \xff\xfe\nposition startpos moves e2e4\n");
+
+        // The line that isn't UTF-8 is skipped and the commands after it still run
+        flounder.process_input(input);
+        assert_eq!(flounder.board.active_color(), Color::Black);
+    }
+
+    fn move_time(flounder: &Flounder, command: &str) -> u128 {
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        flounder
+            .calculate_move_time(&parts)
+            .unwrap()
+            .soft
+            .as_millis()
+    }
+
+    #[test]
+    fn test_hard_limit_allows_overrun_within_clock() {
+        let flounder = Flounder::new();
+        let parts = ["go", "wtime", "60000", "btime", "60000"];
+        let limits = flounder.calculate_move_time(&parts).unwrap();
+
+        assert_eq!(limits.hard, limits.soft * 3);
+
+        let parts = ["go", "wtime", "900", "btime", "900", "winc", "5000"];
+        let limits = flounder.calculate_move_time(&parts).unwrap();
+
+        assert_eq!(limits.hard, Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_move_time_sudden_death() {
+        let flounder = Flounder::new();
+
+        // 55 seconds available split over ~30 expected moves in the opening
+        assert_eq!(
+            move_time(&flounder, "go wtime 60000 btime 60000"),
+            55_000 / 30
+        );
+    }
+
+    #[test]
+    fn test_move_time_sudden_death_below_reserve() {
+        let flounder = Flounder::new();
+
+        // A tenth of a short clock is kept back instead of the full reserve
+        assert_eq!(move_time(&flounder, "go wtime 3000 btime 3000"), 2_700 / 30);
+
+        // However little is left, there is always some time to find a move
+        assert_eq!(
+            move_time(&flounder, "go wtime 20 btime 20"),
+            MIN_MOVE_TIME_MS as u128
+        );
+    }
+
+    #[test]
+    fn test_move_time_with_increment() {
+        let flounder = Flounder::new();
+
+        let without = move_time(&flounder, "go wtime 60000 btime 60000");
+        let with = move_time(&flounder, "go wtime 60000 btime 60000 winc 2000 binc 2000");
+
+        assert_eq!(with, without + 2000);
+    }
+
+    #[test]
+    fn test_move_time_uses_side_to_move_clock() {
+        let mut flounder = Flounder::new();
+        flounder.handle_command("position startpos moves e2e4");
+
+        assert_eq!(
+            move_time(&flounder, "go wtime 1000 btime 60000"),
+            55_000 / 30
+        );
+    }
+
+    #[test]
+    fn test_move_time_with_movestogo() {
+        let flounder = Flounder::new();
+
+        assert_eq!(
+            move_time(&flounder, "go wtime 60000 btime 60000 movestogo 10"),
+            55_000 / 12
+        );
+        assert_eq!(
+            move_time(&flounder, "go movestogo 1 wtime 60000 btime 60000"),
+            55_000 / 3
+        );
+    }
+
+    #[test]
+    fn test_move_time_in_endgame_is_larger() {
+        let mut flounder = Flounder::new();
+        let opening = move_time(&flounder, "go wtime 60000 btime 60000");
+
+        flounder.handle_command("position fen 8/5k2/8/8/8/8/3K4/8 w - - 0 1");
+        let endgame = move_time(&flounder, "go wtime 60000 btime 60000");
+
+        assert_eq!(endgame, 55_000 / 15);
+        assert!(endgame > opening);
+    }
+
+    #[test]
+    fn test_move_time_capped_by_remaining_clock() {
+        let flounder = Flounder::new();
+
+        // The increment alone would exceed the clock, so a third of the clock is used
+        assert_eq!(
+            move_time(&flounder, "go wtime 900 btime 900 winc 5000 binc 5000"),
+            300
+        );
+    }
+
+    #[test]
+    fn test_setoption_contempt() {
+        let mut flounder = Flounder::new();
+
+        // Stalemating with Kf7 is only attractive without contempt
+        flounder.handle_command("setoption name Contempt value 500");
+        flounder.handle_command("position fen 5N1k/p1p5/p1p2K2/p1p5/p1p5/p1p5/P1P5/8 w - - 0 1");
+
+        let board = flounder.board;
+        let (_, best_move) = flounder.searcher().find_best_move(&board, 4, None);
+        assert_ne!(best_move.unwrap().to_algebraic(), "f6f7");
+    }
+
+    #[test]
+    fn test_setoption_piece_values() {
+        let mut flounder = Flounder::new();
+
+        flounder.handle_command("setoption name QueenValue value 900");
+        flounder.handle_command("setoption name KnightValueEndgame value 300");
+        flounder.handle_command("setoption name KingValue value 100");
+
+        let evaluator = flounder.searcher().evaluator_mut();
+        assert_eq!(evaluator.piece_value(Piece::Queen), (900, 936));
+        assert_eq!(evaluator.piece_value(Piece::Knight), (337, 300));
+        assert_eq!(evaluator.piece_value(Piece::King), (0, 0));
+    }
+
+    #[test]
+    fn test_setoption_debug() {
+        let mut flounder = Flounder::new();
+        assert!(option_declarations()
+            .contains(&"option name Debug type check default false".to_string()));
+        assert!(!flounder.debug);
+
+        flounder.handle_command("setoption name Debug value true");
+        assert!(flounder.debug);
+
+        // A queen up against a lone king in the endgame
+        let board = Board::new("8/8/8/3k4/8/8/8/Q6K w - - 0 1");
+        let breakdown = flounder
+            .searcher()
+            .evaluator_mut()
+            .evaluate_breakdown(&board);
+        let line = eval_info_line(&breakdown);
+        assert!(line.starts_with("info string eval opening "));
+        assert!(line.contains(" phase 4/24 "));
+        assert!(line.ends_with(&format!(" total {}", breakdown.total())));
+
+        flounder.handle_command("setoption name Debug value false");
+        assert!(!flounder.debug);
+    }
+
+    #[test]
+    fn test_chess960_castling() {
+        let mut flounder = Flounder::new();
+        assert!(option_declarations()
+            .contains(&"option name UCI_Chess960 type check default false".to_string()));
+
+        flounder.handle_command("setoption name UCI_Chess960 value true");
+        assert!(flounder.searcher().chess960());
+
+        // The king already stands on g1, so castling only moves the rook
+        flounder.handle_command("position fen 4k3/8/8/8/8/8/8/R5KR w HA - 0 1 moves g1h1");
+        assert_eq!(flounder.board.to_fen(), "4k3/8/8/8/8/8/8/R4RK1 b - - 1 1");
+
+        let board = Board::new("4k3/8/8/8/8/8/8/R5KR w HA - 0 1");
+        let castle = Move::from_algebraic("g1h1", &board).unwrap();
+        assert_eq!(castle.move_type, MoveType::Castle);
+        assert_eq!(best_move_line(Some(castle), &[], true), "bestmove g1h1");
+    }
+
+    #[test]
+    fn test_setoption_eval_weights() {
+        let mut flounder = Flounder::new();
+        let options = option_declarations();
+        assert!(options
+            .contains(&"option name KingSafety type spin default 100 min 0 max 1000".to_string()));
+        assert!(options.contains(
+            &"option name KnightOutpostEndgame type spin default 15 min 0 max 1000".to_string()
+        ));
+
+        flounder.handle_command("setoption name KingSafety value 150");
+        flounder.handle_command("setoption name PhalanxEndgame value 20");
+        flounder.handle_command("setoption name RookOnSeventh value -5");
+
+        let evaluator = flounder.searcher().evaluator_mut();
+        assert_eq!(evaluator.weight(EvalWeight::KingSafety), (150, 0));
+        assert_eq!(evaluator.weight(EvalWeight::Phalanx), (8, 20));
+        assert_eq!(evaluator.weight(EvalWeight::RookOnSeventh), (0, 20));
+    }
+
+    /// Waits up to `timeout` for the background search to finish
+    fn wait_for_search_thread(flounder: &Flounder, timeout: Duration) -> bool {
+        let start = std::time::Instant::now();
+        while start.elapsed() < timeout {
+            if flounder.search_thread.as_ref().unwrap().is_finished() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        false
+    }
+
+    #[test]
+    fn test_ponder_ignores_time_until_ponderhit() {
+        let mut flounder = Flounder::new();
+        flounder.handle_command("position startpos moves e2e4");
+        flounder.handle_command("go ponder movetime 50");
+
+        // Still searching well past the move time
+        assert!(!wait_for_search_thread(
+            &flounder,
+            Duration::from_millis(200)
+        ));
+
+        // The move time starts counting on ponderhit
+        flounder.handle_command("ponderhit");
+        assert!(wait_for_search_thread(&flounder, Duration::from_secs(2)));
+
+        flounder.handle_command("stop");
+        assert!(flounder.search_thread.is_none());
+        assert!(flounder.searcher.is_some());
+    }
+
+    #[test]
+    fn test_infinite_search_runs_until_stop() {
+        let mut flounder = Flounder::new();
+        flounder.handle_command("position startpos");
+        flounder.handle_command("go infinite");
+
+        assert!(!wait_for_search_thread(
+            &flounder,
+            Duration::from_millis(300)
+        ));
+
+        // Deepened past the first few iterations before being stopped
+        flounder.handle_command("stop");
+        assert!(flounder.search_thread.is_none());
+        assert!(flounder.searcher().completed_depth() >= 4);
+
+        // A single legal move is searched at once, but the best move waits for `stop`
+        flounder.handle_command("position fen 7k/8/8/8/8/8/7P/r6K w - - 0 1");
+        flounder.handle_command("go infinite");
+        assert!(!wait_for_search_thread(
+            &flounder,
+            Duration::from_millis(100)
+        ));
+
+        flounder.handle_command("stop");
+        assert_eq!(flounder.searcher().completed_depth(), 1);
+    }
+
+    #[test]
+    fn test_stop_ends_ponder_search() {
+        let mut flounder = Flounder::new();
+        flounder.handle_command("position startpos moves e2e4");
+        flounder.handle_command("go ponder");
+
+        assert!(!wait_for_search_thread(
+            &flounder,
+            Duration::from_millis(50)
+        ));
+
+        flounder.handle_command("stop");
+        assert!(flounder.search_thread.is_none());
+        assert!(flounder.searcher.is_some());
+
+        // The searcher is usable again for a normal search
+        flounder.handle_command("go depth 2");
+        assert!(flounder.searcher.is_some());
+    }
+
+    #[test]
+    fn test_ponder_waits_for_ponderhit_after_max_depth() {
+        let mut flounder = Flounder::new();
+        flounder.handle_command("position startpos");
+        flounder.handle_command("go ponder depth 1");
+
+        // The search is done almost instantly but can't report its move yet
+        assert!(!wait_for_search_thread(
+            &flounder,
+            Duration::from_millis(100)
+        ));
+
+        flounder.handle_command("ponderhit");
+        assert!(wait_for_search_thread(&flounder, Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_bestmove_names_ponder_reply() {
+        let mut flounder = Flounder::new();
+
+        // Rxa8+ leaves Kh7 as Black's only legal reply
+        flounder.handle_command("position fen r5k1/5pp1/8/8/8/8/5PPP/R5K1 w - - 0 1");
+        let board = flounder.board;
+        let searcher = flounder.searcher();
+        let (_, best_move) = searcher.find_best_move(&board, 4, None);
+
+        let best_move = best_move.unwrap();
+        let replies = MoveGenerator::new().generate_moves(&board.clone_with_move(&best_move));
+        assert_eq!(replies.len(), 1);
+
+        let line = best_move_line(Some(best_move), searcher.principal_variation(), false);
+        let expected = format!(
+            "bestmove {} ponder {}",
+            best_move.to_algebraic(),
+            replies[0].to_algebraic()
+        );
+        assert_eq!(line, expected);
+    }
+
+    #[test]
+    fn test_bestmove_without_ponder_reply() {
+        let mut flounder = Flounder::new();
+
+        // Mate in one leaves no reply to ponder on
+        flounder.handle_command("position fen 4k3/5p2/8/6B1/8/8/8/3R2K1 w - - 0 1");
+        let board = flounder.board;
+        let searcher = flounder.searcher();
+        let (_, best_move) = searcher.find_best_move(&board, 4, None);
+
+        let line = best_move_line(best_move, searcher.principal_variation(), false);
+        assert_eq!(line, "bestmove d1d8");
+        assert_eq!(best_move_line(None, &[], false), "bestmove 0000");
+    }
+
     #[test]
     fn test_go_command() {
         let mut flounder = Flounder::new();
@@ -250,4 +1438,34 @@ mod tests {
         flounder.handle_command("go depth 1");
         // No panics
     }
+
+    #[test]
+    fn test_go_depth_and_movetime_stop_at_first_limit() {
+        let mut flounder = Flounder::new();
+        flounder.handle_command("position startpos");
+
+        // The depth is reached long before the time runs out
+        let start = Instant::now();
+        flounder.handle_command("go depth 2 movetime 10000");
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(flounder.searcher().completed_depth(), 2);
+
+        // The time runs out long before the depth is reached
+        let start = Instant::now();
+        flounder.handle_command("go movetime 50 depth 30");
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(flounder.searcher().completed_depth() < 30);
+    }
+
+    #[test]
+    fn test_go_without_limits_uses_default_time() {
+        let mut flounder = Flounder::new();
+        flounder.handle_command("position startpos");
+
+        let start = Instant::now();
+        flounder.handle_command("go");
+        assert!(start.elapsed() < DEFAULT_MOVE_TIME + Duration::from_secs(1));
+        assert!(flounder.search_thread.is_none());
+        assert!(flounder.searcher().completed_depth() >= 1);
+    }
 }
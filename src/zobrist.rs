@@ -1,11 +1,21 @@
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::OnceLock;
 
-use crate::bitboard::{BitboardIterator, SQUARES};
+use crate::bitboard::{Bitboard, BitboardIterator, BitboardOperations, SQUARES};
 use crate::board::Board;
-use crate::pieces::{Color, ColorIterator, PieceIterator, COLOR_COUNT, PIECE_COUNT};
+use crate::moves::{EAST, NORTH, SOUTH, WEST};
+use crate::pieces::{Color, ColorIterator, Piece, PieceIterator, COLOR_COUNT, PIECE_COUNT};
+use crate::square::Square;
 
 const CASTLE_RIGHTS_COUNT: usize = 2; // King side and Queen side
 
+/// Seed for the keys, fixed so hashes are the same on every run
+pub const DEFAULT_SEED: u64 = 0x466c_6f75_6e64_6572; // "Flounder" in ASCII
+
+/// Table shared by callers that don't keep their own, built on first use
+static SHARED_ZOBRIST_TABLE: OnceLock<ZobristTable> = OnceLock::new();
+
 pub struct ZobristTable {
     table_keys: [[[u64; SQUARES as usize]; PIECE_COUNT]; COLOR_COUNT],
     white_to_move_key: u64,
@@ -14,8 +24,20 @@ pub struct ZobristTable {
 }
 
 impl ZobristTable {
+    /// Creates the table from the default seed
     pub fn new() -> Self {
-        let mut rng = rand::thread_rng();
+        Self::with_seed(DEFAULT_SEED)
+    }
+
+    /// Gets a table with the default seed shared across the program
+    pub fn shared() -> &'static Self {
+        SHARED_ZOBRIST_TABLE.get_or_init(Self::new)
+    }
+
+    /// Creates a table with keys generated from the given seed. The same seed
+    /// always produces the same keys.
+    pub fn with_seed(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
         let mut table_keys = [[[0; SQUARES as usize]; PIECE_COUNT]; COLOR_COUNT];
         let mut castling_right_keys = [[0; CASTLE_RIGHTS_COUNT]; COLOR_COUNT];
         let mut en_passant_target_key = [0; SQUARES as usize];
@@ -77,9 +99,12 @@ impl ZobristTable {
             }
         }
 
-        // Hash en passant target
+        // Hash en passant target, only if it can actually be captured so that
+        // positions differing by an unusable target share a hash
         if let Some(square) = board.en_passant_target {
-            hash ^= self.en_passant_target_key[square as usize];
+            if can_capture_en_passant(board, square) {
+                hash ^= self.en_passant_target_key[square as usize];
+            }
         }
 
         // Hash active color
@@ -91,10 +116,75 @@ impl ZobristTable {
     }
 }
 
+/// Checks if the side to move has a pawn attacking the en passant target
+fn can_capture_en_passant(board: &Board, target: Square) -> bool {
+    let color = board.active_color();
+    let north = if color == Color::White { NORTH } else { SOUTH };
+
+    let pawns = board.bb(color, Piece::Pawn);
+    let attacks = pawns.shift(north + WEST) | pawns.shift(north + EAST);
+
+    attacks & Bitboard::square_to_bitboard(target) != 0
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
     use crate::board::Board;
-    use crate::zobrist::ZobristTable;
+    use crate::move_gen::MoveGenerator;
+    use crate::moves::Move;
+    use crate::pieces::{ColorIterator, PieceIterator};
+    use crate::zobrist::{can_capture_en_passant, ZobristTable, DEFAULT_SEED};
+
+    /// Everything the hash is meant to distinguish, used to tell real collisions
+    /// apart from positions that should hash the same
+    type PositionKey = (Vec<u64>, bool, Vec<(bool, bool)>, Option<u8>);
+
+    fn position_key(board: &Board) -> PositionKey {
+        let pieces = ColorIterator::new()
+            .flat_map(|color| PieceIterator::new().map(move |piece| board.bb(color, piece)))
+            .collect();
+        let castling = ColorIterator::new().map(|color| board.castling_ability(color)).collect();
+        let en_passant = board
+            .en_passant_target
+            .filter(|&square| can_capture_en_passant(board, square));
+
+        (pieces, board.active_color().index() == 0, castling, en_passant)
+    }
+
+    /// Plays random legal moves from the starting position, calling `check` with
+    /// the position before each move, the move, and the resulting position
+    fn walk_random_game(seed: u64, plies: usize, mut check: impl FnMut(&Board, &Move, &Board)) {
+        let move_gen = MoveGenerator::new();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut board = Board::default();
+
+        for _ in 0..plies {
+            let moves = move_gen.generate_moves(&board);
+            if moves.is_empty() {
+                break;
+            }
+
+            let mv = moves[rng.gen_range(0..moves.len())];
+            let next = board.clone_with_move(&mv);
+            check(&board, &mv, &next);
+            board = next;
+        }
+    }
+
+    #[test]
+    fn default_seed_gives_known_start_position_hash() {
+        let hash = ZobristTable::new().hash(&Board::default());
+
+        assert_eq!(hash, ZobristTable::with_seed(DEFAULT_SEED).hash(&Board::default()));
+        // Changes if the seed, the order keys are drawn in or the RNG changes
+        assert_eq!(hash, 0xdf2b_6c2b_1eae_8eef);
+        assert_ne!(hash, ZobristTable::with_seed(DEFAULT_SEED + 1).hash(&Board::default()));
+    }
 
     #[test]
     fn same_positions_have_same_hash() {
@@ -111,7 +201,7 @@ mod tests {
         let zobrist = ZobristTable::new();
 
         let pos = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
-        let pos_different = Board::new("pnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let pos_different = Board::new("qnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
 
         assert_ne!(zobrist.hash(&pos), zobrist.hash(&pos_different));
     }
@@ -130,9 +220,32 @@ mod tests {
     fn different_en_passant_targets_have_different_hash() {
         let zobrist = ZobristTable::new();
 
-        let pos = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        // The pawn on e5 can capture d6 en passant
+        let pos = Board::new("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3");
+        let pos_with_en_passant =
+            Board::new("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3");
+        let pos_with_other_en_passant =
+            Board::new("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3");
+
+        assert_ne!(zobrist.hash(&pos), zobrist.hash(&pos_with_en_passant));
+        assert_ne!(zobrist.hash(&pos_with_en_passant), zobrist.hash(&pos_with_other_en_passant));
+    }
+
+    #[test]
+    fn uncapturable_en_passant_target_is_not_hashed() {
+        let zobrist = ZobristTable::new();
+
+        // No white pawn is next to e5, so e6 can't be captured
+        let pos = Board::new("rnbqkbnr/pppp1ppp/8/4p3/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 2");
+        let pos_with_en_passant =
+            Board::new("rnbqkbnr/pppp1ppp/8/4p3/8/8/PPPPPPPP/RNBQKBNR w KQkq e6 0 2");
+
+        assert_eq!(zobrist.hash(&pos), zobrist.hash(&pos_with_en_passant));
+
+        // Black to move with a pawn on d4 can capture e3
+        let pos = Board::new("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 3");
         let pos_with_en_passant =
-            Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - e4 0 1");
+            Board::new("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3");
 
         assert_ne!(zobrist.hash(&pos), zobrist.hash(&pos_with_en_passant));
     }
@@ -147,5 +260,62 @@ mod tests {
 
         assert_ne!(zobrist.hash(&pos), zobrist.hash(&pos_different_color));
     }
-}
 
+    #[test]
+    fn making_a_move_leaves_the_original_hash_unchanged() {
+        let zobrist = ZobristTable::new();
+
+        for seed in 0..10 {
+            walk_random_game(seed, 200, |before, mv, after| {
+                let hash_before = zobrist.hash(before);
+                let copy = before.clone_with_move(mv);
+
+                // Discarding the copy is how a move is unmade
+                assert_eq!(zobrist.hash(before), hash_before);
+                assert_eq!(zobrist.hash(&copy), zobrist.hash(after));
+                assert_ne!(zobrist.hash(after), hash_before);
+            });
+        }
+    }
+
+    #[test]
+    fn random_games_have_no_hash_collisions() {
+        let zobrist = ZobristTable::new();
+        let mut seen: HashMap<u64, PositionKey> = HashMap::new();
+        let mut positions = 0;
+
+        for seed in 0..50 {
+            walk_random_game(seed, 100, |_, _, after| {
+                let key = position_key(after);
+                let hash = zobrist.hash(after);
+
+                if let Some(existing) = seen.get(&hash) {
+                    assert_eq!(existing, &key, "Distinct positions share hash {:#x}", hash);
+                }
+
+                seen.insert(hash, key);
+                positions += 1;
+            });
+        }
+
+        assert!(positions > 2000, "Only {} positions were generated", positions);
+    }
+
+    #[test]
+    fn transpositions_have_the_same_hash() {
+        let zobrist = ZobristTable::new();
+        let play = |moves: &[&str]| {
+            let mut board = Board::default();
+            for alg in moves {
+                let mv = Move::from_algebraic(alg, &board).unwrap();
+                board.make_move(&mv);
+            }
+            board
+        };
+
+        let pos = play(&["g1f3", "g8f6", "b1c3", "b8c6"]);
+        let transposed = play(&["b1c3", "b8c6", "g1f3", "g8f6"]);
+
+        assert_eq!(zobrist.hash(&pos), zobrist.hash(&transposed));
+    }
+}